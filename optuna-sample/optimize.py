@@ -77,6 +77,10 @@ class Objective:
         for line in process.stdout:
             result = json.loads(line)
 
+            # Skip the summary record printed at the end
+            if result["type"] != "case":
+                continue
+
             # If an error occurs, stop the process and raise an exception
             if result["error_message"] != "":
                 process.send_signal(subprocess.signal.SIGINT)
@@ -1,39 +1,128 @@
-use anyhow::Result;
-use std::process::{Command, Output};
+use crate::settings::{Git, GitMode, SETTING_FILE_PATH};
+use anyhow::{bail, ensure, Context as _, Result};
+use chrono::Local;
+use clap::Args;
+use colored::Colorize as _;
+use serde::Deserialize;
+use std::{
+    path::Path,
+    process::{Command, Output},
+    time::Duration,
+};
 
-/// 現在の変更をコミットした上でタグ付けし、タグ名を返す
-pub(super) fn commit(tag_name: Option<String>) -> Result<String> {
-    // gitリポジトリが存在しない場合は初期化
-    if !is_git_repository()? {
-        println!("Initializing git repository to tag the current changes...");
-        git_init()?;
-        git_add_all()?;
-        git_commit("Initial commit")?;
-    }
+/// コミットメッセージ・タグメッセージのデフォルト値
+const DEFAULT_MESSAGE: &str = "automatically generated by pahcer";
+/// タグのプッシュ先のリモート名のデフォルト値
+const DEFAULT_REMOTE: &str = "origin";
+/// 一時的なエラーで失敗したgitコマンドの最大試行回数
+const MAX_ATTEMPTS: usize = 3;
+/// 再試行までの最初の待機時間
+const INITIAL_RETRY_BACKOFF: Duration = Duration::from_millis(100);
+/// 一時的なエラーとみなすgitのエラーメッセージ
+const TRANSIENT_ERROR_PATTERNS: &[&str] = &[
+    "index.lock",
+    "cannot lock ref",
+    "Unable to create",
+    "Another git process seems to be running",
+];
 
-    git_add_all()?;
-    let has_diff = git_diff()?;
+/// `commit` で付けたタグ
+#[derive(Debug, Clone)]
+pub(super) struct Tag {
+    pub(super) name: String,
+    /// 新たに作成したか（同名のタグが既に存在した場合は `false` ）
+    pub(super) created: bool,
+}
 
-    if has_diff {
-        git_commit("automatically generated by pahcer")?;
-    }
+/// 現在の変更をコミットした上でタグ付けし、付けたタグを返す
+/// `GitMode::TagOnly` の場合はコミットせず、現在のHEADにタグを付ける
+/// `target_commit` が指定された場合は、作業ツリーに関わらずそのコミットにタグを付ける
+pub(super) fn commit(
+    tag_name: Option<String>,
+    settings: &Git,
+    comment: &str,
+    push: bool,
+    target_commit: Option<&str>,
+) -> Result<Tag> {
+    ensure!(
+        is_git_repository()?,
+        "Tagging requires an existing git repository."
+    );
+
+    let date = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    let has_diff = match (target_commit, settings.mode) {
+        (Some(_), _) => false,
+        (None, GitMode::Full) => commit_changes(tag_name.as_deref(), settings, comment, &date)?,
+        (None, GitMode::TagOnly) => {
+            if has_uncommitted_changes()? {
+                let message = "Warning: The uncommitted changes are not included in the tag because git.mode is TagOnly.";
+                eprintln!("{}", message.yellow());
+            }
+
+            false
+        }
+    };
 
-    let tag_name = generate_tag_name(tag_name)?;
-    git_tag(&tag_name)?;
+    let target_commit = target_commit.unwrap_or("HEAD");
+    let tagged = (|| -> Result<(String, bool)> {
+        let tag_name = generate_tag_name(tag_name, settings, target_commit)?;
+        let message =
+            render_tag_message(settings.tag_message.as_deref(), &tag_name, comment, &date);
+        let created = git_tag(&tag_name, &message, target_commit)?;
+        Ok((tag_name, created))
+    })();
 
+    // タグ付けに失敗した場合も、一時的なコミットはブランチに残さず取り消す
     if has_diff {
         git_reset()?;
     }
 
-    Ok(tag_name)
+    let (tag_name, created) = tagged?;
+
+    if push {
+        push_tag(&tag_name, settings);
+    }
+
+    Ok(Tag {
+        name: tag_name,
+        created,
+    })
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct PruneArgs {
+    /// Print the tags to be deleted without deleting them
+    #[clap(long = "dry-run")]
+    dry_run: bool,
+    /// Number of the most recent tags to keep
+    #[clap(long = "keep", default_value = "0")]
+    keep: usize,
+    /// Path to the setting file
+    #[clap(long = "setting-file", default_value = SETTING_FILE_PATH)]
+    setting_file: String,
+}
+
+/// 設定ファイルのうち、タグの削除に必要な `[git]` セクションのみ
+#[derive(Debug, Default, Deserialize)]
+struct GitSection {
+    #[serde(default)]
+    git: Git,
 }
 
 /// pahcer関連のタグを削除する
-pub(super) fn prune_tags() -> Result<()> {
-    let tags = list_tags("pahcer/*")?;
+pub fn prune_tags(args: &PruneArgs) -> Result<()> {
+    let settings = load_git_settings(&args.setting_file)?;
+
+    // 新しい順に並んでいるため、先頭から `keep` 個を残す
+    let tags = list_tags(&format!("{}*", settings.tag_prefix()))?;
+
+    for tag in tags.iter().skip(args.keep) {
+        if args.dry_run {
+            println!("Would delete tag: {tag}");
+            continue;
+        }
 
-    for tag in tags.iter() {
-        check_return_code(Command::new("git").args(["tag", "-d", tag]).output()?)?;
+        delete_tag(tag)?;
 
         println!("Deleted tag: {tag}");
     }
@@ -41,108 +130,225 @@ pub(super) fn prune_tags() -> Result<()> {
     Ok(())
 }
 
+/// 設定ファイルから `[git]` セクションを読み込む
+/// 設定ファイルが存在しない場合はデフォルトの設定とする
+fn load_git_settings(path: &str) -> Result<Git> {
+    let Ok(settings_str) = std::fs::read_to_string(path) else {
+        return Ok(Git::default());
+    };
+
+    let section: GitSection = toml::from_str(&settings_str)
+        .with_context(|| format!("Failed to load the setting file {path}."))?;
+
+    // 空のプレフィックスでは全てのタグが削除対象となってしまう
+    ensure!(
+        !section.git.tag_prefix().is_empty(),
+        "git.tag_prefix must not be empty."
+    );
+
+    Ok(section.git)
+}
+
+/// 未コミットの変更をコミットし、変更があったかどうかを返す
+fn commit_changes(
+    tag_name: Option<&str>,
+    settings: &Git,
+    comment: &str,
+    date: &str,
+) -> Result<bool> {
+    git_add_all()?;
+    let has_diff = git_diff()?;
+
+    if has_diff {
+        // タグ名を省略した場合はコミット後のハッシュから決まるため、コミットメッセージでは空文字列とする
+        let tag = tag_name
+            .map(|name| settings.prefixed_tag(name))
+            .unwrap_or_default();
+        let message = render_message(settings.commit_message.as_deref(), &tag, comment, date);
+        git_commit(&message)?;
+    }
+
+    Ok(has_diff)
+}
+
+/// タグをリモートへプッシュする
+/// プッシュに失敗してもテストは実行できるため、警告を表示するに留める
+pub(super) fn push_tag(tag_name: &str, settings: &Git) {
+    let remote = settings.remote.as_deref().unwrap_or(DEFAULT_REMOTE);
+
+    if let Err(e) = git_push_tag(remote, tag_name) {
+        let message = format!("Warning: Failed to push the tag {tag_name} to {remote}: {e}");
+        eprintln!("{}", message.trim_end().yellow());
+    }
+}
+
+/// タグを削除する
+pub(super) fn delete_tag(tag_name: &str) -> Result<()> {
+    check_return_code(Command::new("git").args(["tag", "-d", tag_name]))
+}
+
+/// タグ名を解決する（プレフィックスは省略可能）
+pub(super) fn resolve_tag(tag: &str, settings: &Git) -> Result<String> {
+    let prefixed_tag = settings.prefixed_tag(tag);
+
+    for candidate in [prefixed_tag.as_str(), tag] {
+        if tag_exists(candidate)? {
+            return Ok(candidate.to_string());
+        }
+    }
+
+    bail!("Tag not found: {tag}")
+}
+
+/// 指定されたタグのコミットを作業ツリーに影響を与えずに別ディレクトリへチェックアウトする
+pub(super) fn add_worktree(path: &Path, tag_name: &str) -> Result<()> {
+    check_return_code(
+        Command::new("git")
+            .args(["worktree", "add", "--detach"])
+            .arg(path)
+            .arg(tag_name),
+    )
+}
+
+/// `add_worktree` で作成したディレクトリを削除する
+pub(super) fn remove_worktree(path: &Path) -> Result<()> {
+    check_return_code(
+        Command::new("git")
+            .args(["worktree", "remove", "--force"])
+            .arg(path),
+    )
+}
+
+/// メッセージのテンプレートのプレースホルダを置換する
+fn render_message(template: Option<&str>, tag_name: &str, comment: &str, date: &str) -> String {
+    let Some(template) = template else {
+        return DEFAULT_MESSAGE.to_string();
+    };
+
+    template
+        .replace("{TAG}", tag_name)
+        .replace("{COMMENT}", comment)
+        .replace("{DATE}", date)
+}
+
+/// タグの注釈メッセージを生成する
+/// テンプレートが未指定の場合は、 `git tag -n` などで確認できるようにコメントをそのままメッセージとする
+fn render_tag_message(template: Option<&str>, tag_name: &str, comment: &str, date: &str) -> String {
+    if template.is_none() && !comment.trim().is_empty() {
+        return comment.to_string();
+    }
+
+    render_message(template, tag_name, comment, date)
+}
+
 /// タグ名を生成する
-fn generate_tag_name(tag_name: Option<String>) -> Result<String> {
+fn generate_tag_name(tag_name: Option<String>, settings: &Git, commit: &str) -> Result<String> {
     let tag_suffix = match tag_name {
         Some(name) => name,
         None => {
-            // 現在のコミットハッシュの8桁を取得
-            get_current_commit_hash()?
+            // タグを付けるコミットのハッシュの8桁を取得
+            get_commit_hash(commit)?
         }
     };
 
-    Ok(format!("pahcer/{tag_suffix}"))
+    Ok(settings.prefixed_tag(&tag_suffix))
 }
 
-/// 現在のコミットハッシュの最初の8桁を取得する
-fn get_current_commit_hash() -> Result<String> {
-    let output = Command::new("git")
-        .args(["rev-parse", "--short=8", "HEAD"])
-        .output()?;
-
-    let hash = read_stdout(output)?;
+/// 指定されたコミットのハッシュの最初の8桁を取得する
+fn get_commit_hash(commit: &str) -> Result<String> {
+    let hash = read_stdout(Command::new("git").args(["rev-parse", "--short=8", commit]))?;
     Ok(hash.trim().to_string())
 }
 
+/// 指定されたコミットが存在することを確認する
+pub(super) fn verify_commit(commit: &str) -> Result<()> {
+    read_stdout(Command::new("git").args([
+        "rev-parse",
+        "--verify",
+        "--quiet",
+        &format!("{commit}^{{commit}}"),
+    ]))
+    .with_context(|| format!("Commit not found: {commit}"))?;
+
+    Ok(())
+}
+
+/// 現在のブランチ名を取得する
+/// detached HEADの場合は `None` を返す
+pub(super) fn get_current_branch_name() -> Result<Option<String>> {
+    let branch = read_stdout(Command::new("git").args(["rev-parse", "--abbrev-ref", "HEAD"]))?
+        .trim()
+        .to_string();
+    Ok((branch != "HEAD").then_some(branch))
+}
+
 /// タグを生成する
-fn git_tag(tag_name: &str) -> Result<()> {
+/// タグを作成し、新たに作成したかを返す
+fn git_tag(tag_name: &str, message: &str, commit: &str) -> Result<bool> {
     // 既に同じタグが存在する場合は何もしない
     if tag_exists(tag_name)? {
         println!("Tag already exists: {tag_name}. Skipping tag creation.");
-        return Ok(());
+        return Ok(false);
     }
 
-    check_return_code(
-        Command::new("git")
-            .args([
-                "tag",
-                "-a",
-                tag_name,
-                "-m",
-                "automatically generated by pahcer",
-            ])
-            .output()?,
-    )
+    check_return_code(Command::new("git").args(["tag", "-a", tag_name, "-m", message, commit]))?;
+
+    Ok(true)
+}
+
+/// タグをリモートにプッシュする
+fn git_push_tag(remote: &str, tag_name: &str) -> Result<()> {
+    check_return_code(Command::new("git").args(["push", remote, tag_name]))
 }
 
 /// 指定されたタグが存在するかどうかをチェックする
 fn tag_exists(tag_name: &str) -> Result<bool> {
-    let output = Command::new("git")
-        .args(["tag", "--list", tag_name])
-        .output()?;
-
-    let tags = read_stdout(output)?;
+    let tags = read_stdout(Command::new("git").args(["tag", "--list", tag_name]))?;
     Ok(!tags.trim().is_empty())
 }
 
 /// 直前のコミットを取り消す
 fn git_reset() -> Result<()> {
-    check_return_code(
-        Command::new("git")
-            .args(["reset", "--mixed", "HEAD^"])
-            .output()?,
-    )
+    check_return_code(Command::new("git").args(["reset", "--mixed", "HEAD^"]))
 }
 
 /// 全てのファイルをステージングする
 fn git_add_all() -> Result<()> {
-    check_return_code(Command::new("git").args(["add", "--all"]).output()?)?;
+    check_return_code(Command::new("git").args(["add", "--all"]))?;
     Ok(())
 }
 
+/// 作業ツリーに未コミットの変更（未追跡のファイルを含む）があるかどうかを判定する
+fn has_uncommitted_changes() -> Result<bool> {
+    let status = read_stdout(Command::new("git").args(["status", "--porcelain"]))?;
+
+    Ok(!status.is_empty())
+}
+
 /// 変更があるかどうかを判定する
 fn git_diff() -> Result<bool> {
-    let diffs = read_stdout(
-        Command::new("git")
-            .args(["diff", "--cached", "--name-only"])
-            .output()?,
-    )?;
+    let diffs = read_stdout(Command::new("git").args(["diff", "--cached", "--name-only"]))?;
 
     Ok(!diffs.is_empty())
 }
 
 /// 変更をコミットする
 fn git_commit(message: &str) -> Result<()> {
-    check_return_code(
-        Command::new("git")
-            .args(["commit", "-m", message])
-            .output()?,
-    )
+    check_return_code(Command::new("git").args(["commit", "-m", message]))
 }
 
-/// タグ名のリストを取得する
+/// タグ名のリストを作成日時の新しい順に取得する
 fn list_tags(pattern: &str) -> Result<Vec<String>, anyhow::Error> {
-    let tags = read_stdout(
-        Command::new("git")
-            .args(["tag", "--list", pattern])
-            .output()?,
-    )?;
+    let tags =
+        read_stdout(Command::new("git").args(["tag", "--list", "--sort=-creatordate", pattern]))?;
 
     Ok(tags.lines().map(|s| s.to_string()).collect())
 }
 
 /// コマンドの実行結果を文字列として取得する
-fn read_stdout(output: Output) -> Result<String> {
+fn read_stdout(command: &mut Command) -> Result<String> {
+    let output = output_with_retry(command)?;
+
     if output.status.success() {
         let stdout = String::from_utf8_lossy(&output.stdout).to_string();
         Ok(stdout)
@@ -153,7 +359,9 @@ fn read_stdout(output: Output) -> Result<String> {
 }
 
 /// コマンドが正常終了したかどうかをチェックする
-fn check_return_code(output: Output) -> Result<()> {
+fn check_return_code(command: &mut Command) -> Result<()> {
+    let output = output_with_retry(command)?;
+
     if output.status.success() {
         Ok(())
     } else {
@@ -162,6 +370,33 @@ fn check_return_code(output: Output) -> Result<()> {
     }
 }
 
+/// コマンドを実行する
+/// ロックファイルの競合など一時的なエラーで失敗した場合は、待機時間を倍にしながら再試行する
+fn output_with_retry(command: &mut Command) -> Result<Output> {
+    let mut backoff = INITIAL_RETRY_BACKOFF;
+
+    for _ in 1..MAX_ATTEMPTS {
+        let output = command.output()?;
+
+        if output.status.success() || !is_transient_error(&String::from_utf8_lossy(&output.stderr))
+        {
+            return Ok(output);
+        }
+
+        std::thread::sleep(backoff);
+        backoff *= 2;
+    }
+
+    Ok(command.output()?)
+}
+
+/// 再試行により解消する可能性のある一時的なエラーかどうかを判定する
+fn is_transient_error(stderr: &str) -> bool {
+    TRANSIENT_ERROR_PATTERNS
+        .iter()
+        .any(|pattern| stderr.contains(pattern))
+}
+
 /// gitリポジトリが存在するかどうかをチェックする
 fn is_git_repository() -> Result<bool> {
     let output = Command::new("git")
@@ -171,7 +406,257 @@ fn is_git_repository() -> Result<bool> {
     Ok(output.status.success())
 }
 
-/// gitリポジトリを初期化する
-fn git_init() -> Result<()> {
-    check_return_code(Command::new("git").args(["init"]).output()?)
+/// タグ付けできない場合にその理由を返す
+/// gitがインストールされていない場合と、カレントディレクトリがgitリポジトリ内にない場合が該当する
+pub(super) fn unavailable_reason() -> Option<&'static str> {
+    if Command::new("git").arg("--version").output().is_err() {
+        return Some("git is not installed");
+    }
+
+    match is_git_repository() {
+        Ok(true) => None,
+        _ => Some("The current directory is not a git repository"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::util::CurrentDirGuard;
+    use std::{
+        path::PathBuf,
+        sync::{Mutex, MutexGuard, PoisonError},
+    };
+
+    /// カレントディレクトリを変更するテストが同時に実行されないようにするためのロック
+    static CURRENT_DIR_LOCK: Mutex<()> = Mutex::new(());
+
+    /// カレントディレクトリを変更している間、ロックを保持する
+    /// パニックした場合も含め、破棄時にカレントディレクトリを戻してからロックを解放する（フィールドの宣言順に注意）
+    struct CurrentDirScope {
+        _current_dir: CurrentDirGuard,
+        _lock: MutexGuard<'static, ()>,
+    }
+
+    /// カレントディレクトリを `dir` に変更する
+    /// 他のテストのアサーションの失敗でロックが汚染されていても続行する
+    fn enter_dir(dir: &Path) -> CurrentDirScope {
+        let lock = CURRENT_DIR_LOCK
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        CurrentDirScope {
+            _current_dir: CurrentDirGuard::new(dir).unwrap(),
+            _lock: lock,
+        }
+    }
+
+    /// 一時ディレクトリに空のコミットを1つ持つリポジトリを作成する
+    fn init_repository(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("pahcer_{name}_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        for args in [
+            &["init"][..],
+            &["config", "user.name", "pahcer"],
+            &["config", "user.email", "pahcer@example.com"],
+            &["commit", "--allow-empty", "-m", "Initial commit"],
+        ] {
+            run_git(&dir, args);
+        }
+
+        dir
+    }
+
+    fn run_git(dir: &Path, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "git {args:?} failed");
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    }
+
+    #[test]
+    fn test_render_message() {
+        assert_eq!(
+            render_message(None, "pahcer/foo", "bar", "2024-01-01 00:00:00"),
+            DEFAULT_MESSAGE
+        );
+        assert_eq!(
+            render_message(
+                Some("{TAG}: {COMMENT} ({DATE})"),
+                "pahcer/foo",
+                "bar",
+                "2024-01-01 00:00:00"
+            ),
+            "pahcer/foo: bar (2024-01-01 00:00:00)"
+        );
+    }
+
+    #[test]
+    fn test_render_tag_message() {
+        assert_eq!(
+            render_tag_message(None, "pahcer/foo", "try beam search", "2024-01-01 00:00:00"),
+            "try beam search"
+        );
+        assert_eq!(
+            render_tag_message(None, "pahcer/foo", "  ", "2024-01-01 00:00:00"),
+            DEFAULT_MESSAGE
+        );
+        assert_eq!(
+            render_tag_message(
+                Some("{COMMENT} ({DATE})"),
+                "pahcer/foo",
+                "try beam search",
+                "2024-01-01 00:00:00"
+            ),
+            "try beam search (2024-01-01 00:00:00)"
+        );
+    }
+
+    #[test]
+    fn test_is_transient_error() {
+        assert!(is_transient_error(
+            "fatal: Unable to create '/tmp/repo/.git/index.lock': File exists."
+        ));
+        assert!(is_transient_error(
+            "error: cannot lock ref 'refs/tags/pahcer/foo': Unable to create lock"
+        ));
+        assert!(!is_transient_error(
+            "fatal: tag 'pahcer/foo' already exists"
+        ));
+        assert!(!is_transient_error("fatal: not a git repository"));
+    }
+
+    #[test]
+    fn test_prune_tags() {
+        let dir = init_repository("git");
+        run_git(&dir, &["tag", "-a", "manual", "-m", "manual"]);
+        run_git(&dir, &["tag", "-a", "pahcer/old", "-m", "old"]);
+        std::fs::write(
+            dir.join(SETTING_FILE_PATH),
+            "[git]\ntag_prefix = \"exp/alice/\"\n",
+        )
+        .unwrap();
+
+        // タグの作成・削除はカレントディレクトリのリポジトリに対して行われる
+        let current_dir = enter_dir(&dir);
+        let settings = load_git_settings(SETTING_FILE_PATH).unwrap();
+        let tag = commit(Some("test".to_string()), &settings, "", false, None).unwrap();
+        assert_eq!(tag.name, "exp/alice/test");
+        assert!(tag.created);
+        assert_eq!(resolve_tag("test", &settings).unwrap(), "exp/alice/test");
+        let created = list_tags("exp/alice/*").unwrap();
+
+        let args = PruneArgs {
+            dry_run: false,
+            keep: 0,
+            setting_file: SETTING_FILE_PATH.to_string(),
+        };
+        prune_tags(&args).unwrap();
+        let mut remaining = list_tags("*").unwrap();
+        drop(current_dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        remaining.sort();
+        assert_eq!(created, vec!["exp/alice/test"]);
+        assert_eq!(remaining, vec!["manual", "pahcer/old"]);
+    }
+
+    #[test]
+    fn test_commit_tag_only() {
+        let dir = init_repository("git_tag_only");
+        std::fs::write(dir.join("main.rs"), "fn main() {}").unwrap();
+        let head = run_git(&dir, &["rev-parse", "HEAD"]);
+
+        let current_dir = enter_dir(&dir);
+        let settings = Git {
+            mode: GitMode::TagOnly,
+            ..Default::default()
+        };
+        let result = commit(Some("test".to_string()), &settings, "", false, None);
+        drop(current_dir);
+
+        let tag = result.unwrap();
+        let tagged = run_git(&dir, &["rev-parse", "pahcer/test^{commit}"]);
+        let new_head = run_git(&dir, &["rev-parse", "HEAD"]);
+        let status = run_git(&dir, &["status", "--porcelain"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(tag.created);
+        assert_eq!(tagged, head);
+        assert_eq!(new_head, head);
+        assert_eq!(status, "?? main.rs");
+    }
+
+    #[test]
+    fn test_commit_target_commit() {
+        let dir = init_repository("git_target_commit");
+        let first = run_git(&dir, &["rev-parse", "HEAD"]);
+        run_git(&dir, &["commit", "--allow-empty", "-m", "Second commit"]);
+        let head = run_git(&dir, &["rev-parse", "HEAD"]);
+        std::fs::write(dir.join("main.rs"), "fn main() {}").unwrap();
+
+        let current_dir = enter_dir(&dir);
+        let settings = Git::default();
+        let verified = verify_commit(&first[..8]);
+        let missing = verify_commit("0123456789abcdef");
+        let result = commit(None, &settings, "", false, Some(&first));
+        drop(current_dir);
+
+        let tag = result.unwrap();
+        let tagged = run_git(&dir, &["rev-parse", &format!("{}^{{commit}}", tag.name)]);
+        let new_head = run_git(&dir, &["rev-parse", "HEAD"]);
+        let status = run_git(&dir, &["status", "--porcelain"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(verified.is_ok());
+        assert!(missing.is_err());
+        assert_eq!(tag.name, format!("pahcer/{}", &first[..8]));
+        assert_eq!(tagged, first);
+        assert_eq!(new_head, head);
+        assert_eq!(status, "?? main.rs");
+    }
+
+    #[test]
+    fn test_commit_reset_on_failure() {
+        let dir = init_repository("git_reset_on_failure");
+        std::fs::write(dir.join("main.rs"), "fn main() {}").unwrap();
+        let head = run_git(&dir, &["rev-parse", "HEAD"]);
+
+        // 不正なタグ名でタグ付けに失敗しても、一時的なコミットは取り消される
+        let current_dir = enter_dir(&dir);
+        let result = commit(
+            Some("bad..name".to_string()),
+            &Git::default(),
+            "",
+            false,
+            None,
+        );
+        drop(current_dir);
+
+        let new_head = run_git(&dir, &["rev-parse", "HEAD"]);
+        let status = run_git(&dir, &["status", "--porcelain"]);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(result.is_err());
+        assert_eq!(new_head, head);
+        assert_eq!(status, "?? main.rs");
+    }
+
+    #[test]
+    fn test_unavailable_reason() {
+        let dir = std::env::temp_dir().join(format!("pahcer_not_repo_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let current_dir = enter_dir(&dir);
+        let reason = unavailable_reason();
+        drop(current_dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(reason.is_some());
+    }
 }
@@ -0,0 +1,24 @@
+//! A tool to run local tests for heuristic contests such as AtCoder Heuristic Contest.
+//!
+//! Besides the `pahcer` command, the test runner can be driven from Rust code, e.g. from a parameter tuner.
+//!
+//! ```no_run
+//! use pahcer::{load_settings, run_with_settings, RunOptions};
+//!
+//! let settings = load_settings("pahcer_config.toml")?;
+//! let options = RunOptions {
+//!     seeds: Some((0..10).collect()),
+//!     ..Default::default()
+//! };
+//! let stats = run_with_settings(settings, options)?;
+//! println!("{}", stats.average_relative_score());
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+pub mod git;
+pub mod runner;
+pub mod settings;
+pub(crate) mod util;
+
+pub use runner::{load_settings, run_with_settings, RunOptions, TestResult, TestStats};
+pub use settings::Settings;
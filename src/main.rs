@@ -1,17 +1,16 @@
-mod git;
-pub(crate) mod runner;
-pub(crate) mod settings;
-pub(crate) mod util;
-
 use anyhow::Result;
 use clap::{Parser, Subcommand};
 use colored::Colorize;
+use pahcer::{git, runner, settings};
 
 #[derive(Debug, Clone, Parser)]
 #[command(version, about)]
 struct Cli {
     #[command(subcommand)]
     command: Command,
+    /// Disable colored output (also disabled by the NO_COLOR environment variable)
+    #[clap(long = "no-color", global = true)]
+    no_color: bool,
 }
 
 #[derive(Debug, Clone, Subcommand)]
@@ -20,18 +19,44 @@ enum Command {
     Init(settings::InitArgs),
     /// Run tests
     Run(runner::RunArgs),
+    /// Re-run tests whenever the watched files change
+    Watch(runner::WatchArgs),
     /// List past test results
     List(runner::ListArgs),
-    /// Remove all pahcer-related tags
-    Prune,
+    /// Compare scores of two past test results seed by seed
+    Diff(runner::DiffArgs),
+    /// Show the details of a past test result
+    Stats(runner::StatsArgs),
+    /// Re-test the code of a past tag without touching the working tree
+    Rerun(runner::RerunArgs),
+    /// Show the history of the best score of a seed
+    History(runner::HistoryArgs),
+    /// Copy a past test result and its input/output files into a directory
+    Export(runner::ExportArgs),
+    /// Remove pahcer-related tags
+    Prune(git::PruneArgs),
 }
 
 fn main() {
     let args = Cli::parse();
 
+    // https://no-color.org/ に従い、空でないNO_COLORが設定されている場合も色付けを無効化する
+    let no_color_env = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+
+    if args.no_color || no_color_env {
+        colored::control::set_override(false);
+    }
+
     if let Err(e) = run_command(args) {
         eprintln!("{}", format!("Error: {e:?}").yellow().bold());
-        std::process::exit(1);
+
+        // Ctrl-Cによる中断の場合は慣例に従い130を返す
+        let code = if e.is::<runner::Interrupted>() {
+            130
+        } else {
+            1
+        };
+        std::process::exit(code);
     }
 }
 
@@ -43,10 +68,28 @@ fn run_command(args: Cli) -> Result<(), anyhow::Error> {
         Command::Run(args) => {
             runner::run(args)?;
         }
+        Command::Watch(args) => {
+            runner::watch(args)?;
+        }
         Command::List(args) => {
             runner::list(args)?;
         }
-        Command::Prune => git::prune_tags()?,
+        Command::Diff(args) => {
+            runner::diff(args)?;
+        }
+        Command::Stats(args) => {
+            runner::stats(args)?;
+        }
+        Command::Rerun(args) => {
+            runner::rerun(args)?;
+        }
+        Command::History(args) => {
+            runner::history(args)?;
+        }
+        Command::Export(args) => {
+            runner::export(args)?;
+        }
+        Command::Prune(args) => git::prune_tags(&args)?,
     };
     Ok(())
 }
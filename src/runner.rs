@@ -1,33 +1,97 @@
 pub(crate) mod compilie;
+mod diff;
+mod export;
+mod history;
 mod io;
 mod list;
 mod multi;
+mod rerun;
 pub(crate) mod single;
+mod stats;
+mod watch;
+
+pub use multi::TestStats;
+pub use single::TestResult;
 
 use crate::{
     git,
     settings::{Settings, SETTING_FILE_PATH},
+    util::NumberFormat,
 };
 use anyhow::{ensure, Context, Result};
-use clap::Args;
+use chrono::NaiveDate;
+use clap::{Args, ValueEnum};
+use colored::Colorize as _;
 use compilie::compile;
 use rand::prelude::*;
 use regex::Regex;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    io::IsTerminal as _,
+    num::NonZeroUsize,
+    time::Duration,
+};
+
+/// The error returned when the run is interrupted by Ctrl-C.
+#[derive(Debug, Clone, Copy)]
+pub struct Interrupted;
+
+impl Display for Interrupted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Interrupted by the user")
+    }
+}
+
+impl std::error::Error for Interrupted {}
 
 #[derive(Debug, Clone, Args)]
-pub(crate) struct RunArgs {
+pub struct RunArgs {
     /// Shuffle the test cases
     #[clap(long = "shuffle")]
     shuffle: bool,
+    /// Shuffle the test cases deterministically with the given random seed (implies --shuffle)
+    #[clap(long = "shuffle-seed", value_name = "SEED")]
+    shuffle_seed: Option<u64>,
     /// Comment for the run
     #[clap(short = 'c', long = "comment", default_value = "")]
     comment: String,
+    /// Prepend the current git branch name to the comment
+    #[clap(long = "append-comment")]
+    append_comment: bool,
     /// Output the result in JSON format
     #[clap(short = 'j', long = "json")]
     json: bool,
+    /// Output the result file in CSV format as well
+    #[clap(long = "csv")]
+    csv: bool,
+    /// Output the result file in HTML format as well
+    #[clap(long = "html")]
+    html: bool,
+    /// Open the HTML report (or the summary file without --html) after the run
+    #[clap(long = "open")]
+    open: bool,
+    /// Compress the JSON result file with gzip
+    #[clap(long = "compress")]
+    compress: bool,
     /// Tag for the commit
     #[clap(short = 't', long = "tag", num_args = 0..=1, default_missing_value = "")]
     tag: Option<String>,
+    /// Tag the given commit instead of committing the working tree (requires --tag)
+    #[clap(long = "tag-commit", requires = "tag")]
+    tag_commit: Option<String>,
+    /// Push the created tag to the remote repository
+    #[clap(long = "push", requires = "tag")]
+    push: bool,
+    /// Keep the tag only if the average relative score is better than any past run
+    #[clap(long = "tag-if-best", requires = "tag")]
+    tag_if_best: bool,
+    /// Skip all git operations (tagging and getting the branch name) even if they are requested
+    #[clap(long = "no-git")]
+    no_git: bool,
+    /// Directory to output the results to (overrides `out_dir` in the setting file)
+    #[clap(long = "out-dir")]
+    out_dir: Option<String>,
     /// Path to the setting file
     #[clap(long = "setting-file", default_value = SETTING_FILE_PATH)]
     setting_file: String,
@@ -38,92 +102,924 @@ pub(crate) struct RunArgs {
     #[clap(long = "no-result-file")]
     no_result_file: bool,
     /// Do not compile the code
-    #[clap(long = "no-compile")]
+    #[clap(long = "no-compile", alias = "test-only")]
     no_compile: bool,
+    /// Only compile the code without running the tests
+    #[clap(long = "compile-only", conflicts_with_all = ["no_compile", "dry_run"])]
+    compile_only: bool,
+    /// Number of times to re-run failed cases
+    #[clap(long = "retry", default_value = "0")]
+    retry: usize,
+    /// Seeds to run (comma separated). Overrides the seed range in the setting file
+    #[clap(long = "seed", value_delimiter = ',')]
+    seeds: Vec<u64>,
+    /// Path to a file containing seeds to run (one seed per line). Overrides the seed range in the setting file
+    #[clap(long = "seeds-file")]
+    seeds_file: Option<String>,
+    /// Tag of a past run to compute the relative scores against, instead of the best scores
+    #[clap(long = "baseline")]
+    baseline: Option<String>,
+    /// Run only the seeds whose scores in the latest run are worse than in the given tag's run
+    #[clap(long = "regressed-only", conflicts_with_all = ["seeds", "seeds_file"])]
+    regressed_only: Option<String>,
+    /// Path to a CSV/TSV file of `seed,score` to compute the relative scores against, instead of the best scores
+    #[clap(long = "reference-file", conflicts_with = "baseline")]
+    reference_file: Option<String>,
+    /// Compute the relative scores against the best score of each seed across all the past runs in the JSON directory
+    #[clap(long = "reference-all-runs", conflicts_with_all = ["baseline", "reference_file"])]
+    reference_all_runs: bool,
+    /// Show a progress bar instead of the result table
+    #[clap(long = "progress", conflicts_with = "json")]
+    progress: bool,
+    /// Do not print the result of each case (nor the summary with --print-score)
+    #[clap(long = "quiet", conflicts_with_all = ["json", "progress"])]
+    quiet: bool,
+    /// Run only the seeds which are multiples of N
+    #[clap(long = "sample-every", value_parser = clap::value_parser!(u64).range(1..))]
+    sample_every: Option<u64>,
+    /// Run only K seeds picked evenly from the seeds to run
+    #[clap(long = "sample-count", value_parser = clap::value_parser!(u64).range(1..))]
+    sample_count: Option<u64>,
+    /// Stop running new cases as soon as any case fails
+    #[clap(long = "fail-fast")]
+    fail_fast: bool,
+    /// Stop running new cases once SECS seconds have elapsed, and finish with the completed cases
+    #[clap(long = "time-budget", value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..))]
+    time_budget: Option<u64>,
+    /// Exit successfully even if some cases fail
+    #[clap(long = "allow-failures")]
+    allow_failures: bool,
+    /// Print the results in order of completion instead of seed order
+    #[clap(long = "unordered")]
+    unordered: bool,
+    /// Number of threads to run the cases (overrides `threads` in the setting file, 0 for the number of physical CPUs)
+    #[clap(long = "jobs")]
+    jobs: Option<usize>,
+    /// Run the cases one by one so that the execution time reflects uncontended performance
+    #[clap(long = "timing-mode", conflicts_with = "jobs")]
+    timing_mode: bool,
+    /// Run the case of `start_seed` once before the test and discard its result
+    #[clap(long = "warmup")]
+    warmup: bool,
+    /// Number of the slowest cases to show at the end of the run (0 to disable)
+    #[clap(long = "show-slowest", default_value = "5")]
+    show_slowest: usize,
+    /// Print the expanded commands for the first seed without running anything
+    #[clap(long = "dry-run")]
+    dry_run: bool,
+    /// Print the given metric alone on the last line of stdout (for shell scripts)
+    #[clap(long = "print-score", num_args = 0..=1, default_missing_value = "relative")]
+    print_score: Option<ScoreMetric>,
+    /// Print a histogram of the relative scores after the summary
+    #[clap(long = "histogram")]
+    histogram: bool,
+    /// Run each case twice and report the seeds whose scores differ
+    #[clap(long = "verify-deterministic")]
+    verify_deterministic: bool,
+    /// Print the total and mean wall-clock time of each test step across the seeds
+    #[clap(long = "profile")]
+    profile: bool,
+    /// Value of a `{PARAM:name}` placeholder in the arguments of the test steps (can be repeated)
+    #[clap(long = "param", value_name = "NAME=VALUE", value_parser = parse_param)]
+    params: Vec<(String, String)>,
 }
 
-pub(crate) fn run(args: RunArgs) -> Result<()> {
-    let settings = io::load_setting_file(&args.setting_file)
+/// `--param` の値を `名前=値` の形式としてパースする
+fn parse_param(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((name, value)) if !name.is_empty() => Ok((name.to_string(), value.to_string())),
+        _ => Err(format!("expected NAME=VALUE, but got `{s}`")),
+    }
+}
+
+/// `--print-score` で出力する指標
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ScoreMetric {
+    /// Average relative score
+    Relative,
+    /// Average score
+    Score,
+    /// Average score in log10 scale
+    Log10,
+    /// Total score
+    Total,
+}
+
+impl ScoreMetric {
+    fn calc(self, stats: &multi::TestStats) -> f64 {
+        match self {
+            ScoreMetric::Relative => stats.average_relative_score(),
+            ScoreMetric::Score => stats.average_score(),
+            ScoreMetric::Log10 => stats.average_score_log10(),
+            ScoreMetric::Total => stats.score_sum,
+        }
+    }
+}
+
+pub fn run(args: RunArgs) -> Result<()> {
+    run_tests(args, &mut None)
+}
+
+/// Options of [`run_with_settings`].
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    /// Seeds to run. The seed range in the settings is used if `None`.
+    pub seeds: Option<Vec<u64>>,
+    /// Number of threads, which overrides `test.threads` in the settings
+    pub threads: Option<usize>,
+    /// Skip the compile steps
+    pub no_compile: bool,
+    /// Do not update the best scores
+    pub freeze_best_scores: bool,
+    /// Print the progress and the summary to the console as `pahcer run` does
+    pub verbose: bool,
+    /// Values of the `{PARAM:name}` placeholders in the arguments of the test steps
+    pub params: HashMap<String, String>,
+    /// Handle Ctrl-C (and 'q' when `verbose` is set) to finish with the completed cases as `pahcer run` does.
+    /// This installs a process-wide Ctrl-C handler, and [`TestStats::interrupted`] tells whether the run was cut short.
+    pub handle_interrupts: bool,
+}
+
+/// Loads the setting file (e.g. `pahcer_config.toml`).
+pub fn load_settings(path: impl AsRef<std::path::Path>) -> Result<Settings> {
+    let path = path.as_ref();
+    io::load_setting_file(path)
+        .with_context(|| format!("Failed to load the setting file {}.", path.display()))
+}
+
+/// Runs the tests with the given settings and returns the results.
+///
+/// The best scores are updated as `pahcer run` does unless [`RunOptions::freeze_best_scores`] is set,
+/// while no result files are written and no git tags are created.
+pub fn run_with_settings(mut settings: Settings, options: RunOptions) -> Result<TestStats> {
+    if let Some(threads) = options.threads {
+        settings.test.threads = threads;
+    }
+
+    settings.validate().context("Invalid settings.")?;
+    expand_params(&mut settings, &options.params)?;
+
+    let best_scores = load_best_scores(&settings)?;
+    let seeds = options
+        .seeds
+        .unwrap_or_else(|| (settings.test.start_seed..settings.test.end_seed).collect());
+    ensure!(!seeds.is_empty(), "No seeds to run.");
+
+    if !options.no_compile {
+        compile(&settings.test.compile_steps, &settings.test.out_dir)?;
+    }
+
+    let single_runner = build_single_runner(&settings)?;
+    let test_cases = build_test_cases(seeds, &best_scores, &settings);
+    let runner = if options.verbose {
+        multi::MultiCaseRunner::new_console(
+            single_runner,
+            test_cases,
+            settings.test.threads,
+            0,
+            true,
+            settings.display,
+        )
+    } else {
+        multi::MultiCaseRunner::new_quiet(
+            single_runner,
+            test_cases,
+            settings.test.threads,
+            0,
+            false,
+            settings.display,
+        )
+    };
+    let mut runner = runner
+        .with_interrupt_handling(options.handle_interrupts)
+        .with_weights(load_weights(&settings)?);
+    let stats = runner.run()?;
+
+    if stats.results.is_empty() {
+        return Err(Interrupted.into());
+    }
+
+    if !options.freeze_best_scores {
+        update_best_scores(&settings, &stats, &None)?;
+    }
+
+    Ok(stats)
+}
+
+/// テストを実行する
+/// 全ケースの実行を終えた場合、失敗したケースの有無に関わらず `finished_stats` に結果を格納する
+fn run_tests(args: RunArgs, finished_stats: &mut Option<multi::TestStats>) -> Result<()> {
+    let mut settings = io::load_setting_file(&args.setting_file)
         .with_context(|| format!("Failed to load the setting file {}.", &args.setting_file))?;
-    let best_score_path = io::get_best_score_path(&settings.test.out_dir);
-    let mut best_scores = io::load_best_scores(&best_score_path)?;
+
+    if let Some(out_dir) = &args.out_dir {
+        settings.test.out_dir = out_dir.clone();
+    }
+
+    if let Some(jobs) = args.jobs {
+        settings.test.threads = jobs;
+    }
+
+    // 他のケースとCPU・メモリ帯域を奪い合わないよう、1ケースずつ実行する
+    if args.timing_mode {
+        settings.test.threads = 1;
+    }
+
+    settings
+        .validate()
+        .with_context(|| format!("Invalid setting file {}.", &args.setting_file))?;
+
+    if args.compile_only {
+        compile(&settings.test.compile_steps, &settings.test.out_dir)?;
+        println!("Compiled successfully.");
+        return Ok(());
+    }
+
+    let params = args.params.iter().cloned().collect();
+    expand_params(&mut settings, &params)?;
+
+    let best_scores = load_best_scores(&settings)?;
+    let seeds = collect_seeds(&args, &settings)?;
+    let comment = if args.append_comment && !args.no_git {
+        match git::get_current_branch_name() {
+            Ok(Some(branch)) => prepend_branch_name(&args.comment, &branch),
+            Ok(None) => args.comment.clone(),
+            Err(e) => {
+                eprintln!("Warning: Failed to get the current git branch name: {e}");
+                args.comment.clone()
+            }
+        }
+    } else {
+        args.comment.clone()
+    };
+    let seeds = if args.sample_every.is_some() || args.sample_count.is_some() {
+        let seed_count = seeds.len();
+        let seeds = sample_seeds(seeds, args.sample_every, args.sample_count);
+        ensure!(!seeds.is_empty(), "No seeds are selected by the sampling.");
+        let message = format!("Selected {} / {} cases", seeds.len(), seed_count);
+
+        // JSON出力時は標準出力をJSONのみに保つ
+        if args.json {
+            eprintln!("{message}");
+        } else {
+            println!("{message}");
+        }
+
+        seeds
+    } else {
+        seeds
+    };
+    let reference_scores = match &args.baseline {
+        Some(tag) => io::load_baseline_scores(&settings.test.out_dir, tag, &settings.git)
+            .with_context(|| format!("Failed to load the baseline run {tag}."))?,
+        None => match &args.reference_file {
+            Some(path) => io::load_reference_scores(path)?,
+            None if args.reference_all_runs => io::load_all_runs_best_scores(
+                &settings.test.out_dir,
+                settings.problem.primary_objective(),
+            )
+            .context("Failed to load the past runs.")?,
+            None => best_scores.clone(),
+        },
+    };
+
+    let single_runner = build_single_runner(&settings)?;
+
+    if args.dry_run {
+        let seed = *seeds.first().context("No seeds to run.")?;
+        print_dry_run(&settings, &single_runner, seed, args.no_compile);
+        return Ok(());
+    }
+
+    // gitがインストールされていない環境やgitリポジトリ外では、タグ付けせずにテストのみ実行する
+    let can_tag = args.tag.is_some()
+        && !args.no_git
+        && match git::unavailable_reason() {
+            Some(reason) => {
+                let message = format!("Warning: {reason}. Running the tests without a tag.");
+                eprintln!("{}", message.yellow());
+                false
+            }
+            None => true,
+        };
+
+    // 存在しないコミットを指定した場合は、テストを実行する前にエラーとする
+    if let (Some(commit), true) = (&args.tag_commit, can_tag) {
+        git::verify_commit(commit)?;
+    }
 
     if !args.no_compile {
-        compile(&settings.test.compile_steps)?;
+        compile(&settings.test.compile_steps, &settings.test.out_dir)?;
     }
 
-    let tag_name = match args.tag {
-        Some(tag) => {
+    let push = args.push || settings.git.push_on_tag;
+    let tag = match args.tag {
+        Some(tag) if can_tag => {
             let tag = if tag.is_empty() { None } else { Some(tag) };
-            let tag = git::commit(tag).context("Failed to tag the current changes.")?;
-            println!("Tag: {tag}");
+            // `--tag-if-best` の場合はタグを残すと決まってからプッシュする
+            let push_now = push && !args.tag_if_best;
+
+            let tag = git::commit(
+                tag,
+                &settings.git,
+                &comment,
+                push_now,
+                args.tag_commit.as_deref(),
+            )?;
+            println!("Tag: {}", tag.name);
             Some(tag)
         }
-        None => None,
+        _ => None,
+    };
+
+    let mut test_cases = build_test_cases(seeds, &reference_scores, &settings);
+
+    // 乱数のシードが指定された場合は、同じ実行順序を再現できるよう決定的にシャッフルする
+    match args.shuffle_seed {
+        Some(seed) => test_cases.shuffle(&mut StdRng::seed_from_u64(seed)),
+        None if args.shuffle => test_cases.shuffle(&mut rand::rng()),
+        None => {}
+    }
+
+    // JITの初回実行の遅さなどが実行時間に影響しないよう、1ケース実行して結果を捨てる
+    // シャッフルや絞り込みの影響を受けないよう、常に `start_seed` のケースを用いる
+    if args.warmup {
+        let test_case = single::TestCase::new(
+            settings.test.start_seed,
+            None,
+            settings.problem.primary_objective(),
+        );
+        let message = format!("Warming up with seed {}...", test_case.seed());
+
+        if args.json {
+            eprintln!("{message}");
+        } else {
+            println!("{message}");
+        }
+
+        let _ = single_runner.run(test_case);
+    }
+
+    let runner = if args.json {
+        multi::MultiCaseRunner::new_json(
+            single_runner,
+            test_cases,
+            settings.test.threads,
+            args.retry,
+        )
+    } else if args.progress {
+        multi::MultiCaseRunner::new_progress(
+            single_runner,
+            test_cases,
+            settings.test.threads,
+            args.retry,
+            settings.display,
+        )
+    } else if args.quiet {
+        multi::MultiCaseRunner::new_quiet(
+            single_runner,
+            test_cases,
+            settings.test.threads,
+            args.retry,
+            args.print_score.is_none(),
+            settings.display,
+        )
+    } else {
+        multi::MultiCaseRunner::new_console(
+            single_runner,
+            test_cases,
+            settings.test.threads,
+            args.retry,
+            !args.unordered,
+            settings.display,
+        )
     };
+    let mut runner = runner
+        .with_fail_fast(args.fail_fast)
+        .with_time_budget(args.time_budget.map(Duration::from_secs))
+        .with_interrupt_handling(true)
+        .with_weights(load_weights(&settings)?);
+    let stats = runner.run()?;
+
+    if stats.results.is_empty() {
+        return Err(Interrupted.into());
+    }
+
+    // `--quiet` と `--print-score` を同時に指定した場合はスコアのみを出力する
+    let print_summary = !(args.json || args.quiet && args.print_score.is_some());
+
+    if let (Some(tag), true) = (&args.baseline, print_summary) {
+        println!("Baseline               : {tag}");
+    }
+
+    if let (Some(path), true) = (&args.reference_file, print_summary) {
+        println!("Reference              : {path}");
+    }
+
+    if args.reference_all_runs && print_summary {
+        println!("Reference              : best of all runs");
+    }
+
+    if print_summary {
+        // 前回の実行結果の相対スコアも今回と同じ基準で計算し直して比較する
+        let previous = io::load_latest_result_json(&settings.test.out_dir)
+            .ok()
+            .map(|r| list::calc_average_relative_score(&r, &reference_scores, &settings.problem));
+        let current = io::AllResultJson::new(&stats, "", &None);
+        let current =
+            list::calc_average_relative_score(&current, &reference_scores, &settings.problem);
+        println!(
+            "Vs Previous Run        : {}",
+            format_previous_run_delta(current, previous)
+        );
+    }
+
+    if let (Some(slowest), true) = (
+        format_slowest_cases(
+            &stats.results,
+            args.show_slowest,
+            settings.display.number_format(),
+        ),
+        print_summary,
+    ) {
+        println!("Slowest Cases          : {slowest}");
+    }
+
+    if args.histogram && print_summary {
+        // 失敗したケースは分布の表示と同様に0として扱う
+        let relative_scores = stats
+            .results
+            .iter()
+            .map(|r| r.relative_score().as_ref().copied().unwrap_or(0.0))
+            .collect::<Vec<_>>();
+        println!("Relative Score Histogram:");
+        print!("{}", format_histogram(&relative_scores, HISTOGRAM_BUCKETS));
+    }
 
+    if args.profile && print_summary {
+        println!("Step Profile:");
+        print!(
+            "{}",
+            format_step_profile(
+                &stats.results,
+                &settings.test.test_steps,
+                settings.display.number_format()
+            )
+        );
+    }
+
+    if let Some(warning) = check_score_regex(&stats.results, settings.problem.score_regex.primary())
+    {
+        eprintln!("{}", warning.yellow().bold());
+    }
+
+    // 同じseedで2回実行し、スコアが変わるケースを検出する
+    let nondeterministic_count =
+        if args.verify_deterministic && !stats.interrupted() && !stats.stopped {
+            let message = "Verifying determinism by running each case again...";
+
+            if args.json {
+                eprintln!("{message}");
+            } else {
+                println!("{message}");
+            }
+
+            let mismatches = runner.find_nondeterministic_cases(&stats.results);
+
+            match format_nondeterministic_cases(&mismatches, settings.display.number_format()) {
+                Some(report) => eprintln!("{}", report.yellow().bold()),
+                None if args.json => eprintln!("All cases produced the same scores."),
+                None => println!("All cases produced the same scores."),
+            }
+
+            mismatches.len()
+        } else {
+            0
+        };
+
+    // 削除したタグがベストスコアの記録などに残らないよう、先にタグを残すか決める
+    let tag_name = match tag {
+        Some(tag) if args.tag_if_best => {
+            settle_tag_if_best(&settings, &stats, &best_scores, tag, push)?
+        }
+        tag => tag.map(|tag| tag.name),
+    };
+
+    if !args.freeze_best_scores {
+        update_best_scores(&settings, &stats, &tag_name)?;
+    }
+
+    if !args.no_result_file {
+        let summary_file_path = io::get_summary_score_path(&settings.test.out_dir);
+        io::save_summary_log(
+            &summary_file_path,
+            &stats,
+            &comment,
+            &tag_name,
+            settings.test.summary_max_rows,
+            settings.display.number_format(),
+        )?;
+        let json_file_path = io::get_json_log_path(&settings.test.out_dir, &stats, args.compress);
+        io::save_json_log(
+            &json_file_path,
+            &stats,
+            &comment,
+            &tag_name,
+            &settings.general.version,
+        )?;
+
+        if args.csv {
+            let csv_file_path = io::get_csv_log_path(&settings.test.out_dir, &stats);
+            io::save_csv_log(&csv_file_path, &stats)?;
+        }
+
+        let report_path = if args.html {
+            let html_file_path = io::get_html_report_path(&settings.test.out_dir, &stats);
+            io::save_html_report(
+                &html_file_path,
+                &stats,
+                &comment,
+                &tag_name,
+                settings.display.number_format(),
+            )?;
+            html_file_path
+        } else {
+            summary_file_path
+        };
+
+        if args.open {
+            open_report(&report_path);
+        }
+    } else if args.open {
+        let message = "Warning: --open is ignored because --no-result-file is specified.";
+        eprintln!("{}", message.yellow());
+    }
+
+    if stats.interrupted() {
+        return Err(Interrupted.into());
+    }
+
+    // 失敗したケースがあり非ゼロで終了する場合も、スクリプトから読めるよう最後の行に出力する
+    if let Some(metric) = args.print_score {
+        println!("{}", metric.calc(&stats));
+    }
+
+    let failed = stats.results.iter().filter(|r| r.score().is_err()).count();
+    let case_count = stats.results.len();
+    *finished_stats = Some(stats);
+
+    if args.fail_fast {
+        ensure!(
+            failed == 0,
+            "Stopped running the cases because a case failed (--fail-fast)."
+        );
+    }
+
+    ensure!(
+        nondeterministic_count == 0,
+        "{nondeterministic_count} seeds produced different scores on the second run (--verify-deterministic)."
+    );
+
+    // CIなどで失敗を検知できるよう、失敗したケースがある場合は非ゼロで終了する
+    if !args.allow_failures {
+        ensure!(
+            failed == 0,
+            "{failed} of {case_count} cases failed. Pass --allow-failures to ignore the failures."
+        );
+    }
+
+    Ok(())
+}
+
+/// `--tag-if-best` 指定時、平均相対スコアが過去の全ての実行結果を上回った場合のみタグを残す
+/// 残したタグの名前を返す
+fn settle_tag_if_best(
+    settings: &Settings,
+    stats: &multi::TestStats,
+    best_scores: &HashMap<u64, single::Score>,
+    tag: git::Tag,
+    push: bool,
+) -> Result<Option<String>> {
+    // 今回の結果で更新したベストスコアを基準に、今回と過去の平均相対スコアを計算し直す
+    let mut new_best_scores = best_scores.clone();
+
+    for result in stats.results.iter() {
+        let (Ok(score), true) = (result.score(), result.has_score()) else {
+            continue;
+        };
+
+        let seed = result.test_case().seed();
+        let best_case = single::TestCase::new(
+            seed,
+            new_best_scores.get(&seed).copied(),
+            settings.problem.primary_objective(),
+        );
+
+        if best_case.is_best(Some(*score)) {
+            new_best_scores.insert(seed, *score);
+        }
+    }
+
+    let current = io::AllResultJson::new(stats, "", &None);
+    let avg_relative =
+        list::calc_average_relative_score(&current, &new_best_scores, &settings.problem);
+    let best_past = list::best_past_avg_relative_score(settings, &new_best_scores)?;
+
+    let best_past = match best_past {
+        Some(best_past) if avg_relative <= best_past => best_past,
+        _ => {
+            println!(
+                "The average relative score {avg_relative:.3} is the best so far. Keeping the tag {}.",
+                tag.name
+            );
+
+            if push {
+                git::push_tag(&tag.name, &settings.git);
+            }
+
+            return Ok(Some(tag.name));
+        }
+    };
+
+    let reason = format!(
+        "The average relative score {avg_relative:.3} did not exceed the best past run ({best_past:.3})."
+    );
+
+    // 元から存在したタグは今回の実行とは無関係なため削除しない
+    if !tag.created {
+        println!("{reason} Keeping the existing tag {}.", tag.name);
+        return Ok(Some(tag.name));
+    }
+
+    git::delete_tag(&tag.name)
+        .with_context(|| format!("Failed to delete the tag {}.", tag.name))?;
+    println!("{reason} Deleted the tag {}.", tag.name);
+
+    Ok(None)
+}
+
+/// テストステップの引数の `{PARAM:name}` を展開する
+fn expand_params(settings: &mut Settings, params: &HashMap<String, String>) -> Result<()> {
+    for (i, step) in settings.test.test_steps.iter_mut().enumerate() {
+        step.expand_params(params)
+            .with_context(|| format!("Failed to expand the parameters of test.test_steps[{i}]."))?;
+    }
+
+    Ok(())
+}
+
+/// ベストスコアのファイルからseedごとのベストスコアを読み込む
+fn load_best_scores(settings: &Settings) -> Result<HashMap<u64, single::Score>> {
+    let best_score_path = io::get_best_score_path(settings);
+    let best_score_records = io::load_best_score_records(&best_score_path)?;
+    let best_scores = best_score_records
+        .iter()
+        .map(|(&seed, record)| (seed, record.score()))
+        .collect();
+
+    Ok(best_scores)
+}
+
+/// 設定に重みのファイルが指定されている場合、seedごとの重みを読み込む
+fn load_weights(settings: &Settings) -> Result<Option<HashMap<u64, f64>>> {
+    settings
+        .problem
+        .weights_file
+        .as_ref()
+        .map(io::load_weights)
+        .transpose()
+}
+
+/// 設定に従って1ケースを実行するランナーを構築する
+fn build_single_runner(settings: &Settings) -> Result<single::SingleCaseRunner> {
     let single_runner = single::SingleCaseRunner::new(
         settings.test.test_steps.clone(),
-        Regex::new(&settings.problem.score_regex)?,
-    );
+        Regex::new(settings.problem.score_regex.primary())?,
+        settings.problem.score_aggregation,
+    )
+    .with_score_source(settings.problem.score_source)
+    .with_max_scan_bytes(settings.problem.score_scan_max_bytes)
+    .with_sub_score_patterns(settings.problem.score_regex.sub_score_patterns()?)
+    .with_seed_format(settings.test.seed_format());
+
+    Ok(single_runner)
+}
+
+/// seedごとに相対スコアの基準を設定したテストケースを構築する
+fn build_test_cases(
+    seeds: Vec<u64>,
+    reference_scores: &HashMap<u64, single::Score>,
+    settings: &Settings,
+) -> Vec<single::TestCase> {
+    seeds
+        .into_iter()
+        .map(|seed| {
+            single::TestCase::new(
+                seed,
+                reference_scores.get(&seed).copied(),
+                settings.problem.primary_objective(),
+            )
+            .with_relative_score_mode(settings.problem.relative_score_mode)
+            .with_clamp_relative(settings.problem.clamp_relative)
+        })
+        .collect()
+}
+
+/// 今回の実行結果のうち、ベストスコアを更新したものを記録する
+fn update_best_scores(
+    settings: &Settings,
+    stats: &TestStats,
+    tag_name: &Option<String>,
+) -> Result<()> {
+    let best_score_path = io::get_best_score_path(settings);
+
+    // 実行中に他のpahcerがベストスコアを更新している可能性があるため、ファイル上の最新の値と比較する
+    io::update_best_scores(&best_score_path, |best_score_records| {
+        for result in stats.results.iter() {
+            // 期待される出力との一致のみで正解としたケースには比較できるスコアがない
+            let (Ok(score), true) = (result.score(), result.has_score()) else {
+                continue;
+            };
+            let score = *score;
+
+            // 相対スコアの基準がベースラインの場合もあるため、ベストスコアと改めて比較する
+            let seed = result.test_case().seed();
+            let best_case = single::TestCase::new(
+                seed,
+                best_score_records.get(&seed).map(|record| record.score()),
+                settings.problem.primary_objective(),
+            );
+
+            if best_case.is_best(Some(score)) {
+                best_score_records
+                    .entry(seed)
+                    .and_modify(|record| record.update(score, stats.start_time, tag_name.clone()))
+                    .or_insert_with(|| {
+                        io::BestScoreRecord::new(score, stats.start_time, tag_name.clone())
+                    });
+            }
+        }
+    })
+}
+
+/// 実行するseedの一覧を取得する
+/// `--seed` または `--seeds-file` が指定されている場合、設定ファイルのseed範囲より優先される
+fn collect_seeds(args: &RunArgs, settings: &Settings) -> Result<Vec<u64>> {
+    if let Some(tag) = &args.regressed_only {
+        let baseline = io::find_result_json_by_tag(&settings.test.out_dir, tag, &settings.git)
+            .with_context(|| format!("Failed to load the baseline run {tag}."))?;
+        let latest = io::load_latest_result_json(&settings.test.out_dir)
+            .context("Failed to load the latest run.")?;
+        let seeds = find_regressed_seeds(&latest, &baseline, settings.problem.primary_objective());
+        ensure!(
+            !seeds.is_empty(),
+            "No seeds regressed in the latest run compared with {tag}."
+        );
+
+        return Ok(seeds);
+    }
+
+    let mut seeds = args.seeds.clone();
+
+    if let Some(path) = &args.seeds_file {
+        seeds.extend(io::load_seeds_file(path)?);
+    }
+
+    // 重複したseedは1度だけ実行する
+    let mut seen = HashSet::new();
+    seeds.retain(|seed| seen.insert(*seed));
+
+    if !seeds.is_empty() {
+        return Ok(seeds);
+    }
 
     let seed_range = settings.test.start_seed..settings.test.end_seed;
     ensure!(
         !seed_range.is_empty(),
-        "Seed range [{}, {}) is empty. Ensure that start_seed < end_seed (note that end_seed is exclusive).",
+        "No seeds to run. Seeds specified by --seed/--seeds-file are empty, and seed range [{}, {}) is also empty. Ensure that start_seed < end_seed (note that end_seed is exclusive).",
         seed_range.start,
         seed_range.end
     );
 
-    let mut test_cases = seed_range
-        .map(|seed| {
-            single::TestCase::new(
-                seed,
-                best_scores.get(&seed).copied(),
-                settings.problem.objective,
-            )
+    Ok(seed_range.collect())
+}
+
+/// `baseline` で成功していたseedのうち、 `latest` でスコアが悪化した（または失敗した）seedを列挙する
+/// `latest` で実行されていないseedは対象外とする
+fn find_regressed_seeds(
+    latest: &io::AllResultJson,
+    baseline: &io::AllResultJson,
+    objective: single::Objective,
+) -> Vec<u64> {
+    let latest_scores = latest
+        .cases
+        .iter()
+        .map(|c| (c.seed, single::Score::new(c.score)))
+        .collect::<HashMap<_, _>>();
+
+    let mut seeds = baseline
+        .cases
+        .iter()
+        .filter_map(|case| {
+            let baseline_score = single::Score::new(case.score)?;
+            let latest_score = latest_scores.get(&case.seed)?;
+            let regressed = match latest_score {
+                Some(score) => match objective {
+                    single::Objective::Max => *score < baseline_score,
+                    single::Objective::Min => *score > baseline_score,
+                },
+                None => true,
+            };
+
+            regressed.then_some(case.seed)
         })
         .collect::<Vec<_>>();
 
-    if args.shuffle {
-        test_cases.shuffle(&mut rand::rng());
-    }
+    seeds.sort_unstable();
+    seeds
+}
 
-    let mut runner = if args.json {
-        multi::MultiCaseRunner::new_json(single_runner, test_cases, settings.test.threads)
-    } else {
-        multi::MultiCaseRunner::new_console(single_runner, test_cases, settings.test.threads)
+/// seedを間引く
+/// `every` が指定された場合はその倍数のseedのみを残し、 `count` が指定された場合は均等な間隔で `count` 個を選ぶ
+fn sample_seeds(seeds: Vec<u64>, every: Option<u64>, count: Option<u64>) -> Vec<u64> {
+    let seeds = match every {
+        Some(every) => seeds.into_iter().filter(|seed| seed % every == 0).collect(),
+        None => seeds,
     };
-    let stats = runner.run()?;
 
-    for result in stats.results.iter() {
-        let Some(score) = result.score().as_ref().ok().copied() else {
-            continue;
-        };
-
-        if result.test_case().is_best(Some(score)) {
-            best_scores.insert(result.test_case().seed(), score);
+    match count {
+        Some(count) if (count as usize) < seeds.len() => {
+            let count = count as usize;
+            (0..count).map(|i| seeds[i * seeds.len() / count]).collect()
         }
+        _ => seeds,
     }
+}
 
-    if !args.freeze_best_scores {
-        io::save_best_scores(&best_score_path, best_scores)?;
+/// コメントの先頭にブランチ名を付与する
+fn prepend_branch_name(comment: &str, branch: &str) -> String {
+    if comment.is_empty() {
+        format!("[{branch}]")
+    } else {
+        format!("[{branch}] {comment}")
     }
+}
 
-    if !args.no_result_file {
-        let summary_file_path = io::get_summary_score_path(&settings.test.out_dir);
-        io::save_summary_log(&summary_file_path, &stats, &args.comment, &tag_name)?;
-        let json_file_path = io::get_json_log_path(&settings.test.out_dir, &stats);
-        io::save_json_log(&json_file_path, &stats, &args.comment, &tag_name)?;
+/// レポートを既定のアプリケーションで開く
+/// GUIのない環境や、開くのに失敗した場合は警告を出して何もしない
+fn open_report(path: &std::path::Path) {
+    if is_headless() {
+        let message = format!(
+            "Warning: --open is ignored because no display is available. The report is saved to {}",
+            path.display()
+        );
+        eprintln!("{}", message.yellow());
+        return;
     }
 
-    Ok(())
+    if let Err(e) = opener::open(path) {
+        let message = format!("Warning: Failed to open {}: {e}", path.display());
+        eprintln!("{}", message.yellow());
+    }
+}
+
+/// GUIのない環境（SSH接続先やCIなど）かどうかを判定する
+fn is_headless() -> bool {
+    if cfg!(any(target_os = "windows", target_os = "macos")) {
+        return false;
+    }
+
+    std::env::var_os("DISPLAY").is_none() && std::env::var_os("WAYLAND_DISPLAY").is_none()
 }
 
 #[derive(Debug, Clone, Args)]
-pub(crate) struct ListArgs {
+pub struct ListArgs {
     #[command(flatten)]
     number: Number,
+    /// Columns to display (comma separated)
+    #[clap(long = "columns", value_delimiter = ',')]
+    columns: Vec<list::ListColumn>,
+    /// Show only results whose tag name contains the given string
+    #[clap(long = "tag")]
+    tag: Option<String>,
+    /// Show only results run on or after the given date (e.g. 2024-01-31)
+    #[clap(long = "since")]
+    since: Option<NaiveDate>,
+    /// Show only results run on or before the given date (e.g. 2024-01-31)
+    #[clap(long = "until")]
+    until: Option<NaiveDate>,
+    /// Print the table as plain markdown to paste into documents (implies --no-color)
+    #[clap(long = "markdown")]
+    markdown: bool,
+    /// Print the summaries of the results as a JSON array instead of a table
+    #[clap(long = "json", conflicts_with_all = ["markdown", "columns"])]
+    json: bool,
+    /// Merge the listed results into a single row with the best score of each seed
+    #[clap(long = "aggregate")]
+    aggregate: bool,
+    /// Directory to output the results to (overrides `out_dir` in the setting file)
+    #[clap(long = "out-dir")]
+    out_dir: Option<String>,
     /// Path to the setting file
     #[clap(long = "setting-file", default_value = SETTING_FILE_PATH)]
     setting_file: String,
@@ -140,16 +1036,664 @@ struct Number {
     all: bool,
 }
 
-pub(crate) fn list(args: ListArgs) -> Result<()> {
-    let settings = io::load_setting_file(&args.setting_file)
+pub fn list(args: ListArgs) -> Result<()> {
+    let mut settings = io::load_setting_file(&args.setting_file)
         .with_context(|| format!("Failed to load the setting file {}.", &args.setting_file))?;
 
+    if let Some(out_dir) = &args.out_dir {
+        settings.test.out_dir = out_dir.clone();
+    }
+
+    settings
+        .validate()
+        .with_context(|| format!("Invalid setting file {}.", &args.setting_file))?;
+
     let limit = if args.number.all {
         None
     } else {
         Some(args.number.number)
     };
-    list::list_past_results(&settings, limit)?;
+    let columns = if args.columns.is_empty() {
+        list::ListColumn::DEFAULT.to_vec()
+    } else {
+        args.columns
+    };
+    let filter = list::ListFilter {
+        tag: args.tag,
+        since: args.since,
+        until: args.until,
+    };
+    // リダイレクト時などにエスケープシーケンスが混入しないよう色付けを無効化する
+    if args.markdown || args.json || !std::io::stdout().is_terminal() {
+        colored::control::set_override(false);
+    }
+
+    list::list_past_results(
+        &settings,
+        limit,
+        &filter,
+        &columns,
+        args.markdown,
+        args.aggregate,
+        args.json,
+    )?;
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct DiffArgs {
+    /// Tag of the base run (the tag prefix can be omitted)
+    tag_a: String,
+    /// Tag of the run to compare with the base run (the tag prefix can be omitted)
+    tag_b: String,
+    /// Path to the setting file
+    #[clap(long = "setting-file", default_value = SETTING_FILE_PATH)]
+    setting_file: String,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct RerunArgs {
+    /// Tag of the past code to re-test (the tag prefix can be omitted)
+    tag: String,
+    /// Paths not tracked by git (e.g. the local tester) to link into the checked out code
+    #[clap(long = "link", default_value = "tools")]
+    links: Vec<String>,
+    /// Path to the setting file
+    #[clap(long = "setting-file", default_value = SETTING_FILE_PATH)]
+    setting_file: String,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct WatchArgs {
+    /// Glob patterns of the files to watch, relative to the current directory (e.g. "src/**/*.rs")
+    #[clap(required = true)]
+    patterns: Vec<String>,
+    /// Time to wait after the last change before re-running, in milliseconds
+    #[clap(long = "debounce", default_value = "500")]
+    debounce_ms: u64,
+    #[command(flatten)]
+    run_args: RunArgs,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct StatsArgs {
+    /// Tag (the tag prefix can be omitted) or start time (e.g. 20240131_123456) of the run
+    run: String,
+    /// Path to the setting file
+    #[clap(long = "setting-file", default_value = SETTING_FILE_PATH)]
+    setting_file: String,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct HistoryArgs {
+    /// Seed to show the best score history of
+    seed: u64,
+    /// Path to the setting file
+    #[clap(long = "setting-file", default_value = SETTING_FILE_PATH)]
+    setting_file: String,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct ExportArgs {
+    /// Tag (the tag prefix can be omitted) or start time (e.g. 20240131_123456) of the run
+    run: String,
+    /// Directory to copy the result, the setting file and the input/output files into
+    #[clap(long = "to")]
+    to: String,
+    /// Path to the setting file
+    #[clap(long = "setting-file", default_value = SETTING_FILE_PATH)]
+    setting_file: String,
+}
+
+pub fn rerun(args: RerunArgs) -> Result<()> {
+    let settings = io::load_setting_file(&args.setting_file)
+        .with_context(|| format!("Failed to load the setting file {}.", &args.setting_file))?;
+    settings
+        .validate()
+        .with_context(|| format!("Invalid setting file {}.", &args.setting_file))?;
+
+    let stats = rerun::rerun(&settings, &args.tag, &args.links)?;
+
+    if stats.results.is_empty() || stats.interrupted() {
+        return Err(Interrupted.into());
+    }
+
+    Ok(())
+}
+
+pub fn watch(args: WatchArgs) -> Result<()> {
+    watch::watch(
+        &args.patterns,
+        std::time::Duration::from_millis(args.debounce_ms),
+        &args.run_args,
+    )
+}
+
+pub fn diff(args: DiffArgs) -> Result<()> {
+    let settings = io::load_setting_file(&args.setting_file)
+        .with_context(|| format!("Failed to load the setting file {}.", &args.setting_file))?;
+
+    diff::diff_past_results(&settings, &args.tag_a, &args.tag_b)?;
+
+    Ok(())
+}
+
+pub fn stats(args: StatsArgs) -> Result<()> {
+    let settings = io::load_setting_file(&args.setting_file)
+        .with_context(|| format!("Failed to load the setting file {}.", &args.setting_file))?;
+
+    stats::print_run_stats(&settings, &args.run)?;
+
+    Ok(())
+}
+
+pub fn history(args: HistoryArgs) -> Result<()> {
+    let settings = io::load_setting_file(&args.setting_file)
+        .with_context(|| format!("Failed to load the setting file {}.", &args.setting_file))?;
+
+    history::print_best_score_history(&settings, args.seed)?;
 
     Ok(())
 }
+
+pub fn export(args: ExportArgs) -> Result<()> {
+    let settings = io::load_setting_file(&args.setting_file)
+        .with_context(|| format!("Failed to load the setting file {}.", &args.setting_file))?;
+
+    export::export_run(
+        &settings,
+        std::path::Path::new(&args.setting_file),
+        &args.run,
+        std::path::Path::new(&args.to),
+    )?;
+
+    Ok(())
+}
+
+/// 実際には実行せず、プレースホルダを展開したコマンドを表示する
+fn print_dry_run(
+    settings: &Settings,
+    single_runner: &single::SingleCaseRunner,
+    seed: u64,
+    no_compile: bool,
+) {
+    println!("Dry run for seed {seed}. Nothing is executed.");
+
+    if !no_compile {
+        println!();
+        println!("Compile steps:");
+
+        if settings.test.compile_steps.is_empty() {
+            println!("  (none)");
+        } else {
+            print!("{}", compilie::describe_steps(&settings.test.compile_steps));
+        }
+    }
+
+    println!();
+    println!("Test steps:");
+    print!("{}", single_runner.describe_steps(seed));
+}
+
+/// 前回の実行結果からの平均相対スコアの変化を文字列化する
+fn format_previous_run_delta(current: f64, previous: Option<f64>) -> String {
+    match previous {
+        Some(previous) => format!(
+            "{:+.3} (average relative score {previous:.3} -> {current:.3})",
+            current - previous
+        ),
+        None => "- (no previous run)".to_string(),
+    }
+}
+
+/// 実行時間の長い順に上位 `count` ケースのseedと実行時間を文字列化する
+fn format_slowest_cases(
+    results: &[single::TestResult],
+    count: usize,
+    format: NumberFormat,
+) -> Option<String> {
+    if count == 0 || results.is_empty() {
+        return None;
+    }
+
+    let mut results = results.iter().collect::<Vec<_>>();
+    results.sort_by_key(|r| (std::cmp::Reverse(r.execution_time()), r.test_case().seed()));
+
+    let slowest = results
+        .iter()
+        .take(count)
+        .map(|r| {
+            let time = format.integer(r.execution_time().as_millis());
+            format!("{:04} ({time} ms)", r.test_case().seed())
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    Some(slowest)
+}
+
+/// ヒストグラムの区間数
+const HISTOGRAM_BUCKETS: usize = 10;
+/// ヒストグラムの最も長い棒の文字数
+const HISTOGRAM_BAR_WIDTH: usize = 40;
+
+/// 値の分布を、最小値から最大値までを等分した区間ごとの棒グラフとして文字列化する
+fn format_histogram(values: &[f64], buckets: usize) -> String {
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+
+    if values.is_empty() || buckets == 0 {
+        return String::new();
+    }
+
+    // 全て同じ値の場合は1区間のみとする
+    let buckets = if min < max { buckets } else { 1 };
+    let bucket_width = (max - min) / buckets as f64;
+    let mut counts = vec![0; buckets];
+
+    for &value in values {
+        let index = if bucket_width > 0.0 {
+            (((value - min) / bucket_width) as usize).min(buckets - 1)
+        } else {
+            0
+        };
+        counts[index] += 1;
+    }
+
+    let max_count = counts.iter().copied().max().unwrap_or(0);
+    let labels = (0..=buckets)
+        .map(|i| format!("{:.3}", min + bucket_width * i as f64))
+        .collect::<Vec<_>>();
+    let label_width = labels.iter().map(|l| l.len()).max().unwrap_or(0);
+    let mut histogram = String::new();
+
+    for (i, &count) in counts.iter().enumerate() {
+        let bar_len = (count * HISTOGRAM_BAR_WIDTH).div_ceil(max_count);
+        // 最後の区間のみ最大値を含む
+        let close = if i + 1 == buckets { ']' } else { ')' };
+        histogram += &format!(
+            "  [{:>label_width$}, {:>label_width$}{close} | {:<HISTOGRAM_BAR_WIDTH$} {count}\n",
+            labels[i],
+            labels[i + 1],
+            "#".repeat(bar_len),
+        );
+    }
+
+    histogram
+}
+
+/// ステップごとに全seedの実行時間の合計・平均を集計し、文字列化する
+/// 前のステップで失敗したケースなど、実行されなかったステップは平均の計算に含めない
+fn format_step_profile(
+    results: &[single::TestResult],
+    steps: &[single::TestStep],
+    format: NumberFormat,
+) -> String {
+    let mut totals = vec![(Duration::ZERO, 0u32); steps.len()];
+
+    for result in results {
+        for &(index, elapsed) in result.step_times() {
+            let (total, count) = &mut totals[index];
+            *total += elapsed;
+            *count += 1;
+        }
+    }
+
+    let sum = totals.iter().map(|&(total, _)| total).sum::<Duration>();
+    let program_width = steps.iter().map(|s| s.program().len()).max().unwrap_or(0);
+    let mut profile = String::new();
+
+    for (i, (step, &(total, count))) in steps.iter().zip(totals.iter()).enumerate() {
+        let mean = if count > 0 {
+            total.as_secs_f64() * 1e3 / count as f64
+        } else {
+            0.0
+        };
+        let share = if sum.is_zero() {
+            0.0
+        } else {
+            total.as_secs_f64() / sum.as_secs_f64() * 100.0
+        };
+
+        profile += &format!(
+            "  [{}] {:<program_width$} : total {} ms ({share:.1}%), mean {} ms ({count} cases)\n",
+            i + 1,
+            step.program(),
+            format.integer(total.as_millis()),
+            format.float(mean, NonZeroUsize::new(2).unwrap()),
+        );
+    }
+
+    profile
+}
+
+/// 2回の実行でスコアが異なったケースのseedと両方のスコアを文字列化する
+fn format_nondeterministic_cases(
+    mismatches: &[(single::TestResult, single::TestResult)],
+    format: NumberFormat,
+) -> Option<String> {
+    if mismatches.is_empty() {
+        return None;
+    }
+
+    let format_score = |result: &single::TestResult| match result.score() {
+        Ok(score) => format.score(score.get()),
+        Err(e) => e.to_string(),
+    };
+
+    let mut lines = vec![format!(
+        "Warning: {} seeds produced different scores on the second run. The solver may be non-deterministic.",
+        mismatches.len()
+    )];
+
+    for (first, second) in mismatches {
+        lines.push(format!(
+            "  {:04}: {} -> {}",
+            first.test_case().seed(),
+            format_score(first),
+            format_score(second)
+        ));
+    }
+
+    Some(lines.join("\n"))
+}
+
+/// スコアが1ケースも抽出できなかった場合、正規表現の設定ミスを疑う警告文を返す
+fn check_score_regex(results: &[single::TestResult], score_regex: &str) -> Option<String> {
+    // Wrong Answerはスコア自体は抽出できているため除外する
+    // ただし終了ステータスを無視するステップでスコアが見つからなかった場合は出力が保持されており、抽出できていない
+    // 期待される出力との一致のみで正解としたケースも、スコアは抽出できていない
+    let any_score_found = results.iter().any(|r| match r.score() {
+        Ok(_) => r.has_score(),
+        Err(e) => *e == single::CaseError::WrongAnswer && r.unmatched_output().is_none(),
+    });
+
+    if any_score_found {
+        return None;
+    }
+
+    let sample = results.iter().find_map(|r| r.unmatched_output())?;
+    let sample = if sample.is_empty() { "(empty)" } else { sample };
+
+    Some(format!(
+        "Warning: The score could not be extracted from any case. score_regex may be misconfigured.\n\
+         score_regex: {score_regex}\n\
+         --- captured output (tail) ---\n\
+         {sample}"
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_run_with_settings() -> Result<()> {
+        let out_dir = std::env::temp_dir().join(format!("pahcer_lib_{}", std::process::id()));
+        let settings: Settings = toml::from_str(&format!(
+            r#"
+[general]
+version = "0.0.0"
+
+[problem]
+problem_name = "test"
+objective = "Max"
+score_regex = '^Score = (?P<score>\d+)$'
+
+[test]
+start_seed = 0
+end_seed = 100
+threads = 1
+out_dir = '{}'
+compile_steps = []
+
+[[test.test_steps]]
+program = "echo"
+args = ["Score = 1{{SEED}}"]
+measure_time = true
+"#,
+            out_dir.display()
+        ))?;
+
+        let options = RunOptions {
+            seeds: Some(vec![1, 3]),
+            ..Default::default()
+        };
+        let stats = run_with_settings(settings.clone(), options);
+        let best_scores = load_best_scores(&settings);
+        let _ = std::fs::remove_dir_all(&out_dir);
+
+        let stats = stats?;
+        let seeds = stats.results().iter().map(|r| r.test_case().seed());
+        assert_eq!(seeds.collect::<Vec<_>>(), vec![1, 3]);
+        assert_eq!(stats.average_score(), 12.0);
+        assert_eq!(stats.average_relative_score(), 100.0);
+
+        let best_scores = best_scores?;
+        assert_eq!(best_scores.len(), 2);
+        assert_eq!(best_scores[&3].get(), 13.0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_previous_run_delta() {
+        assert_eq!(
+            format_previous_run_delta(98.2, Some(97.5)),
+            "+0.700 (average relative score 97.500 -> 98.200)"
+        );
+        assert_eq!(
+            format_previous_run_delta(97.5, Some(98.2)),
+            "-0.700 (average relative score 98.200 -> 97.500)"
+        );
+        assert_eq!(
+            format_previous_run_delta(100.0, None),
+            "- (no previous run)"
+        );
+    }
+
+    #[test]
+    fn test_parse_param() {
+        assert_eq!(
+            parse_param("temp=0.98"),
+            Ok(("temp".to_string(), "0.98".to_string()))
+        );
+        assert_eq!(
+            parse_param("expr=a=b"),
+            Ok(("expr".to_string(), "a=b".to_string()))
+        );
+        assert!(parse_param("temp").is_err());
+        assert!(parse_param("=0.98").is_err());
+    }
+
+    #[test]
+    fn test_sample_seeds() {
+        let seeds = (0..100).collect::<Vec<_>>();
+
+        assert_eq!(
+            sample_seeds(seeds.clone(), Some(10), None),
+            vec![0, 10, 20, 30, 40, 50, 60, 70, 80, 90]
+        );
+        assert_eq!(
+            sample_seeds(seeds.clone(), None, Some(4)),
+            vec![0, 25, 50, 75]
+        );
+        assert_eq!(
+            sample_seeds(seeds.clone(), Some(10), Some(3)),
+            vec![0, 30, 60]
+        );
+        assert_eq!(sample_seeds(seeds.clone(), None, Some(1000)), seeds);
+    }
+
+    #[test]
+    fn test_find_regressed_seeds() {
+        let baseline = io::AllResultJson::from_scores(&[
+            (0, 100.0),
+            (1, 100.0),
+            (2, 100.0),
+            (3, 0.0),
+            (4, 100.0),
+        ]);
+        let latest = io::AllResultJson::from_scores(&[(3, 50.0), (2, 0.0), (1, 150.0), (0, 50.0)]);
+
+        assert_eq!(
+            find_regressed_seeds(&latest, &baseline, single::Objective::Max),
+            vec![0, 2]
+        );
+        assert_eq!(
+            find_regressed_seeds(&latest, &baseline, single::Objective::Min),
+            vec![1, 2]
+        );
+    }
+
+    #[test]
+    fn test_prepend_branch_name() {
+        assert_eq!(
+            prepend_branch_name("faster sa", "feature/sa"),
+            "[feature/sa] faster sa"
+        );
+        assert_eq!(prepend_branch_name("", "main"), "[main]");
+    }
+
+    #[test]
+    fn test_format_slowest_cases() {
+        use single::{CaseError, Objective, TestCase, TestResult};
+        use std::time::Duration;
+
+        let results = [(0, 10), (1, 1500), (2, 30), (3, 30)]
+            .into_iter()
+            .map(|(seed, ms)| {
+                let test_case = TestCase::new(seed, None, Objective::Max);
+                TestResult::new(test_case, Err(CaseError::Tle), Duration::from_millis(ms))
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            format_slowest_cases(&results, 3, NumberFormat::default()).as_deref(),
+            Some("0001 (1,500 ms), 0002 (30 ms), 0003 (30 ms)")
+        );
+        assert_eq!(
+            format_slowest_cases(&results, 0, NumberFormat::default()),
+            None
+        );
+        assert_eq!(format_slowest_cases(&[], 5, NumberFormat::default()), None);
+    }
+
+    #[test]
+    fn test_format_step_profile() {
+        use single::{CaseError, Objective, TestCase, TestResult, TestStep};
+
+        let step = |program: &str| -> TestStep {
+            toml::from_str(&format!(
+                "program = \"{program}\"\nargs = []\nmeasure_time = false"
+            ))
+            .unwrap()
+        };
+        let steps = [step("./gen"), step("./a.out")];
+        let ms = Duration::from_millis;
+        let results = [
+            vec![(0, ms(100)), (1, ms(1500))],
+            vec![(0, ms(300)), (1, ms(2500))],
+            vec![(0, ms(200))],
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(seed, step_times)| {
+            let test_case = TestCase::new(seed as u64, None, Objective::Max);
+            TestResult::new(test_case, Err(CaseError::WrongAnswer), Duration::ZERO)
+                .with_step_times(step_times)
+        })
+        .collect::<Vec<_>>();
+
+        let expected = "  [1] ./gen   : total 600 ms (13.0%), mean 200.00 ms (3 cases)
+  [2] ./a.out : total 4,000 ms (87.0%), mean 2,000.00 ms (2 cases)
+";
+        assert_eq!(
+            format_step_profile(&results, &steps, NumberFormat::default()),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_format_histogram() {
+        let values = [0.0, 50.0, 90.0, 95.0, 100.0, 100.0];
+        let expected = "  [  0.000,  50.000) | ########                                 1
+  [ 50.000, 100.000] | ######################################## 5
+";
+        assert_eq!(format_histogram(&values, 2), expected);
+
+        assert_eq!(
+            format_histogram(&[100.0, 100.0], 10),
+            format!("  [100.000, 100.000] | {} 2\n", "#".repeat(40))
+        );
+        assert_eq!(format_histogram(&[], 10), "");
+    }
+
+    #[test]
+    fn test_format_nondeterministic_cases() {
+        use single::{CaseError, Score, TestResult};
+
+        let mismatches = [
+            (
+                TestResult::for_seed(3, Ok(Score::new(1234.0).unwrap())),
+                TestResult::for_seed(3, Ok(Score::new(1240.0).unwrap())),
+            ),
+            (
+                TestResult::for_seed(7, Ok(Score::new(5.0).unwrap())),
+                TestResult::for_seed(7, Err(CaseError::WrongAnswer)),
+            ),
+        ];
+
+        let report = format_nondeterministic_cases(&mismatches, NumberFormat::default()).unwrap();
+        let lines = report.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1], "  0003: 1,234 -> 1,240");
+        assert_eq!(lines[2], "  0007: 5 -> Wrong Answer");
+        assert_eq!(
+            format_nondeterministic_cases(&[], NumberFormat::default()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_check_score_regex() {
+        use single::{CaseError, Objective, Score, TestCase, TestResult};
+        use std::time::Duration;
+
+        let not_found = |seed: u64| {
+            TestResult::for_seed(seed, Err(CaseError::ScoreNotFound))
+                .with_unmatched_output("score: 123".to_string())
+        };
+
+        let results = vec![not_found(0), TestResult::for_seed(1, Err(CaseError::Tle))];
+        let warning = check_score_regex(&results, "Score = (?P<score>\\d+)").unwrap();
+        assert!(warning.contains("score: 123"));
+
+        let results = vec![
+            not_found(0),
+            TestResult::for_seed(1, Err(CaseError::WrongAnswer)),
+        ];
+        assert_eq!(check_score_regex(&results, ""), None);
+
+        // ignore_exit_statusでスコアが見つからなかったWAは、抽出できていないものとして扱う
+        let wrong_answer_without_score = TestResult::for_seed(1, Err(CaseError::WrongAnswer))
+            .with_unmatched_output("score: 456".to_string());
+        let results = vec![wrong_answer_without_score];
+        let warning = check_score_regex(&results, "").unwrap();
+        assert!(warning.contains("score: 456"));
+
+        let results = vec![
+            not_found(0),
+            TestResult::for_seed(1, Ok(Score::new(1.0).unwrap())),
+        ];
+        assert_eq!(check_score_regex(&results, ""), None);
+
+        // 期待される出力との一致のみで正解としたケースは、スコアが抽出できていないものとして扱う
+        let unscored =
+            TestResult::new_unscored(TestCase::new(2, None, Objective::Max), Duration::ZERO)
+                .with_unmatched_output("score: 789".to_string());
+        let warning = check_score_regex(&[unscored], "").unwrap();
+        assert!(warning.contains("score: 789"));
+    }
+}
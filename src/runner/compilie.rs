@@ -1,26 +1,71 @@
+use crate::util::{deserialize_program, format_command};
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    ffi::OsStr,
+    path::Path,
+    process::{Command, ExitStatus},
+    time::{Duration, Instant},
+};
+
+const COMPILE_HASH_FILE: &str = "compile_hashes.json";
+/// タイムアウトを設定したコンパイルの終了を確認する間隔
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub(crate) struct CompileStep {
+    #[serde(deserialize_with = "deserialize_program")]
     program: String,
     args: Vec<String>,
     current_dir: Option<String>,
+    /// 入力ファイルが前回のコンパイル時から変更されていない場合にスキップするかどうか
+    #[serde(default)]
+    skip_if_unchanged: bool,
+    /// 変更を検知する入力ファイルのglobパターン
+    #[serde(default)]
+    inputs: Vec<String>,
+    /// コンパイルの制限時間（超過した場合は強制終了してエラーとする）
+    timeout_ms: Option<u64>,
 }
 
-pub(super) fn compile(steps: &[CompileStep]) -> Result<()> {
-    for step in steps {
-        let mut cmd = std::process::Command::new(&step.program);
+pub(super) fn compile(steps: &[CompileStep], out_dir: impl AsRef<OsStr>) -> Result<()> {
+    let hash_path = Path::new(&out_dir).join(COMPILE_HASH_FILE);
+    let mut hashes = load_hashes(&hash_path);
+
+    for (i, step) in steps.iter().enumerate() {
+        let mut cmd = Command::new(&step.program);
         cmd.args(&step.args);
 
         if let Some(ref dir) = step.current_dir {
             cmd.current_dir(dir);
         }
 
-        let status = cmd
-            .status()
+        // ステップの内容が変わった場合も再コンパイルするよう、コマンドもキーに含める
+        let key = format!("{i}: {cmd:?}");
+        let hash = if step.skip_if_unchanged && !step.inputs.is_empty() {
+            Some(hash_inputs(&step.inputs)?)
+        } else {
+            None
+        };
+
+        if hash.is_some() && hashes.get(&key) == hash.as_ref() {
+            println!("Inputs are unchanged. Skipping the compile step: {cmd:?}");
+            continue;
+        }
+
+        let timeout = step.timeout_ms.map(Duration::from_millis);
+        let status = run_with_timeout(&mut cmd, timeout)
             .with_context(|| format!("Failed to compile. command: {cmd:?}"))?;
 
+        let Some(status) = status else {
+            return Err(anyhow::anyhow!(
+                "Compile timed out after {} ms. command: {:?}",
+                step.timeout_ms.unwrap_or_default(),
+                cmd
+            ));
+        };
+
         if !status.success() {
             return Err(anyhow::anyhow!(
                 "Failed to compile. command: {:?}, status: {}",
@@ -28,14 +73,138 @@ pub(super) fn compile(steps: &[CompileStep]) -> Result<()> {
                 status
             ));
         }
+
+        if let Some(hash) = hash {
+            hashes.insert(key, hash);
+            save_hashes(&hash_path, &hashes)?;
+        }
     }
 
     Ok(())
 }
 
+/// コマンドを実行し、終了を待つ
+/// `timeout` を過ぎても終了しない場合は強制終了し、 `None` を返す
+fn run_with_timeout(cmd: &mut Command, timeout: Option<Duration>) -> Result<Option<ExitStatus>> {
+    let Some(timeout) = timeout else {
+        return Ok(Some(cmd.status()?));
+    };
+
+    let since = Instant::now();
+    let mut child = cmd.spawn()?;
+
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+
+        if since.elapsed() >= timeout {
+            child.kill()?;
+            child.wait()?;
+            return Ok(None);
+        }
+
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// 実行されるコンパイルコマンドを表示用に文字列化する
+pub(super) fn describe_steps(steps: &[CompileStep]) -> String {
+    let mut description = String::new();
+
+    for (i, step) in steps.iter().enumerate() {
+        description += &format!(
+            "  [{}] {}\n",
+            i + 1,
+            format_command(&step.program, &step.args)
+        );
+
+        if let Some(dir) = &step.current_dir {
+            description += &format!("      current_dir: {dir}\n");
+        }
+    }
+
+    description
+}
+
+/// 前回のコンパイル時のハッシュ値を読み込む
+/// ファイルが存在しない・壊れている場合は空として扱い、常にコンパイルする
+fn load_hashes(path: &Path) -> BTreeMap<String, String> {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save_hashes(path: &Path, hashes: &BTreeMap<String, String>) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    std::fs::write(path, serde_json::to_string_pretty(hashes)?)
+        .with_context(|| format!("Failed to save the compile hashes to {}", path.display()))
+}
+
+/// globパターンにマッチする全ファイルのパスと内容からハッシュ値を計算する
+fn hash_inputs(patterns: &[String]) -> Result<String> {
+    let mut paths = vec![];
+
+    for pattern in patterns {
+        let entries = glob::glob(pattern)
+            .with_context(|| format!("Invalid glob pattern for the compile inputs: {pattern}"))?;
+
+        for entry in entries {
+            let path = entry?;
+
+            if path.is_file() {
+                paths.push(path);
+            }
+        }
+    }
+
+    paths.sort_unstable();
+    paths.dedup();
+
+    let mut hasher = Fnv1a::new();
+
+    for path in paths.iter() {
+        let contents = std::fs::read(path)
+            .with_context(|| format!("Failed to read the compile input {}", path.display()))?;
+        hasher.write(path.to_string_lossy().as_bytes());
+        hasher.write(&(contents.len() as u64).to_le_bytes());
+        hasher.write(&contents);
+    }
+
+    Ok(format!("{:016x}", hasher.finish()))
+}
+
+/// Rustのバージョンによらず安定したハッシュ値を得るためのFNV-1aハッシュ
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    const fn new() -> Self {
+        Self(Self::OFFSET_BASIS)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 ^= b as u64;
+            self.0 = self.0.wrapping_mul(Self::PRIME);
+        }
+    }
+
+    const fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use std::path::PathBuf;
 
     impl CompileStep {
         pub(crate) const fn new(
@@ -47,19 +216,73 @@ mod test {
                 program,
                 args,
                 current_dir,
+                skip_if_unchanged: false,
+                inputs: vec![],
+                timeout_ms: None,
             }
         }
     }
 
+    fn temp_out_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("pahcer_{name}_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
     #[test]
     fn test_compile_success() {
         let steps = vec![CompileStep::new("true".to_string(), vec![], None)];
-        assert!(compile(&steps).is_ok());
+        let out_dir = temp_out_dir("compile_success");
+        assert!(compile(&steps, &out_dir).is_ok());
     }
 
     #[test]
     fn test_compile_fail() {
         let steps = vec![CompileStep::new("false".to_string(), vec![], None)];
-        assert!(compile(&steps).is_err());
+        let out_dir = temp_out_dir("compile_fail");
+        assert!(compile(&steps, &out_dir).is_err());
+    }
+
+    #[test]
+    fn test_compile_timeout() {
+        let mut step = CompileStep::new("sleep".to_string(), vec!["10".to_string()], None);
+        step.timeout_ms = Some(100);
+        let out_dir = temp_out_dir("compile_timeout");
+
+        let since = Instant::now();
+        let error = compile(&[step], &out_dir).unwrap_err();
+        assert!(since.elapsed() < Duration::from_secs(5));
+        assert!(error.to_string().contains("timed out after 100 ms"));
+
+        let _ = std::fs::remove_dir_all(&out_dir);
+    }
+
+    #[test]
+    fn test_compile_skip_if_unchanged() {
+        let out_dir = temp_out_dir("compile_skip");
+        let source = out_dir.join("main.rs");
+        let counter = out_dir.join("counter");
+        std::fs::write(&source, "fn main() {}").unwrap();
+
+        let mut step = CompileStep::new(
+            "sh".to_string(),
+            vec!["-c".to_string(), format!("echo x >> {}", counter.display())],
+            None,
+        );
+        step.skip_if_unchanged = true;
+        step.inputs = vec![out_dir.join("*.rs").to_string_lossy().to_string()];
+        let steps = vec![step];
+        let compile_count = || std::fs::read_to_string(&counter).unwrap().lines().count();
+
+        compile(&steps, &out_dir).unwrap();
+        compile(&steps, &out_dir).unwrap();
+        assert_eq!(compile_count(), 1);
+
+        std::fs::write(&source, "fn main() { println!(); }").unwrap();
+        compile(&steps, &out_dir).unwrap();
+        assert_eq!(compile_count(), 2);
+
+        let _ = std::fs::remove_dir_all(&out_dir);
     }
 }
@@ -0,0 +1,248 @@
+use super::io::{self, AllResultJson};
+use crate::runner::single::Objective;
+use crate::settings::Settings;
+use crate::util::NumberFormat;
+use anyhow::Result;
+use colored::Colorize as _;
+use std::collections::HashMap;
+use tabled::{
+    settings::{object::Columns, Alignment, Style},
+    Table, Tabled,
+};
+
+#[derive(Tabled)]
+struct DiffTableRow {
+    #[tabled(rename = "Seed")]
+    seed: String,
+    #[tabled(rename = "Score A")]
+    score_a: String,
+    #[tabled(rename = "Score B")]
+    score_b: String,
+    #[tabled(rename = "Delta")]
+    delta: String,
+    #[tabled(rename = "Rel. Delta")]
+    relative_delta: String,
+}
+
+/// seedごとのスコアの差分
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct SeedDiff {
+    seed: u64,
+    score_a: f64,
+    score_b: f64,
+}
+
+impl SeedDiff {
+    fn delta(&self) -> f64 {
+        self.score_b - self.score_a
+    }
+
+    /// Aのスコアに対する変化率（%）
+    fn relative_delta(&self) -> Option<f64> {
+        (self.score_a != 0.0).then(|| self.delta() / self.score_a * 100.0)
+    }
+
+    /// 目的関数の向きを考慮して改善したかどうかを判定する
+    fn is_improved(&self, objective: Objective) -> bool {
+        match objective {
+            Objective::Max => self.score_b > self.score_a,
+            Objective::Min => self.score_b < self.score_a,
+        }
+    }
+}
+
+/// 2つの過去のテスト結果をseedごとに比較して表示する関数
+pub(super) fn diff_past_results(settings: &Settings, tag_a: &str, tag_b: &str) -> Result<()> {
+    let result_a = io::find_result_json_by_tag(&settings.test.out_dir, tag_a, &settings.git)?;
+    let result_b = io::find_result_json_by_tag(&settings.test.out_dir, tag_b, &settings.git)?;
+    let objective = settings.problem.primary_objective();
+    let format = settings.display.number_format();
+
+    let diffs = compare_results(&result_a, &result_b);
+    let changed = diffs
+        .iter()
+        .filter(|d| d.score_a != d.score_b)
+        .collect::<Vec<_>>();
+
+    if changed.is_empty() {
+        println!("No score changes found.");
+    } else {
+        let table_rows = changed
+            .iter()
+            .map(|d| convert_to_table_row(d, objective, format))
+            .collect::<Vec<_>>();
+
+        let mut table = Table::new(table_rows);
+        table.with(Style::markdown());
+        table.modify(Columns::new(1..=4), Alignment::right());
+        println!("{table}");
+    }
+
+    println!("{}", format_counts(&diffs, objective));
+
+    // サブスコアはそれぞれの最適化の方向で改善・悪化を判定する
+    for (name, objective) in settings.problem.sub_score_objectives() {
+        let diffs = compare_sub_scores(&result_a, &result_b, &name);
+
+        if !diffs.is_empty() {
+            println!("{name}: {}", format_counts(&diffs, objective));
+        }
+    }
+
+    Ok(())
+}
+
+/// 改善・悪化・変化なしのseed数を整形する
+fn format_counts(diffs: &[SeedDiff], objective: Objective) -> String {
+    let changed_count = diffs.iter().filter(|d| d.score_a != d.score_b).count();
+    let improved_count = diffs.iter().filter(|d| d.is_improved(objective)).count();
+    let regressed_count = changed_count - improved_count;
+    let unchanged_count = diffs.len() - changed_count;
+
+    format!(
+        "Improved: {}, Regressed: {}, Unchanged: {}",
+        improved_count.to_string().green(),
+        regressed_count.to_string().yellow(),
+        unchanged_count
+    )
+}
+
+/// 両方の結果に含まれるseedについてスコアを比較する
+fn compare_results(result_a: &AllResultJson, result_b: &AllResultJson) -> Vec<SeedDiff> {
+    let scores_b = result_b
+        .cases
+        .iter()
+        .map(|c| (c.seed, c.score))
+        .collect::<HashMap<_, _>>();
+
+    let mut diffs = result_a
+        .cases
+        .iter()
+        .filter_map(|c| {
+            scores_b.get(&c.seed).map(|&score_b| SeedDiff {
+                seed: c.seed,
+                score_a: c.score,
+                score_b,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    diffs.sort_unstable_by_key(|d| d.seed);
+    diffs
+}
+
+/// 両方の結果に `name` のサブスコアが記録されているseedについてサブスコアを比較する
+fn compare_sub_scores(
+    result_a: &AllResultJson,
+    result_b: &AllResultJson,
+    name: &str,
+) -> Vec<SeedDiff> {
+    let scores_b = result_b
+        .cases
+        .iter()
+        .filter_map(|c| c.sub_scores.get(name).map(|&score| (c.seed, score)))
+        .collect::<HashMap<_, _>>();
+
+    let mut diffs = result_a
+        .cases
+        .iter()
+        .filter_map(|c| {
+            let score_a = *c.sub_scores.get(name)?;
+            let score_b = *scores_b.get(&c.seed)?;
+            Some(SeedDiff {
+                seed: c.seed,
+                score_a,
+                score_b,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    diffs.sort_unstable_by_key(|d| d.seed);
+    diffs
+}
+
+fn convert_to_table_row(
+    diff: &SeedDiff,
+    objective: Objective,
+    format: NumberFormat,
+) -> DiffTableRow {
+    let delta = match format.score(diff.delta()) {
+        d if diff.delta() > 0.0 => format!("+{d}"),
+        d => d,
+    };
+    let relative_delta = diff
+        .relative_delta()
+        .map_or_else(|| "-".to_string(), |d| format!("{d:+.3}%"));
+
+    let (delta, relative_delta) = if diff.is_improved(objective) {
+        (delta.green(), relative_delta.green())
+    } else {
+        (delta.yellow(), relative_delta.yellow())
+    };
+
+    DiffTableRow {
+        seed: format!("{:04}", diff.seed),
+        score_a: format.score(diff.score_a),
+        score_b: format.score(diff.score_b),
+        delta: delta.to_string(),
+        relative_delta: relative_delta.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_compare_results() {
+        let result_a = AllResultJson::from_scores(&[(1, 100.0), (0, 200.0), (2, 300.0)]);
+        let result_b = AllResultJson::from_scores(&[(0, 100.0), (1, 100.0), (3, 400.0)]);
+
+        let diffs = compare_results(&result_a, &result_b);
+        assert_eq!(
+            diffs,
+            vec![
+                SeedDiff {
+                    seed: 0,
+                    score_a: 200.0,
+                    score_b: 100.0
+                },
+                SeedDiff {
+                    seed: 1,
+                    score_a: 100.0,
+                    score_b: 100.0
+                },
+            ]
+        );
+
+        assert!(!diffs[0].is_improved(Objective::Max));
+        assert!(diffs[0].is_improved(Objective::Min));
+        assert_eq!(diffs[0].relative_delta(), Some(-50.0));
+    }
+
+    #[test]
+    fn test_compare_sub_scores() {
+        let mut result_a = AllResultJson::from_scores(&[(0, 100.0), (1, 100.0), (2, 100.0)]);
+        let mut result_b = AllResultJson::from_scores(&[(0, 100.0), (1, 100.0), (2, 100.0)]);
+
+        for (result, penalties) in [(&mut result_a, [10.0, 20.0]), (&mut result_b, [5.0, 30.0])] {
+            for (case, penalty) in result.cases.iter_mut().zip(penalties) {
+                case.sub_scores.insert("Penalty".to_string(), penalty);
+            }
+        }
+
+        // サブスコアが片方にしか記録されていないseedは比較しない
+        result_a.cases[2]
+            .sub_scores
+            .insert("Penalty".to_string(), 0.0);
+
+        let diffs = compare_sub_scores(&result_a, &result_b, "Penalty");
+        assert_eq!(diffs.len(), 2);
+        assert!(compare_sub_scores(&result_a, &result_b, "Bonus").is_empty());
+
+        // primaryのスコアとは独立に、サブスコアの最適化の方向で判定する
+        assert!(diffs[0].is_improved(Objective::Min));
+        assert!(!diffs[1].is_improved(Objective::Min));
+        assert!(!diffs[0].is_improved(Objective::Max));
+    }
+}
@@ -0,0 +1,102 @@
+use super::{build_single_runner, io};
+use crate::settings::Settings;
+use anyhow::{Context as _, Result};
+use colored::Colorize as _;
+use std::path::{Component, Path, PathBuf};
+
+/// 実行結果のJSONファイルを書き出すディレクトリ（出力先からの相対パス）
+const JSON_DIR: &str = "json";
+
+/// タグ名または実行日時で指定された実行結果を、再現に必要なファイルとともにディレクトリにまとめる
+/// 実行結果のJSONファイル・設定ファイル・各ケースの入出力ファイルをコピーする
+pub(super) fn export_run(
+    settings: &Settings,
+    setting_file: &Path,
+    run: &str,
+    to: &Path,
+) -> Result<()> {
+    let (json_path, result) =
+        io::find_result_json_with_path(&settings.test.out_dir, run, &settings.git)?;
+
+    let mut exported = 0;
+    let json_file_name = json_path.file_name().context("Invalid result file path.")?;
+    copy_file(&json_path, &to.join(JSON_DIR).join(json_file_name))?;
+    exported += 1;
+
+    let setting_file_name = setting_file
+        .file_name()
+        .context("Invalid setting file path.")?;
+    copy_file(setting_file, &to.join(setting_file_name))?;
+    exported += 1;
+
+    // 入出力ファイルは実行のたびに上書きされるため、現在残っているものをコピーする
+    let single_runner = build_single_runner(settings)?;
+    let mut missing = vec![];
+
+    for case in &result.cases {
+        for path in single_runner.io_files(case.seed) {
+            if path.is_file() {
+                copy_file(&path, &to.join(bundle_path(&path)))?;
+                exported += 1;
+            } else {
+                missing.push(path);
+            }
+        }
+    }
+
+    println!("Exported {exported} files to {}", to.display());
+
+    if !missing.is_empty() {
+        let message = format!(
+            "Warning: {} files referenced by the run were not found (e.g. {}).",
+            missing.len(),
+            missing[0].display()
+        );
+        eprintln!("{}", message.yellow());
+    }
+
+    Ok(())
+}
+
+/// ファイルをコピーする（コピー先のディレクトリがなければ作成する）
+fn copy_file(from: &Path, to: &Path) -> Result<()> {
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    std::fs::copy(from, to)
+        .with_context(|| format!("Failed to copy {} to {}.", from.display(), to.display()))?;
+    Ok(())
+}
+
+/// 出力先のディレクトリ内でのパスを返す
+/// 出力先の外を指さないよう、ルートや `..` などは取り除く
+fn bundle_path(path: &Path) -> PathBuf {
+    path.components()
+        .filter_map(|c| match c {
+            Component::Normal(name) => Some(name),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_bundle_path() {
+        assert_eq!(
+            bundle_path(Path::new("./tools/in/0000.txt")),
+            PathBuf::from("tools/in/0000.txt")
+        );
+        assert_eq!(
+            bundle_path(Path::new("../shared/out/0000.txt")),
+            PathBuf::from("shared/out/0000.txt")
+        );
+        assert_eq!(
+            bundle_path(Path::new("/tmp/in/0000.txt")),
+            PathBuf::from("tmp/in/0000.txt")
+        );
+    }
+}
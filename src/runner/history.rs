@@ -0,0 +1,56 @@
+use super::io;
+use crate::settings::Settings;
+use anyhow::{Context as _, Result};
+use tabled::{
+    settings::{object::Columns, Alignment, Style},
+    Table, Tabled,
+};
+
+#[derive(Tabled)]
+struct HistoryTableRow {
+    #[tabled(rename = "Time")]
+    time: String,
+    #[tabled(rename = "Score")]
+    score: String,
+    #[tabled(rename = "Tag")]
+    tag: String,
+}
+
+/// 指定したseedのベストスコア更新履歴を表示する関数
+pub(super) fn print_best_score_history(settings: &Settings, seed: u64) -> Result<()> {
+    let best_score_path = io::get_best_score_path(settings);
+    let records = io::load_best_score_records(&best_score_path)?;
+    let record = records
+        .get(&seed)
+        .with_context(|| format!("No best score found for seed {seed}."))?;
+
+    let format = settings.display.number_format();
+    println!("Best Score: {}", format.score(record.score().get()));
+
+    if record.history().is_empty() {
+        println!("No history is recorded for seed {seed}.");
+        return Ok(());
+    }
+
+    let rows = record
+        .history()
+        .iter()
+        .rev()
+        .map(|history| HistoryTableRow {
+            time: history.time.format("%Y/%m/%d %H:%M:%S").to_string(),
+            score: format.score(history.score.get()),
+            tag: history
+                .tag_name
+                .as_deref()
+                .map_or("-", |tag_name| settings.git.strip_tag_prefix(tag_name))
+                .to_string(),
+        });
+
+    let mut table = Table::new(rows);
+    table
+        .with(Style::markdown())
+        .modify(Columns::one(1), Alignment::right());
+    println!("{table}");
+
+    Ok(())
+}
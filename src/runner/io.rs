@@ -1,48 +1,267 @@
-use crate::util::format_float_with_commas;
+use crate::settings::Git;
+use crate::util::{serialize_score, NumberFormat};
 
 use super::{
     multi::{self, TestStats},
+    single::{CaseErrorKind, Objective, Score},
     Settings,
 };
-use anyhow::{Context as _, Result};
+use anyhow::{bail, ensure, Context as _, Result};
 use chrono::{DateTime, Local};
-use num_format::{Locale, ToFormattedString as _};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::{BTreeMap, HashMap},
+    collections::{BTreeMap, BTreeSet, HashMap},
     ffi::OsStr,
     fs::{File, OpenOptions},
     io::{BufReader, BufWriter, Write},
-    num::{NonZeroU64, NonZeroUsize},
+    num::NonZeroUsize,
     path::{Path, PathBuf},
 };
+use sysinfo::{CpuRefreshKind, RefreshKind, System};
 
 const BEST_SCORE_FILE: &str = "best_scores.json";
+const JSON_EXTENSION: &str = ".json";
+const JSON_GZ_EXTENSION: &str = ".json.gz";
 const SUMMARY_SCORE_FILE: &str = "summary.md";
+/// サマリファイルのヘッダの行数
+const SUMMARY_HEADER_ROWS: usize = 2;
+/// 全実行結果のseedごとのベストスコアのキャッシュ
+const ALL_RUNS_BEST_CACHE_FILE: &str = "all_runs_best_scores.json";
+/// 最新の実行結果を指すファイルの名前（拡張子を除く）
+const LATEST_JSON_STEM: &str = "latest";
+/// seedごとに保持するベストスコア更新履歴の最大件数
+const MAX_BEST_SCORE_HISTORY: usize = 10;
+const HTML_REPORT_TEMPLATE: &str = include_str!("io/report.html");
 
-pub(super) fn get_best_score_path(dir_path: impl AsRef<OsStr>) -> PathBuf {
-    Path::new(&dir_path).join(Path::new(BEST_SCORE_FILE))
+/// ベストスコアのファイルのパスを返す
+/// `best_score_file` が指定されていない場合は `out_dir` 内の `best_scores.json` とする
+pub(super) fn get_best_score_path(settings: &Settings) -> PathBuf {
+    match &settings.test.best_score_file {
+        Some(path) => PathBuf::from(path),
+        None => Path::new(&settings.test.out_dir).join(BEST_SCORE_FILE),
+    }
 }
 
+/// 設定ファイルを読み込む
 pub(super) fn load_setting_file(path: impl AsRef<OsStr>) -> Result<Settings> {
     let settings_str = std::fs::read_to_string(Path::new(&path))?;
-    let settings = toml::from_str(&settings_str)?;
+    let settings: Settings = toml::from_str(&settings_str)?;
     Ok(settings)
 }
 
-pub(super) fn load_best_scores(path: impl AsRef<Path>) -> Result<HashMap<u64, NonZeroU64>> {
+/// 1行に1つのseedが書かれたファイルからseedを読み込む
+/// 空行と `#` で始まる行は無視する
+pub(super) fn load_seeds_file(path: impl AsRef<Path>) -> Result<Vec<u64>> {
+    let path = path.as_ref();
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read the seeds file {}", path.display()))?;
+    parse_seeds(&contents)
+        .with_context(|| format!("Failed to parse the seeds file {}", path.display()))
+}
+
+fn parse_seeds(contents: &str) -> Result<Vec<u64>> {
+    let mut seeds = vec![];
+
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let seed = line
+            .parse()
+            .with_context(|| format!("Invalid seed at line {}: {line}", i + 1))?;
+        seeds.push(seed);
+    }
+
+    Ok(seeds)
+}
+
+/// 各行が `seed,score` （またはタブ区切り）のCSV/TSVファイルから基準スコアを読み込む
+/// 空行・ `#` で始まる行・ヘッダ行は無視する
+pub(super) fn load_reference_scores(path: impl AsRef<Path>) -> Result<HashMap<u64, Score>> {
+    let path = path.as_ref();
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read the reference file {}", path.display()))?;
+    parse_reference_scores(&contents)
+        .with_context(|| format!("Failed to parse the reference file {}", path.display()))
+}
+
+fn parse_reference_scores(contents: &str) -> Result<HashMap<u64, Score>> {
+    // スコアが0のseedは基準スコアなしとして扱う
+    let scores = parse_seed_values(contents, "score")?
+        .into_iter()
+        .filter_map(|(seed, score)| Score::new(score).map(|score| (seed, score)))
+        .collect();
+
+    Ok(scores)
+}
+
+/// 各行が `seed,weight` （またはタブ区切り）のCSV/TSVファイルからseedの重みを読み込む
+/// 形式は基準スコアのファイルと同じ
+pub(super) fn load_weights(path: impl AsRef<Path>) -> Result<HashMap<u64, f64>> {
+    let path = path.as_ref();
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read the weights file {}", path.display()))?;
+    parse_weights(&contents)
+        .with_context(|| format!("Failed to parse the weights file {}", path.display()))
+}
+
+fn parse_weights(contents: &str) -> Result<HashMap<u64, f64>> {
+    let weights = parse_seed_values(contents, "weight")?;
+
+    for &(seed, weight) in &weights {
+        ensure!(
+            weight.is_finite() && weight >= 0.0,
+            "The weight of seed {seed} must be a non-negative number: {weight}"
+        );
+    }
+
+    Ok(weights.into_iter().collect())
+}
+
+/// `seed,value` の2列のCSV/TSVを読み込む
+/// 空行・ `#` で始まる行・先頭のヘッダ行は無視する
+fn parse_seed_values(contents: &str, value_name: &str) -> Result<Vec<(u64, f64)>> {
+    let mut values = vec![];
+    let mut is_first_record = true;
+
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields = line.split([',', '\t']).map(str::trim).collect::<Vec<_>>();
+        let [seed, value] = fields[..] else {
+            bail!(
+                "Expected 2 columns (seed, {value_name}) at line {}: {line}",
+                i + 1
+            );
+        };
+
+        // 先頭行のseedが数値でない場合はヘッダとみなす
+        let is_header = is_first_record && seed.parse::<u64>().is_err();
+        is_first_record = false;
+
+        if is_header {
+            continue;
+        }
+
+        let seed = seed
+            .parse()
+            .with_context(|| format!("Invalid seed at line {}: {seed}", i + 1))?;
+        let value = value
+            .parse::<f64>()
+            .with_context(|| format!("Invalid {value_name} at line {}: {value}", i + 1))?;
+        values.push((seed, value));
+    }
+
+    Ok(values)
+}
+
+/// seedごとのベストスコアとその更新履歴
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(super) struct BestScoreRecord {
+    score: Score,
+    #[serde(default)]
+    history: Vec<BestScoreHistory>,
+}
+
+impl BestScoreRecord {
+    pub(super) fn new(score: Score, time: DateTime<Local>, tag_name: Option<String>) -> Self {
+        Self {
+            score,
+            history: vec![BestScoreHistory {
+                score,
+                time,
+                tag_name,
+            }],
+        }
+    }
+
+    /// 旧形式から読み込んだ場合など、履歴を持たないレコードを作成する
+    const fn without_history(score: Score) -> Self {
+        Self {
+            score,
+            history: vec![],
+        }
+    }
+
+    pub(super) const fn score(&self) -> Score {
+        self.score
+    }
+
+    pub(super) fn history(&self) -> &[BestScoreHistory] {
+        &self.history
+    }
+
+    /// ベストスコアを更新し、スコアが変化した場合は履歴に追加する
+    pub(super) fn update(&mut self, score: Score, time: DateTime<Local>, tag_name: Option<String>) {
+        let is_changed = self.score != score;
+        self.score = score;
+
+        if is_changed {
+            self.history.push(BestScoreHistory {
+                score,
+                time,
+                tag_name,
+            });
+
+            let overflow = self.history.len().saturating_sub(MAX_BEST_SCORE_HISTORY);
+            self.history.drain(..overflow);
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(super) struct BestScoreHistory {
+    pub(super) score: Score,
+    pub(super) time: DateTime<Local>,
+    pub(super) tag_name: Option<String>,
+}
+
+/// 旧形式（スコアのみ）と新形式（履歴付き）の両方を読み込めるようにする
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum BestScoreEntry {
+    Flat(f64),
+    Record(BestScoreRecord),
+}
+
+pub(super) fn load_best_scores(path: impl AsRef<Path>) -> Result<HashMap<u64, Score>> {
+    let records = load_best_score_records(path)?;
+    let map = records
+        .into_iter()
+        .map(|(seed, record)| (seed, record.score))
+        .collect();
+
+    Ok(map)
+}
+
+pub(super) fn load_best_score_records(
+    path: impl AsRef<Path>,
+) -> Result<BTreeMap<u64, BestScoreRecord>> {
     let Ok(file) = File::open(&path) else {
-        return Ok(HashMap::new());
+        return Ok(BTreeMap::new());
     };
     let reader = BufReader::new(file);
-    let temp_map: HashMap<String, u64> =
+    let temp_map: HashMap<String, BestScoreEntry> =
         serde_json::from_reader(reader).context("Failed to parse json")?;
 
     let map = temp_map
         .into_iter()
         .flat_map(|(key, value)| {
             let key = key.parse::<u64>().ok();
-            let value = NonZeroU64::new(value);
+            let value = match value {
+                BestScoreEntry::Flat(score) => {
+                    Score::new(score).map(BestScoreRecord::without_history)
+                }
+                BestScoreEntry::Record(record) => Some(record),
+            };
             match (key, value) {
                 (Some(key), Some(value)) => Some((key, value)),
                 (_, _) => None,
@@ -55,31 +274,191 @@ pub(super) fn load_best_scores(path: impl AsRef<Path>) -> Result<HashMap<u64, No
 
 pub(super) fn save_best_scores(
     path: impl AsRef<Path>,
-    best_scores: HashMap<u64, NonZeroU64>,
+    best_scores: BTreeMap<u64, BestScoreRecord>,
 ) -> Result<()> {
-    let json_map: BTreeMap<String, u64> = best_scores
+    let json_map: BTreeMap<String, BestScoreRecord> = best_scores
         .into_iter()
-        .map(|(key, value)| (format!("{key:04}"), value.get()))
+        .map(|(key, value)| (format!("{key:04}"), value))
         .collect();
 
     create_parent_dir(&path)?;
 
-    let file = File::create(path)?;
-    let writer = BufWriter::new(file);
-    serde_json::to_writer_pretty(writer, &json_map)?;
+    // 書き込み途中のファイルを他のプロセスが読まないよう、一時ファイルに書き込んでから置き換える
+    let path = path.as_ref();
+    let temp_path = append_extension(path, "tmp");
+    let file = File::create(&temp_path)?;
+    let mut writer = BufWriter::new(file);
+    serde_json::to_writer_pretty(&mut writer, &json_map)?;
+    writer.flush()?;
+    drop(writer);
+    std::fs::rename(&temp_path, path)
+        .with_context(|| format!("Failed to save best scores to {}", path.display()))?;
+
+    Ok(())
+}
+
+/// ベストスコアのファイルをロックした上で読み込み、`update` で更新して保存する
+/// 複数のpahcerが同時に実行されても、他のプロセスが更新したベストスコアを上書きしない
+pub(super) fn update_best_scores(
+    path: impl AsRef<Path>,
+    update: impl FnOnce(&mut BTreeMap<u64, BestScoreRecord>),
+) -> Result<()> {
+    let path = path.as_ref();
+    create_parent_dir(path)?;
+
+    let lock_path = append_extension(path, "lock");
+    let lock_file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .with_context(|| format!("Failed to open {}", lock_path.display()))?;
+    lock_file
+        .lock()
+        .with_context(|| format!("Failed to lock {}", lock_path.display()))?;
+
+    let mut records = load_best_score_records(path)?;
+    update(&mut records);
+    save_best_scores(path, records)?;
+
+    // ロックはファイルを閉じた時点で解放される
+    drop(lock_file);
 
     Ok(())
 }
 
+/// `best_scores.json` を `best_scores.json.lock` のように、パスの末尾に拡張子を追加する
+fn append_extension(path: &Path, extension: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(".");
+    path.push(extension);
+    PathBuf::from(path)
+}
+
+/// 指定したタグの過去の実行結果を読み込み、seedごとのスコアを返す
+/// 失敗したケースとスコアのないケースは含まれない
+pub(super) fn load_baseline_scores(
+    dir_path: impl AsRef<OsStr>,
+    tag: &str,
+    git_settings: &Git,
+) -> Result<HashMap<u64, Score>> {
+    let result = find_result_json_by_tag(dir_path, tag, git_settings)?;
+    let scores = result
+        .cases
+        .iter()
+        .filter_map(|c| c.scored().map(|s| (c.seed, s)))
+        .collect();
+
+    Ok(scores)
+}
+
+/// 全実行結果のseedごとのベストスコアのキャッシュ
+/// 実行結果のファイルは作成後に変更されないため、読み込み済みのファイル名とともに保存し、新しいファイルのみを読み込む
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct AllRunsBestCache {
+    objective: Option<Objective>,
+    files: BTreeSet<String>,
+    scores: BTreeMap<u64, f64>,
+}
+
+/// JSONディレクトリ内の全ての実行結果から、seedごとのベストスコアを読み込む
+/// 読み込み結果は `out_dir` 内にキャッシュし、次回以降は新しい実行結果のみを読み込む
+pub(super) fn load_all_runs_best_scores(
+    dir_path: impl AsRef<OsStr>,
+    objective: Objective,
+) -> Result<HashMap<u64, Score>> {
+    let cache_path = Path::new(&dir_path).join(ALL_RUNS_BEST_CACHE_FILE);
+    let paths = list_result_json_paths(&dir_path)?;
+    let file_names = paths
+        .iter()
+        .filter_map(|p| p.file_name().and_then(|n| n.to_str()).map(str::to_string))
+        .collect::<BTreeSet<_>>();
+
+    // 実行結果が削除された場合や目的関数が変わった場合はキャッシュを作り直す
+    let mut cache = std::fs::read_to_string(&cache_path)
+        .ok()
+        .and_then(|s| serde_json::from_str::<AllRunsBestCache>(&s).ok())
+        .filter(|c| c.objective == Some(objective) && c.files.is_subset(&file_names))
+        .unwrap_or_default();
+    cache.objective = Some(objective);
+
+    let new_paths = paths
+        .iter()
+        .filter(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| !cache.files.contains(n))
+        })
+        .collect::<Vec<_>>();
+
+    for path in &new_paths {
+        // 壊れたファイルは無視する（次回も読み込みを試みる）
+        let Ok(result) = load_result_json(path) else {
+            continue;
+        };
+
+        merge_best_scores(&mut cache.scores, &result, objective);
+
+        if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+            cache.files.insert(name.to_string());
+        }
+    }
+
+    if !new_paths.is_empty() {
+        // キャッシュの保存に失敗しても結果には影響しないため無視する
+        if let Ok(json) = serde_json::to_string(&cache) {
+            let _ = std::fs::write(&cache_path, json);
+        }
+    }
+
+    let scores = cache
+        .scores
+        .iter()
+        .filter_map(|(&seed, &score)| Score::new(score).map(|s| (seed, s)))
+        .collect();
+
+    Ok(scores)
+}
+
+/// 実行結果のスコアのうち、 `scores` より良いものでseedごとのベストスコアを更新する
+fn merge_best_scores(
+    scores: &mut BTreeMap<u64, f64>,
+    result: &AllResultJson,
+    objective: Objective,
+) {
+    for case in &result.cases {
+        // スコアが0以下のケースは失敗として扱い、スコアのないケースとともに除外する
+        let Some(score) = case.scored() else {
+            continue;
+        };
+
+        let is_better = match scores.get(&case.seed) {
+            Some(&best) => match objective {
+                Objective::Max => score.get() > best,
+                Objective::Min => score.get() < best,
+            },
+            None => true,
+        };
+
+        if is_better {
+            scores.insert(case.seed, score.get());
+        }
+    }
+}
+
 pub(super) fn get_summary_score_path(dir_path: impl AsRef<OsStr>) -> PathBuf {
     Path::new(&dir_path).join(Path::new(SUMMARY_SCORE_FILE))
 }
 
+/// サマリファイルに実行結果を1行追記する
+/// `max_rows` が指定された場合、行数が上限を超えたら古い行から削除する
 pub(super) fn save_summary_log(
     path: impl AsRef<Path>,
     stats: &multi::TestStats,
     comment: &str,
     tag_name: &Option<String>,
+    max_rows: Option<usize>,
+    number_format: NumberFormat,
 ) -> Result<()> {
     let comment = match tag_name {
         Some(tag_name) => format!("({tag_name}) {comment}"),
@@ -90,17 +469,51 @@ pub(super) fn save_summary_log(
         Ok(file) => BufWriter::new(file),
         Err(_) => {
             create_parent_dir(&path)?;
-            let mut writer = BufWriter::new(File::create(path)?);
+            let mut writer = BufWriter::new(File::create(&path)?);
             save_summary_header(&mut writer)?;
             writer
         }
     };
 
-    save_summary_log_inner(&mut writer, stats, &comment)?;
+    save_summary_log_inner(&mut writer, stats, &comment, number_format)?;
+    writer.flush()?;
+    drop(writer);
+
+    if let Some(max_rows) = max_rows {
+        let contents = std::fs::read_to_string(&path)?;
+
+        if let Some(truncated) = truncate_summary_rows(&contents, max_rows) {
+            // 書き込み途中で中断されてもサマリファイルが壊れないよう、一時ファイルに書き出してから置き換える
+            let tmp_path = append_extension(path.as_ref(), "tmp");
+            std::fs::write(&tmp_path, truncated)?;
+            std::fs::rename(&tmp_path, &path)?;
+        }
+    }
 
     Ok(())
 }
 
+/// サマリファイルの内容から、ヘッダを残して直近の `max_rows` 行のみを残したものを返す
+/// 上限を超えていない場合は `None` を返す
+fn truncate_summary_rows(contents: &str, max_rows: usize) -> Option<String> {
+    let lines = contents.lines().collect::<Vec<_>>();
+    let row_count = lines.len().saturating_sub(SUMMARY_HEADER_ROWS);
+
+    if row_count <= max_rows {
+        return None;
+    }
+
+    let header = &lines[..SUMMARY_HEADER_ROWS];
+    let rows = &lines[lines.len() - max_rows..];
+    let truncated = header
+        .iter()
+        .chain(rows)
+        .map(|line| format!("{line}\n"))
+        .collect();
+
+    Some(truncated)
+}
+
 fn save_summary_header(writer: &mut impl Write) -> Result<()> {
     writeln!(
         writer,
@@ -118,6 +531,7 @@ fn save_summary_log_inner(
     writer: &mut impl Write,
     stats: &multi::TestStats,
     comment: &str,
+    number_format: NumberFormat,
 ) -> Result<()> {
     let nonzero2 = NonZeroUsize::new(2).unwrap();
     let nonzero5 = NonZeroUsize::new(5).unwrap();
@@ -125,16 +539,12 @@ fn save_summary_log_inner(
     let start_time = stats
         .start_time
         .to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
-    let case_count = stats.results.len().to_formatted_string(&Locale::en);
-    let score = stats.score_sum.to_formatted_string(&Locale::en);
-    let average_score = format_float_with_commas(
-        stats.score_sum as f64 / stats.results.len() as f64,
-        nonzero2,
-    );
+    let case_count = number_format.integer(stats.results.len());
+    let score = number_format.score(stats.score_sum);
+    let average_score = number_format.float(stats.average_score(), nonzero2);
 
-    let score_log10 = format_float_with_commas(stats.score_sum_log10, nonzero5);
-    let average_score_log10 =
-        format_float_with_commas(stats.score_sum_log10 / stats.results.len() as f64, nonzero5);
+    let score_log10 = number_format.float(stats.score_sum_log10, nonzero5);
+    let average_score_log10 = number_format.float(stats.average_score_log10(), nonzero5);
 
     writeln!(
         writer,
@@ -148,26 +558,35 @@ fn save_summary_log_inner(
 pub(super) struct AllResultJson {
     pub(super) start_time: DateTime<Local>,
     pub(super) case_count: usize,
-    pub(super) total_score: u64,
+    #[serde(serialize_with = "serialize_score")]
+    pub(super) total_score: f64,
     pub(super) total_score_log10: f64,
     pub(super) total_relative_score: f64,
     pub(super) max_execution_time: f64,
     pub(super) comment: String,
     pub(super) tag_name: Option<String>,
+    /// 実行環境の情報（過去のバージョンで出力されたファイルには存在しない）
+    #[serde(default)]
+    pub(super) hostname: Option<String>,
+    #[serde(default)]
+    pub(super) cpu_model: Option<String>,
+    #[serde(default)]
+    pub(super) threads: Option<usize>,
+    /// 出力したpahcerと設定ファイルのバージョン（過去のバージョンで出力されたファイルには存在しない）
+    #[serde(default)]
+    pub(super) pahcer_version: Option<String>,
+    #[serde(default)]
+    pub(super) config_version: Option<String>,
     pub(super) wa_seeds: Vec<u64>,
     pub(super) cases: Vec<CaseResultJson>,
 }
 
 impl AllResultJson {
-    fn new(stats: &TestStats, comment: &str, tag_name: &Option<String>) -> Self {
+    pub(super) fn new(stats: &TestStats, comment: &str, tag_name: &Option<String>) -> Self {
         let cases = stats
             .results
             .iter()
             .map(|r| {
-                let score = match r.score() {
-                    &Ok(score) => score.get(),
-                    Err(_) => 0,
-                };
                 let error_message = r
                     .score()
                     .as_ref()
@@ -177,11 +596,15 @@ impl AllResultJson {
 
                 CaseResultJson::new(
                     r.test_case().seed(),
-                    score,
-                    *r.relative_score().as_ref().unwrap_or(&0.0),
+                    r.score_or_zero(),
+                    r.relative_score_or_zero(),
                     r.execution_time().as_secs_f64(),
+                    r.wall_time().as_secs_f64(),
                     error_message,
                 )
+                .with_error_type(r.score().as_ref().err().map(|e| e.kind()))
+                .with_unscored(!r.has_score())
+                .with_sub_scores(r.sub_scores())
             })
             .collect();
         let wa_seeds = stats
@@ -206,25 +629,94 @@ impl AllResultJson {
             wa_seeds,
             cases,
             tag_name: tag_name.clone(),
+            hostname: System::host_name(),
+            cpu_model: get_cpu_model(),
+            threads: (stats.threads > 0).then_some(stats.threads),
+            pahcer_version: Some(env!("CARGO_PKG_VERSION").to_string()),
+            config_version: None,
         }
     }
+
+    pub(super) fn with_config_version(mut self, config_version: &str) -> Self {
+        self.config_version = Some(config_version.to_string());
+        self
+    }
+
+    /// 平均の計算に用いるケース数（スコアのないケースを除く）
+    pub(super) fn scored_case_count(&self) -> usize {
+        self.case_count - self.cases.iter().filter(|c| c.unscored).count()
+    }
+}
+
+#[cfg(test)]
+impl AllResultJson {
+    /// seedとスコアの組から、テスト用の実行結果を生成する（全てのケースがACとなる）
+    pub(super) fn from_scores(scores: &[(u64, f64)]) -> Self {
+        let cases = scores
+            .iter()
+            .map(|&(seed, score)| CaseResultJson::new(seed, score, 0.0, 0.0, 0.0, String::new()))
+            .collect();
+
+        Self {
+            start_time: Local::now(),
+            case_count: scores.len(),
+            total_score: scores.iter().map(|&(_, score)| score).sum(),
+            total_score_log10: 0.0,
+            total_relative_score: 0.0,
+            max_execution_time: 0.0,
+            comment: String::new(),
+            tag_name: None,
+            hostname: None,
+            cpu_model: None,
+            threads: None,
+            pahcer_version: None,
+            config_version: None,
+            wa_seeds: vec![],
+            cases,
+        }
+    }
+}
+
+/// CPUのモデル名を取得する
+fn get_cpu_model() -> Option<String> {
+    let refresh_kind = RefreshKind::nothing().with_cpu(CpuRefreshKind::nothing());
+    let system = System::new_with_specifics(refresh_kind);
+    system
+        .cpus()
+        .first()
+        .map(|cpu| cpu.brand().trim().to_string())
+        .filter(|brand| !brand.is_empty())
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub(super) struct CaseResultJson {
     pub(super) seed: u64,
-    pub(super) score: u64,
+    #[serde(serialize_with = "serialize_score")]
+    pub(super) score: f64,
     pub(super) relative_score: f64,
     pub(super) execution_time: f64,
+    /// 過去のバージョンで出力されたファイルには存在しない
+    #[serde(default)]
+    pub(super) wall_time: f64,
     pub(super) error_message: String,
+    /// 失敗の種類（過去のバージョンで出力されたファイルには存在しない）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(super) error_type: Option<CaseErrorKind>,
+    /// 名前付きのスコアパターンで抽出されたサブスコア
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(super) sub_scores: BTreeMap<String, f64>,
+    /// 期待される出力との一致のみで正解とした、スコアのないケースか（スコアは0として保存する）
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(super) unscored: bool,
 }
 
 impl CaseResultJson {
     fn new(
         seed: u64,
-        score: u64,
+        score: f64,
         relative_score: f64,
         execution_time: f64,
+        wall_time: f64,
         error_message: String,
     ) -> Self {
         Self {
@@ -232,35 +724,362 @@ impl CaseResultJson {
             score,
             relative_score,
             execution_time,
+            wall_time,
             error_message,
+            error_type: None,
+            sub_scores: BTreeMap::new(),
+            unscored: false,
         }
     }
+
+    /// 比較できるスコアを持つケースであれば、そのスコアを返す（失敗したケースとスコアのないケースは `None` ）
+    pub(super) fn scored(&self) -> Option<Score> {
+        Score::new(self.score).filter(|_| !self.unscored)
+    }
+
+    fn with_unscored(mut self, unscored: bool) -> Self {
+        self.unscored = unscored;
+        self
+    }
+
+    fn with_error_type(mut self, error_type: Option<CaseErrorKind>) -> Self {
+        self.error_type = error_type;
+        self
+    }
+
+    /// 出力順を固定するため、名前順に並べて保持する
+    fn with_sub_scores(mut self, sub_scores: &HashMap<String, f64>) -> Self {
+        self.sub_scores = sub_scores
+            .iter()
+            .map(|(name, &score)| (name.clone(), score))
+            .collect();
+        self
+    }
 }
 
 pub(super) fn get_json_dir_path(dir_path: impl AsRef<OsStr>) -> PathBuf {
     Path::new(&dir_path).join("json")
 }
 
-pub(super) fn get_json_log_path(dir_path: impl AsRef<OsStr>, stats: &TestStats) -> PathBuf {
-    let file_name = format!("result_{}.json", stats.start_time.format("%Y%m%d_%H%M%S"));
+/// `compress` が指定された場合はgzip圧縮したファイル（ `.json.gz` ）のパスを返す
+pub(super) fn get_json_log_path(
+    dir_path: impl AsRef<OsStr>,
+    stats: &TestStats,
+    compress: bool,
+) -> PathBuf {
+    let extension = if compress {
+        JSON_GZ_EXTENSION
+    } else {
+        JSON_EXTENSION
+    };
+    let file_name = format!(
+        "result_{}{extension}",
+        stats.start_time.format("%Y%m%d_%H%M%S")
+    );
     get_json_dir_path(dir_path).join(file_name)
 }
 
+fn is_gzip_path(path: &Path) -> bool {
+    path.to_str()
+        .is_some_and(|path| path.ends_with(JSON_GZ_EXTENSION))
+}
+
 pub(super) fn save_json_log(
     path: impl AsRef<Path>,
     stats: &TestStats,
     comment: &str,
     tag_name: &Option<String>,
+    config_version: &str,
 ) -> Result<()> {
     create_parent_dir(&path)?;
-    let file = File::create(path)?;
+    let file = File::create(&path)?;
     let writer = BufWriter::new(file);
-    let json = AllResultJson::new(stats, comment, tag_name);
-    serde_json::to_writer_pretty(writer, &json)?;
+    let json = AllResultJson::new(stats, comment, tag_name).with_config_version(config_version);
+
+    if is_gzip_path(path.as_ref()) {
+        let mut encoder = GzEncoder::new(writer, Compression::default());
+        serde_json::to_writer_pretty(&mut encoder, &json)?;
+        encoder.finish()?.flush()?;
+    } else {
+        serde_json::to_writer_pretty(writer, &json)?;
+    }
+
+    update_latest_json(path.as_ref()).with_context(|| {
+        format!(
+            "Failed to update the latest result for {}",
+            path.as_ref().display()
+        )
+    })
+}
+
+/// JSONディレクトリの `latest.json` （gzip圧縮時は `latest.json.gz` ）を `path` の実行結果に更新する
+/// シンボリックリンクを作成できる環境ではシンボリックリンクとし、それ以外ではコピーする
+fn update_latest_json(path: &Path) -> Result<()> {
+    let Some(dir) = path.parent() else {
+        return Ok(());
+    };
+
+    // 圧縮の有無を切り替えた場合に古い方が残らないよう、両方とも削除する
+    for extension in [JSON_EXTENSION, JSON_GZ_EXTENSION] {
+        let latest = dir.join(format!("{LATEST_JSON_STEM}{extension}"));
+
+        if latest.symlink_metadata().is_ok() {
+            std::fs::remove_file(&latest)?;
+        }
+    }
+
+    let extension = if is_gzip_path(path) {
+        JSON_GZ_EXTENSION
+    } else {
+        JSON_EXTENSION
+    };
+    let latest = dir.join(format!("{LATEST_JSON_STEM}{extension}"));
+
+    // 同じディレクトリ内のファイルを指すため、相対パスのシンボリックリンクとする
+    #[cfg(unix)]
+    if let Some(file_name) = path.file_name() {
+        if std::os::unix::fs::symlink(file_name, &latest).is_ok() {
+            return Ok(());
+        }
+    }
+
+    std::fs::copy(path, &latest)?;
+    Ok(())
+}
+
+pub(super) fn get_csv_log_path(dir_path: impl AsRef<OsStr>, stats: &TestStats) -> PathBuf {
+    let file_name = format!("result_{}.csv", stats.start_time.format("%Y%m%d_%H%M%S"));
+    Path::new(&dir_path).join("csv").join(file_name)
+}
+
+pub(super) fn save_csv_log(path: impl AsRef<Path>, stats: &TestStats) -> Result<()> {
+    create_parent_dir(&path)?;
+    let file = File::create(path)?;
+    let mut writer = BufWriter::new(file);
+    save_csv_log_inner(&mut writer, stats)?;
 
     Ok(())
 }
 
+fn save_csv_log_inner(writer: &mut impl Write, stats: &TestStats) -> Result<()> {
+    writeln!(
+        writer,
+        "seed,score,relative_score,execution_time,wall_time,error_message"
+    )?;
+
+    for result in stats.results.iter() {
+        // スコアのないケースはスコア・相対スコアを空欄とする
+        let (score, relative_score) = if result.has_score() {
+            (
+                result.score_or_zero().to_string(),
+                result.relative_score_or_zero().to_string(),
+            )
+        } else {
+            (String::new(), String::new())
+        };
+        let error_message = result
+            .score()
+            .as_ref()
+            .err()
+            .map(|e| e.to_string())
+            .unwrap_or_default();
+
+        writeln!(
+            writer,
+            "{},{},{},{},{},{}",
+            result.test_case().seed(),
+            score,
+            relative_score,
+            result.execution_time().as_secs_f64(),
+            result.wall_time().as_secs_f64(),
+            escape_csv_field(&error_message)
+        )?;
+    }
+
+    Ok(())
+}
+
+pub(super) fn get_html_report_path(dir_path: impl AsRef<OsStr>, stats: &TestStats) -> PathBuf {
+    let file_name = format!("result_{}.html", stats.start_time.format("%Y%m%d_%H%M%S"));
+    Path::new(&dir_path).join("html").join(file_name)
+}
+
+/// 外部リソースに依存しない単一のHTMLファイルとしてレポートを出力する
+pub(super) fn save_html_report(
+    path: impl AsRef<Path>,
+    stats: &TestStats,
+    comment: &str,
+    tag_name: &Option<String>,
+    number_format: NumberFormat,
+) -> Result<()> {
+    create_parent_dir(&path)?;
+    let file = File::create(path)?;
+    let mut writer = BufWriter::new(file);
+    save_html_report_inner(&mut writer, stats, comment, tag_name, number_format)?;
+
+    Ok(())
+}
+
+fn save_html_report_inner(
+    writer: &mut impl Write,
+    stats: &TestStats,
+    comment: &str,
+    tag_name: &Option<String>,
+    number_format: NumberFormat,
+) -> Result<()> {
+    let nonzero2 = NonZeroUsize::new(2).unwrap();
+    let case_count = stats.results.len();
+    let ac_count = stats.results.iter().filter(|r| r.score().is_ok()).count();
+    let start_time = stats
+        .start_time
+        .to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+
+    let summary_items = [
+        ("Start Time", start_time.clone()),
+        ("Comment", comment.to_string()),
+        ("Tag", tag_name.clone().unwrap_or_default()),
+        ("Accepted", format!("{ac_count} / {case_count}")),
+        ("Total Score", number_format.score(stats.score_sum)),
+        (
+            "Average Score",
+            number_format.float(stats.average_score(), nonzero2),
+        ),
+        (
+            "Average Relative Score",
+            format!("{:.3}", stats.average_relative_score()),
+        ),
+    ];
+    let summary = summary_items
+        .iter()
+        .map(|(key, value)| format!("<tr><th>{key}</th><td>{}</td></tr>", escape_html(value)))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let rows = stats
+        .results
+        .iter()
+        .map(|r| {
+            let seed = r.test_case().seed();
+            let score = r.score_or_zero();
+            let relative_score = r.relative_score_or_zero();
+            let score_cell = if r.has_score() {
+                number_format.score(score)
+            } else {
+                "-".to_string()
+            };
+            let execution_time = r.execution_time().as_millis();
+            let error_message = r
+                .score()
+                .as_ref()
+                .err()
+                .map(|e| e.to_string())
+                .unwrap_or_default();
+            let class = if r.score().is_err() {
+                " class=\"failed\""
+            } else {
+                ""
+            };
+
+            format!(
+                "<tr{class}><td class=\"num\" data-value=\"{seed}\">{seed:04}</td><td class=\"num\" data-value=\"{score}\">{}</td><td class=\"num\" data-value=\"{relative_score}\">{relative_score:.3}</td><td class=\"num\" data-value=\"{execution_time}\">{}</td><td data-value=\"{error}\">{error}</td></tr>",
+                score_cell,
+                number_format.integer(execution_time),
+                error = escape_html(&error_message),
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let title = escape_html(&format!("pahcer report {start_time}"));
+    let html = HTML_REPORT_TEMPLATE
+        .replace("{{TITLE}}", &title)
+        .replace("{{SUMMARY}}", &summary)
+        .replace("{{CHART}}", &render_relative_score_chart(stats))
+        .replace("{{ROWS}}", &rows);
+
+    write!(writer, "{html}")?;
+
+    Ok(())
+}
+
+/// seedと相対スコアの散布図をSVGで描画する
+fn render_relative_score_chart(stats: &TestStats) -> String {
+    const WIDTH: f64 = 800.0;
+    const HEIGHT: f64 = 320.0;
+    const MARGIN: f64 = 40.0;
+
+    let points = stats
+        .results
+        .iter()
+        .filter(|r| r.has_score())
+        .map(|r| {
+            (
+                r.test_case().seed(),
+                r.relative_score_or_zero(),
+                r.score().is_ok(),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let min_seed = points.iter().map(|p| p.0).min().unwrap_or(0);
+    let max_seed = points.iter().map(|p| p.0).max().unwrap_or(0);
+    let seed_range = (max_seed - min_seed).max(1) as f64;
+    let max_relative_score = points.iter().map(|p| p.1).fold(100.0, f64::max);
+
+    let to_x = |seed: u64| MARGIN + (seed - min_seed) as f64 / seed_range * (WIDTH - 2.0 * MARGIN);
+    let to_y = |score: f64| HEIGHT - MARGIN - score / max_relative_score * (HEIGHT - 2.0 * MARGIN);
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{WIDTH}\" height=\"{HEIGHT}\" viewBox=\"0 0 {WIDTH} {HEIGHT}\">\n"
+    );
+
+    // 軸と相対スコア100の基準線
+    svg.push_str(&format!(
+        "<line x1=\"{MARGIN}\" y1=\"{y}\" x2=\"{x}\" y2=\"{y}\" stroke=\"#888\"/>\n<line x1=\"{MARGIN}\" y1=\"{MARGIN}\" x2=\"{MARGIN}\" y2=\"{y}\" stroke=\"#888\"/>\n",
+        x = WIDTH - MARGIN,
+        y = HEIGHT - MARGIN,
+    ));
+    svg.push_str(&format!(
+        "<line x1=\"{MARGIN}\" y1=\"{y:.1}\" x2=\"{x}\" y2=\"{y:.1}\" stroke=\"#4a90d9\" stroke-dasharray=\"4 4\"/>\n<text x=\"4\" y=\"{y:.1}\" font-size=\"12\">100</text>\n",
+        x = WIDTH - MARGIN,
+        y = to_y(100.0),
+    ));
+    svg.push_str(&format!(
+        "<text x=\"{MARGIN}\" y=\"{y}\" font-size=\"12\">{min_seed}</text>\n<text x=\"{x}\" y=\"{y}\" font-size=\"12\" text-anchor=\"end\">{max_seed}</text>\n",
+        x = WIDTH - MARGIN,
+        y = HEIGHT - MARGIN + 16.0,
+    ));
+
+    for (seed, relative_score, accepted) in points {
+        let color = if accepted { "#2e7d32" } else { "#e65100" };
+        svg.push_str(&format!(
+            "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"3\" fill=\"{color}\"><title>seed {seed:04}: {relative_score:.3}</title></circle>\n",
+            to_x(seed),
+            to_y(relative_score),
+        ));
+    }
+
+    svg.push_str("</svg>");
+    svg
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// カンマや改行、ダブルクオートを含むフィールドをダブルクオートで囲む
+fn escape_csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
 fn create_parent_dir(path: impl AsRef<Path>) -> Result<()> {
     if let Some(parent) = path.as_ref().parent() {
         std::fs::create_dir_all(parent)?;
@@ -269,19 +1088,482 @@ fn create_parent_dir(path: impl AsRef<Path>) -> Result<()> {
     Ok(())
 }
 
+/// 実行結果のJSONファイルを読み込む（gzip圧縮されたファイルにも対応）
 pub(super) fn load_result_json(path: &Path) -> Result<AllResultJson> {
     let file = File::open(path)?;
     let reader = BufReader::new(file);
-    let result: AllResultJson = serde_json::from_reader(reader)?;
+    let result: AllResultJson = if is_gzip_path(path) {
+        serde_json::from_reader(BufReader::new(GzDecoder::new(reader)))?
+    } else {
+        serde_json::from_reader(reader)?
+    };
     Ok(result)
 }
 
+/// JSONディレクトリ内の結果ファイルのパスを新しい順に列挙する
+/// `latest.json` は他の結果ファイルと重複するため含めない
+pub(super) fn list_result_json_paths(dir_path: impl AsRef<OsStr>) -> Result<Vec<PathBuf>> {
+    let json_dir = get_json_dir_path(dir_path);
+
+    ensure!(
+        json_dir.exists(),
+        "No results found. JSON directory does not exist: {}",
+        json_dir.display()
+    );
+
+    let mut json_files = vec![];
+
+    for entry in std::fs::read_dir(&json_dir)? {
+        let entry = entry?;
+        let path = entry.path();
+
+        if let Some(file_name) = path.file_name().and_then(|n| n.to_str()) {
+            let is_json =
+                file_name.ends_with(JSON_EXTENSION) || file_name.ends_with(JSON_GZ_EXTENSION);
+
+            if file_name.starts_with("result_") && is_json {
+                json_files.push(path);
+            }
+        }
+    }
+
+    // ファイル名でソート（新しい順）
+    json_files.sort_by(|a, b| {
+        let name_a = a.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        let name_b = b.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        name_b.cmp(name_a)
+    });
+
+    Ok(json_files)
+}
+
+/// 指定されたタグ名の実行結果のうち最新のものを読み込む
+/// タグ名はプレフィックスを省略して指定できる
+pub(super) fn find_result_json_by_tag(
+    dir_path: impl AsRef<OsStr>,
+    tag: &str,
+    git_settings: &Git,
+) -> Result<AllResultJson> {
+    find_result_json_with_path_by_tag(dir_path, tag, git_settings).map(|(_, result)| result)
+}
+
+/// 指定されたタグ名の実行結果のうち最新のものを、ファイルのパスとともに読み込む
+fn find_result_json_with_path_by_tag(
+    dir_path: impl AsRef<OsStr>,
+    tag: &str,
+    git_settings: &Git,
+) -> Result<(PathBuf, AllResultJson)> {
+    let prefixed_tag = git_settings.prefixed_tag(tag);
+
+    for path in list_result_json_paths(dir_path)? {
+        let Ok(result) = load_result_json(&path) else {
+            continue;
+        };
+
+        if let Some(tag_name) = &result.tag_name {
+            if tag_name == tag || tag_name == &prefixed_tag {
+                return Ok((path, result));
+            }
+        }
+    }
+
+    bail!("No result found for tag: {tag}")
+}
+
+/// 最新の実行結果を読み込む
+pub(super) fn load_latest_result_json(dir_path: impl AsRef<OsStr>) -> Result<AllResultJson> {
+    let path = list_result_json_paths(dir_path)?
+        .into_iter()
+        .next()
+        .context("No results found.")?;
+    load_result_json(&path).with_context(|| format!("Failed to load {}", path.display()))
+}
+
+/// タグ名または実行日時（ `20240131_123456` 形式。前方一致）で指定された実行結果を読み込む
+/// 両方に該当する場合はタグ名を優先する
+pub(super) fn find_result_json(
+    dir_path: impl AsRef<OsStr>,
+    run: &str,
+    git_settings: &Git,
+) -> Result<AllResultJson> {
+    find_result_json_with_path(dir_path, run, git_settings).map(|(_, result)| result)
+}
+
+/// `find_result_json` と同様に実行結果を読み込み、ファイルのパスとともに返す
+pub(super) fn find_result_json_with_path(
+    dir_path: impl AsRef<OsStr>,
+    run: &str,
+    git_settings: &Git,
+) -> Result<(PathBuf, AllResultJson)> {
+    if let Ok(found) = find_result_json_with_path_by_tag(&dir_path, run, git_settings) {
+        return Ok(found);
+    }
+
+    let prefix = format!("result_{run}");
+    let path = list_result_json_paths(&dir_path)?.into_iter().find(|path| {
+        path.file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| n.starts_with(&prefix))
+    });
+
+    match path {
+        Some(path) => {
+            let result = load_result_json(&path)?;
+            Ok((path, result))
+        }
+        None => bail!("No result found for tag or time: {run}"),
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::runner::single::{Objective, TestCase, TestResult};
+    use crate::runner::single::{CaseError, Objective, TestCase, TestResult};
     use chrono::DateTime;
-    use std::{num::NonZero, time::Duration};
+    use std::time::Duration;
+
+    #[test]
+    fn test_load_best_scores_migration() -> Result<()> {
+        let path =
+            std::env::temp_dir().join(format!("pahcer_best_scores_{}.json", std::process::id()));
+        std::fs::write(&path, r#"{"0000": 100, "0001": 0, "0002": 2.5}"#)?;
+
+        let mut records = load_best_score_records(&path)?;
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[&0].score(), Score::new(100.0).unwrap());
+        assert!(records[&0].history().is_empty());
+
+        let time = Local::now();
+        let record = records.get_mut(&0).unwrap();
+        record.update(
+            Score::new(200.0).unwrap(),
+            time,
+            Some("pahcer/foo".to_string()),
+        );
+        record.update(Score::new(200.0).unwrap(), time, None);
+        save_best_scores(&path, records)?;
+
+        let records = load_best_score_records(&path)?;
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(records[&0].score(), Score::new(200.0).unwrap());
+        assert_eq!(records[&0].history().len(), 1);
+        assert_eq!(
+            records[&0].history()[0].tag_name.as_deref(),
+            Some("pahcer/foo")
+        );
+        assert_eq!(records[&2].score(), Score::new(2.5).unwrap());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_best_scores_merges_concurrent_updates() -> Result<()> {
+        let dir =
+            std::env::temp_dir().join(format!("pahcer_update_best_scores_{}", std::process::id()));
+        let path = dir.join("best_scores.json");
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let time = Local::now();
+        let threads = (0..8u64)
+            .map(|seed| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    update_best_scores(&path, |records| {
+                        let score = Score::new((100 + seed) as f64).unwrap();
+                        records.insert(seed, BestScoreRecord::new(score, time, None));
+                    })
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for thread in threads {
+            thread.join().unwrap()?;
+        }
+
+        let records = load_best_score_records(&path)?;
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(records.len(), 8);
+
+        for seed in 0..8 {
+            assert_eq!(
+                records[&seed].score(),
+                Score::new((100 + seed) as f64).unwrap()
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_log_gzip() -> Result<()> {
+        let stats = multi::TestStats::new(
+            vec![TestResult::new(
+                TestCase::new(0, None, Objective::Max),
+                Ok(Score::new(1000.0).unwrap()),
+                Duration::from_millis(1500),
+            )],
+            Local::now(),
+        );
+        let dir = std::env::temp_dir().join(format!("pahcer_json_gz_{}", std::process::id()));
+        let tag_name = Some("pahcer/foo".to_string());
+
+        let path = get_json_log_path(&dir, &stats, true);
+        assert!(path.to_str().unwrap().ends_with(".json.gz"));
+        save_json_log(&path, &stats, "", &tag_name, "0.1.0")?;
+
+        let paths = list_result_json_paths(&dir)?;
+        let result = find_result_json(&dir, "foo", &Git::default());
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(paths, vec![path]);
+        let result = result?;
+        assert_eq!(result.cases[0].score, 1000.0);
+        assert_eq!(result.config_version.as_deref(), Some("0.1.0"));
+        assert_eq!(
+            result.pahcer_version.as_deref(),
+            Some(env!("CARGO_PKG_VERSION"))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_latest_json() -> Result<()> {
+        let stats_at = |sec: u32| {
+            let start_time = chrono::TimeZone::with_ymd_and_hms(&Local, 2024, 1, 1, 0, 0, sec);
+            multi::TestStats::from_scores(&[(0, sec as f64 + 1.0)], start_time.unwrap())
+        };
+        let dir = std::env::temp_dir().join(format!("pahcer_latest_json_{}", std::process::id()));
+        let json_dir = get_json_dir_path(&dir);
+
+        let path_a = get_json_log_path(&dir, &stats_at(0), false);
+        save_json_log(&path_a, &stats_at(0), "", &None, "0.1.0")?;
+        let latest_a = load_result_json(&json_dir.join("latest.json"))?;
+
+        let path_b = get_json_log_path(&dir, &stats_at(1), false);
+        save_json_log(&path_b, &stats_at(1), "", &None, "0.1.0")?;
+        let latest_b = load_result_json(&json_dir.join("latest.json"))?;
+
+        let path_c = get_json_log_path(&dir, &stats_at(2), true);
+        save_json_log(&path_c, &stats_at(2), "", &None, "0.1.0")?;
+        let latest_c = load_result_json(&json_dir.join("latest.json.gz"))?;
+        let stale_exists = json_dir.join("latest.json").exists();
+
+        let paths = list_result_json_paths(&dir)?;
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(latest_a.cases[0].score, 1.0);
+        assert_eq!(latest_b.cases[0].score, 2.0);
+        assert_eq!(latest_c.cases[0].score, 3.0);
+        assert!(!stale_exists);
+        assert_eq!(paths, vec![path_c, path_b, path_a]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_all_runs_best_scores() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("pahcer_all_runs_{}", std::process::id()));
+        let save = |sec: u32, scores: &[(u64, f64)]| {
+            let start_time = chrono::TimeZone::with_ymd_and_hms(&Local, 2024, 1, 1, 0, 0, sec);
+            let stats = multi::TestStats::from_scores(scores, start_time.unwrap());
+            save_json_log(
+                get_json_log_path(&dir, &stats, false),
+                &stats,
+                "",
+                &None,
+                "0.1.0",
+            )
+        };
+
+        save(0, &[(0, 10.0), (1, 50.0), (2, 0.0)])?;
+        save(1, &[(0, 30.0), (1, 20.0), (2, 0.0)])?;
+        let first = load_all_runs_best_scores(&dir, Objective::Max)?;
+        let cache_exists = dir.join(ALL_RUNS_BEST_CACHE_FILE).exists();
+
+        // キャッシュ済みのファイルに加えて、新しいファイルのみが読み込まれる
+        save(2, &[(0, 5.0), (1, 60.0), (2, 7.0)])?;
+        let second = load_all_runs_best_scores(&dir, Objective::Max)?;
+        let min = load_all_runs_best_scores(&dir, Objective::Min)?;
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert!(cache_exists);
+        assert_eq!(first.len(), 2);
+        assert_eq!(first[&0].get(), 30.0);
+        assert_eq!(first[&1].get(), 50.0);
+        assert_eq!(second[&0].get(), 30.0);
+        assert_eq!(second[&1].get(), 60.0);
+        assert_eq!(second[&2].get(), 7.0);
+        assert_eq!(min[&0].get(), 5.0);
+        assert_eq!(min[&1].get(), 20.0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_seeds() {
+        let seeds = parse_seeds("3\n\n# comment\n 7 \n42\n").unwrap();
+        assert_eq!(seeds, vec![3, 7, 42]);
+
+        assert!(parse_seeds("3\nfoo\n").is_err());
+    }
+
+    #[test]
+    fn test_case_result_json_error_type() {
+        let stats = multi::TestStats::new(
+            vec![
+                TestResult::for_seed(0, Err(CaseError::Tle)),
+                TestResult::for_seed(1, Ok(Score::new(1.0).unwrap())),
+            ],
+            Local::now(),
+        );
+        let json = serde_json::to_value(AllResultJson::new(&stats, "", &None)).unwrap();
+
+        assert_eq!(json["cases"][0]["error_type"], "tle");
+        assert_eq!(json["cases"][0]["error_message"], "TLE");
+        assert!(json["cases"][1].get("error_type").is_none());
+
+        // 種類が記録されていない過去のファイルも読み込める
+        let case: CaseResultJson = serde_json::from_str(
+            r#"{"seed":0,"score":0,"relative_score":0.0,"execution_time":0.0,"error_message":"TLE"}"#,
+        )
+        .unwrap();
+        assert_eq!(case.error_type, None);
+    }
+
+    #[test]
+    fn test_case_result_json_unscored() {
+        let stats = multi::TestStats::new(
+            vec![
+                TestResult::new_unscored(TestCase::new(0, None, Objective::Max), Duration::ZERO),
+                TestResult::for_seed(1, Ok(Score::new(300.0).unwrap())),
+            ],
+            Local::now(),
+        );
+
+        // スコアのないケースは合計にも平均にも含めない
+        assert_eq!(stats.score_sum, 300.0);
+        assert_eq!(stats.average_score(), 300.0);
+        assert_eq!(stats.average_relative_score(), 100.0);
+
+        let result = AllResultJson::new(&stats, "", &None);
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(json["cases"][0]["score"], 0);
+        assert_eq!(json["cases"][0]["unscored"], true);
+        assert!(json["cases"][1].get("unscored").is_none());
+        assert_eq!(result.scored_case_count(), 1);
+
+        let result: AllResultJson = serde_json::from_value(json).unwrap();
+        assert_eq!(result.cases[0].scored(), None);
+        assert_eq!(result.cases[1].scored(), Score::new(300.0));
+    }
+
+    #[test]
+    fn test_parse_weights() {
+        let weights = parse_weights("seed,weight\n0,2\n1\t0.5\n2,0\n").unwrap();
+        assert_eq!(weights.len(), 3);
+        assert_eq!(weights[&0], 2.0);
+        assert_eq!(weights[&1], 0.5);
+        assert_eq!(weights[&2], 0.0);
+
+        assert!(parse_weights("0,-1\n").is_err());
+        assert!(parse_weights("0,nan\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_reference_scores() {
+        let scores =
+            parse_reference_scores("seed,score\n0,1234\n\n# comment\n1\t 56.5\n2,0\n").unwrap();
+        assert_eq!(
+            scores,
+            HashMap::from([
+                (0, Score::new(1234.0).unwrap()),
+                (1, Score::new(56.5).unwrap()),
+            ])
+        );
+
+        assert!(parse_reference_scores("0,1\nfoo,2\n").is_err());
+        assert!(parse_reference_scores("0,1,2\n").is_err());
+    }
+
+    #[test]
+    fn save_csv_log() -> Result<()> {
+        let mut buf = vec![];
+        let stats = multi::TestStats::new(
+            vec![
+                TestResult::new(
+                    TestCase::new(0, Score::new(500.0), Objective::Max),
+                    Ok(Score::new(1000.0).unwrap()),
+                    Duration::from_millis(1500),
+                ),
+                TestResult::new(
+                    TestCase::new(1, None, Objective::Max),
+                    Err(CaseError::RuntimeError(
+                        "Failed to run, \"exit status: 1\"".to_string(),
+                    )),
+                    Duration::ZERO,
+                ),
+            ],
+            Local::now(),
+        );
+
+        save_csv_log_inner(&mut buf, &stats)?;
+
+        let expected = r#"seed,score,relative_score,execution_time,wall_time,error_message
+0,1000,200,1.5,1.5,
+1,0,0,0,0,"Failed to run, ""exit status: 1"""
+"#;
+        let actual = String::from_utf8(buf).unwrap();
+        assert_eq!(actual, expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn save_html_report() -> Result<()> {
+        let mut buf = vec![];
+        let stats = multi::TestStats::new(
+            vec![
+                TestResult::new(
+                    TestCase::new(0, Score::new(500.0), Objective::Max),
+                    Ok(Score::new(1000.0).unwrap()),
+                    Duration::from_millis(1500),
+                ),
+                TestResult::for_seed(1, Err(CaseError::RuntimeError("<failed>".to_string()))),
+            ],
+            Local::now(),
+        );
+
+        save_html_report_inner(
+            &mut buf,
+            &stats,
+            "a & b",
+            &Some("pahcer/foo".to_string()),
+            NumberFormat::default(),
+        )?;
+        let actual = String::from_utf8(buf).unwrap();
+
+        assert!(!actual.contains("{{"));
+        assert!(!actual.contains("<script src"));
+        assert!(actual.contains("<td>a &amp; b</td>"));
+        assert!(actual.contains("<td>pahcer/foo</td>"));
+        assert!(actual.contains("<td class=\"num\" data-value=\"1000\">1,000</td>"));
+        assert!(actual.contains("&lt;failed&gt;"));
+        assert_eq!(actual.matches("<circle").count(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_truncate_summary_rows() {
+        let contents = "header\n---\nrow1\nrow2\nrow3\n";
+        assert_eq!(
+            truncate_summary_rows(contents, 2).as_deref(),
+            Some("header\n---\nrow2\nrow3\n")
+        );
+        assert_eq!(truncate_summary_rows(contents, 3), None);
+        assert_eq!(truncate_summary_rows("header\n---\n", 1), None);
+    }
 
     #[test]
     fn save_summary_log_no_file() -> Result<()> {
@@ -294,12 +1576,12 @@ mod test {
             vec![
                 TestResult::new(
                     TestCase::new(0, None, Objective::Max),
-                    Ok(NonZero::new(1000).unwrap()),
+                    Ok(Score::new(1000.0).unwrap()),
                     Duration::from_millis(1000),
                 ),
                 TestResult::new(
                     TestCase::new(1, None, Objective::Max),
-                    Ok(NonZero::new(10000).unwrap()),
+                    Ok(Score::new(10000.0).unwrap()),
                     Duration::from_millis(100),
                 ),
             ],
@@ -307,7 +1589,7 @@ mod test {
         );
 
         save_summary_header(&mut buf)?;
-        save_summary_log_inner(&mut buf, &stats, "hoge")?;
+        save_summary_log_inner(&mut buf, &stats, "hoge", NumberFormat::default())?;
 
         let expected = format!(
 "Time                      | Cases | Total Score      | Avg. Score       | Total log10  | Avg. log10  | Comment
@@ -1,39 +1,177 @@
-use super::io::{load_result_json, AllResultJson};
+use super::io::{load_result_json, AllResultJson, CaseResultJson};
 use crate::runner::io;
-use crate::runner::single::Objective;
-use crate::settings::Settings;
-use anyhow::{ensure, Result};
+use crate::runner::single::{clamp_relative_score, Objective, Score};
+use crate::settings::{Git, Problem, Settings};
+use crate::util::NumberFormat;
+use anyhow::Result;
+use chrono::{DateTime, Local, NaiveDate};
+use clap::ValueEnum;
 use colored::Colorize as _;
-use std::collections::HashMap;
-use std::fs;
-use std::num::NonZeroU64;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
 use tabled::{
+    builder::Builder,
     settings::{object::Columns, Alignment, Style},
-    Table, Tabled,
 };
 
-#[derive(Tabled)]
+/// 一覧表示する列
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(crate) enum ListColumn {
+    /// Start time of the run
+    #[value(name = "time")]
+    Time,
+    /// Number of accepted cases / all cases
+    #[value(name = "ac")]
+    AcTotal,
+    /// Average score
+    #[value(name = "avg")]
+    AvgScore,
+    /// Average relative score recalculated with the current best scores
+    #[value(name = "rel")]
+    AvgRelative,
+    /// Total score
+    #[value(name = "total")]
+    TotalScore,
+    /// Average score in log10 scale
+    #[value(name = "log10")]
+    AvgLog10,
+    /// Total relative score at the time of the run
+    #[value(name = "totalrel")]
+    TotalRelative,
+    /// Maximum execution time
+    #[value(name = "maxtime")]
+    MaxTime,
+    /// Tag name
+    #[value(name = "tag")]
+    Tag,
+    /// Comment
+    #[value(name = "comment")]
+    Comment,
+}
+
+impl ListColumn {
+    pub(crate) const DEFAULT: [ListColumn; 7] = [
+        ListColumn::Time,
+        ListColumn::AcTotal,
+        ListColumn::AvgScore,
+        ListColumn::AvgRelative,
+        ListColumn::MaxTime,
+        ListColumn::Tag,
+        ListColumn::Comment,
+    ];
+
+    const fn header(self) -> &'static str {
+        match self {
+            ListColumn::Time => "Time",
+            ListColumn::AcTotal => "AC/All",
+            ListColumn::AvgScore => "Avg Score",
+            ListColumn::AvgRelative => "Avg Rel.",
+            ListColumn::TotalScore => "Total Score",
+            ListColumn::AvgLog10 => "Avg log10",
+            ListColumn::TotalRelative => "Total Rel.",
+            ListColumn::MaxTime => "Max Time",
+            ListColumn::Tag => "Tag",
+            ListColumn::Comment => "Comment",
+        }
+    }
+
+    /// 数値の列は右寄せで表示する
+    const fn is_numeric(self) -> bool {
+        !matches!(
+            self,
+            ListColumn::Time | ListColumn::Tag | ListColumn::Comment
+        )
+    }
+}
+
+/// 一覧表示する実行結果の絞り込み条件
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ListFilter {
+    /// タグ名に含まれる文字列
+    pub(crate) tag: Option<String>,
+    /// この日付以降に実行された結果のみ表示する
+    pub(crate) since: Option<NaiveDate>,
+    /// この日付以前に実行された結果のみ表示する
+    pub(crate) until: Option<NaiveDate>,
+}
+
+impl ListFilter {
+    fn matches(&self, result: &AllResultJson) -> bool {
+        if let Some(tag) = &self.tag {
+            let Some(tag_name) = &result.tag_name else {
+                return false;
+            };
+
+            if !tag_name.contains(tag.as_str()) {
+                return false;
+            }
+        }
+
+        let date = result.start_time.date_naive();
+
+        if self.since.is_some_and(|since| date < since) {
+            return false;
+        }
+
+        if self.until.is_some_and(|until| date > until) {
+            return false;
+        }
+
+        true
+    }
+}
+
 struct ResultTableRow {
-    #[tabled(rename = "Time")]
     time: String,
-    #[tabled(rename = "AC/All")]
     ac_total: String,
-    #[tabled(rename = "Avg Score")]
     avg_score: String,
-    #[tabled(rename = "Avg Rel.")]
     avg_relative: String,
-    #[tabled(rename = "Max Time")]
+    total_score: String,
+    avg_log10: String,
+    total_relative: String,
     max_time: String,
-    #[tabled(rename = "Tag")]
     tag: String,
-    #[tabled(rename = "Comment")]
     comment: String,
 }
 
+impl ResultTableRow {
+    fn cell(&self, column: ListColumn) -> &str {
+        match column {
+            ListColumn::Time => &self.time,
+            ListColumn::AcTotal => &self.ac_total,
+            ListColumn::AvgScore => &self.avg_score,
+            ListColumn::AvgRelative => &self.avg_relative,
+            ListColumn::TotalScore => &self.total_score,
+            ListColumn::AvgLog10 => &self.avg_log10,
+            ListColumn::TotalRelative => &self.total_relative,
+            ListColumn::MaxTime => &self.max_time,
+            ListColumn::Tag => &self.tag,
+            ListColumn::Comment => &self.comment,
+        }
+    }
+}
+
 /// 過去のテスト結果をリスト表示する関数
-pub(super) fn list_past_results(settings: &Settings, limit: Option<usize>) -> Result<()> {
+pub(super) fn list_past_results(
+    settings: &Settings,
+    limit: Option<usize>,
+    filter: &ListFilter,
+    columns: &[ListColumn],
+    markdown: bool,
+    aggregate: bool,
+    json: bool,
+) -> Result<()> {
     // JSONファイルから結果を読み込む
-    let results = load_results(settings, limit)?;
+    let results = load_results(settings, limit, filter)?;
+    let version_warning = format_config_version_warning(&results, &settings.general.version);
+
+    // 分割して実行した結果をまとめて1つの実行結果として扱う
+    let results = if aggregate && !results.is_empty() {
+        let best_scores = load_best_scores(settings);
+        vec![aggregate_results(&results, &best_scores, &settings.problem)]
+    } else {
+        results
+    };
 
     // 絶対ベストスコア
     let best_avg_absolute_score = calculate_best_avg_absolute_score(settings, &results);
@@ -43,53 +181,116 @@ pub(super) fn list_past_results(settings: &Settings, limit: Option<usize>) -> Re
     let best_avg_relative_score =
         calculate_best_avg_relative_score(settings, &results, &best_scores);
 
-    // テーブル形式で結果を表示
-    print_table(
-        settings,
-        results,
-        best_avg_absolute_score,
-        best_scores,
-        best_avg_relative_score,
-    );
+    if json {
+        let summaries = results
+            .iter()
+            .map(|result| ResultSummaryJson::new(result, &best_scores, &settings.problem))
+            .collect::<Vec<_>>();
+        println!("{}", serde_json::to_string(&summaries)?);
+    } else {
+        // テーブル形式で結果を表示
+        print_table(
+            settings,
+            results,
+            best_avg_absolute_score,
+            best_scores,
+            best_avg_relative_score,
+            columns,
+            markdown,
+        );
+    }
+
+    // 表をそのまま貼り付けられるよう、警告は標準エラー出力に出す
+    if let Some(warning) = version_warning {
+        eprintln!("{}", warning.yellow());
+    }
 
     Ok(())
 }
 
-fn load_results(settings: &Settings, limit: Option<usize>) -> Result<Vec<AllResultJson>> {
-    let json_dir = io::get_json_dir_path(&settings.test.out_dir);
-
-    ensure!(
-        json_dir.exists(),
-        "No results found. JSON directory does not exist: {}",
-        json_dir.display()
-    );
-
-    let mut json_files = vec![];
-
-    for entry in fs::read_dir(&json_dir)? {
-        let entry = entry?;
-        let path = entry.path();
+/// `pahcer list --json` で出力する実行結果の要約（各ケースの結果は含めない）
+/// 平均相対スコアは表と同様に現在のベストスコアで計算し直す
+#[derive(Debug, Clone, Serialize)]
+struct ResultSummaryJson<'a> {
+    start_time: DateTime<Local>,
+    tag_name: Option<&'a str>,
+    comment: &'a str,
+    case_count: usize,
+    accepted_count: usize,
+    average_score: f64,
+    average_score_log10: f64,
+    average_relative_score: f64,
+    max_execution_time: f64,
+    wa_seeds: &'a [u64],
+    hostname: Option<&'a str>,
+    cpu_model: Option<&'a str>,
+    threads: Option<usize>,
+    pahcer_version: Option<&'a str>,
+    config_version: Option<&'a str>,
+}
 
-        if let Some(file_name) = path.file_name().and_then(|n| n.to_str()) {
-            if file_name.starts_with("result_") && file_name.ends_with(".json") {
-                json_files.push(path);
-            }
+impl<'a> ResultSummaryJson<'a> {
+    fn new(
+        result: &'a AllResultJson,
+        best_scores: &HashMap<u64, Score>,
+        problem: &Problem,
+    ) -> Self {
+        let scored_count = result.scored_case_count().max(1) as f64;
+
+        Self {
+            start_time: result.start_time,
+            tag_name: result.tag_name.as_deref(),
+            comment: &result.comment,
+            case_count: result.case_count,
+            accepted_count: result.case_count - result.wa_seeds.len(),
+            average_score: result.total_score / scored_count,
+            average_score_log10: result.total_score_log10 / scored_count,
+            average_relative_score: calc_average_relative_score(result, best_scores, problem),
+            max_execution_time: result.max_execution_time,
+            wa_seeds: &result.wa_seeds,
+            hostname: result.hostname.as_deref(),
+            cpu_model: result.cpu_model.as_deref(),
+            threads: result.threads,
+            pahcer_version: result.pahcer_version.as_deref(),
+            config_version: result.config_version.as_deref(),
         }
     }
+}
 
-    // ファイル名でソート（新しい順）
-    json_files.sort_by(|a, b| {
-        let name_a = a.file_name().and_then(|n| n.to_str()).unwrap_or("");
-        let name_b = b.file_name().and_then(|n| n.to_str()).unwrap_or("");
-        name_b.cmp(name_a)
-    });
+/// 現在の設定ファイルと異なるバージョンの設定ファイルで出力された結果があれば警告を返す
+/// バージョンが記録されていない過去の結果は対象外とする
+fn format_config_version_warning(results: &[AllResultJson], current: &str) -> Option<String> {
+    let mismatched = results
+        .iter()
+        .filter(|r| r.config_version.as_deref().is_some_and(|v| v != current))
+        .map(|r| {
+            format!(
+                "{} (config {})",
+                r.start_time.format("%m/%d %H:%M:%S"),
+                r.config_version.as_deref().unwrap_or_default()
+            )
+        })
+        .collect::<Vec<_>>();
 
-    // 制限数まで読み込み（Noneの場合は制限なし）
-    if let Some(limit_value) = limit {
-        json_files.truncate(limit_value);
+    if mismatched.is_empty() {
+        return None;
     }
 
-    // ファイルを読み込み
+    Some(format!(
+        "Warning: {} results were produced with a config version different from the current one ({current}): {}",
+        mismatched.len(),
+        mismatched.join(", ")
+    ))
+}
+
+fn load_results(
+    settings: &Settings,
+    limit: Option<usize>,
+    filter: &ListFilter,
+) -> Result<Vec<AllResultJson>> {
+    let json_files = io::list_result_json_paths(&settings.test.out_dir)?;
+
+    // ファイルを新しい順に読み込み、条件に合うものを制限数まで集める（Noneの場合は制限なし）
     let results = json_files
         .iter()
         .filter_map(|file| match load_result_json(file) {
@@ -99,22 +300,91 @@ fn load_results(settings: &Settings, limit: Option<usize>) -> Result<Vec<AllResu
                 None
             }
         })
+        .filter(|result| filter.matches(result))
+        .take(limit.unwrap_or(usize::MAX))
         .collect::<Vec<_>>();
 
     Ok(results)
 }
 
+/// 複数の実行結果をseedごとに最も良いスコアでまとめ、1つの実行結果とする
+/// 開始時刻は最も新しい実行のものとし、相対スコアは現在のベストスコアで計算し直す
+fn aggregate_results(
+    results: &[AllResultJson],
+    best_scores: &HashMap<u64, Score>,
+    problem: &Problem,
+) -> AllResultJson {
+    let mut cases = BTreeMap::new();
+
+    for case in results.iter().flat_map(|r| &r.cases) {
+        cases
+            .entry(case.seed)
+            .and_modify(|best: &mut CaseResultJson| {
+                // スコアが0のケースは失敗として扱い、成功したケースを優先する
+                // スコアのないケースは、失敗したケースよりは優先する
+                let is_better = match (case.scored(), best.scored()) {
+                    (Some(score), Some(best)) => match problem.primary_objective() {
+                        Objective::Max => score > best,
+                        Objective::Min => score < best,
+                    },
+                    (Some(_), None) => true,
+                    (None, Some(_)) => false,
+                    (None, None) => case.unscored && !best.unscored,
+                };
+
+                if is_better {
+                    *best = case.clone();
+                }
+            })
+            .or_insert_with(|| case.clone());
+    }
+
+    let cases = cases.into_values().collect::<Vec<_>>();
+    let start_time = results.iter().map(|r| r.start_time).max().unwrap();
+    let wa_seeds = cases
+        .iter()
+        .filter(|c| Score::new(c.score).is_none() && !c.unscored)
+        .map(|c| c.seed)
+        .collect();
+
+    let mut aggregated = AllResultJson {
+        start_time,
+        case_count: cases.len(),
+        total_score: cases.iter().map(|c| c.score).sum(),
+        total_score_log10: cases
+            .iter()
+            .filter_map(|c| c.scored().map(|s| s.get().log10()))
+            .sum(),
+        total_relative_score: 0.0,
+        max_execution_time: cases.iter().map(|c| c.execution_time).fold(0.0, f64::max),
+        comment: format!("Aggregated {} runs", results.len()),
+        tag_name: None,
+        hostname: None,
+        cpu_model: None,
+        threads: None,
+        pahcer_version: None,
+        config_version: None,
+        wa_seeds,
+        cases,
+    };
+
+    aggregated.total_relative_score =
+        calc_average_relative_score(&aggregated, best_scores, problem)
+            * aggregated.scored_case_count() as f64;
+    aggregated
+}
+
 fn calculate_best_avg_absolute_score(settings: &Settings, results: &[AllResultJson]) -> f64 {
     let best_avg_absolute_score = results
         .iter()
         .map(|result| {
-            if result.case_count > 0 {
-                result.total_score as f64 / result.case_count as f64
+            if result.scored_case_count() > 0 {
+                result.total_score / result.scored_case_count() as f64
             } else {
                 0.0
             }
         })
-        .max_by(|a, b| match settings.problem.objective {
+        .max_by(|a, b| match settings.problem.primary_objective() {
             Objective::Max => a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal),
             Objective::Min => b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal),
         })
@@ -123,59 +393,81 @@ fn calculate_best_avg_absolute_score(settings: &Settings, results: &[AllResultJs
     best_avg_absolute_score
 }
 
-fn load_best_scores(settings: &Settings) -> HashMap<u64, NonZeroU64> {
-    let best_score_path = io::get_best_score_path(&settings.test.out_dir);
+/// 過去の実行結果の平均相対スコアを与えられたベストスコアで再計算し、その最大値を返す
+/// 過去の実行結果が存在しない場合は `None` を返す
+pub(super) fn best_past_avg_relative_score(
+    settings: &Settings,
+    best_scores: &HashMap<u64, Score>,
+) -> Result<Option<f64>> {
+    // 初回の実行時はJSONディレクトリが存在しない
+    if !io::get_json_dir_path(&settings.test.out_dir).exists() {
+        return Ok(None);
+    }
+
+    let results = load_results(settings, None, &ListFilter::default())?;
+    let best = calculate_best_avg_relative_score(settings, &results, best_scores);
+    Ok((!best.is_nan()).then_some(best))
+}
+
+fn load_best_scores(settings: &Settings) -> HashMap<u64, Score> {
+    let best_score_path = io::get_best_score_path(settings);
     io::load_best_scores(&best_score_path).unwrap_or_else(|_| std::collections::HashMap::new())
 }
 
 fn calculate_best_avg_relative_score(
     settings: &Settings,
     results: &[AllResultJson],
-    best_scores: &HashMap<u64, NonZeroU64>,
+    best_scores: &HashMap<u64, Score>,
 ) -> f64 {
     let best_avg_relative_score = results
         .iter()
-        .map(|result| calc_average_relative_score(result, best_scores, settings.problem.objective))
+        .map(|result| calc_average_relative_score(result, best_scores, &settings.problem))
         .max_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
         .unwrap_or(f64::NAN);
 
     best_avg_relative_score
 }
 
-fn calc_average_relative_score(
+pub(super) fn calc_average_relative_score(
     result: &AllResultJson,
-    best_scores: &HashMap<u64, NonZeroU64>,
-    objective: Objective,
+    best_scores: &HashMap<u64, Score>,
+    problem: &Problem,
 ) -> f64 {
-    if result.case_count == 0 {
+    if result.scored_case_count() == 0 {
         return 0.0;
     }
 
     let mut total_relative_score = 0.0;
 
     for case in &result.cases {
-        if case.score == 0 {
-            continue; // スコアが0のケースは無視
-        }
+        let Some(score) = case.scored() else {
+            continue; // スコアが0のケースとスコアのないケースは無視
+        };
 
-        let relative_score = match (best_scores.get(&case.seed).copied(), objective) {
-            (Some(best), Objective::Max) => case.score as f64 / best.get() as f64 * 100.0,
-            (Some(best), Objective::Min) => best.get() as f64 / case.score as f64 * 100.0,
-            (None, _) => 100.0,
+        let relative_score = match best_scores.get(&case.seed).copied() {
+            Some(best) => clamp_relative_score(
+                problem
+                    .relative_score_mode
+                    .calc(score, best, problem.primary_objective()),
+                problem.clamp_relative,
+            ),
+            None => 100.0,
         };
 
         total_relative_score += relative_score;
     }
 
-    total_relative_score / result.case_count as f64
+    total_relative_score / result.scored_case_count() as f64
 }
 
 fn print_table(
     settings: &Settings,
     results: Vec<AllResultJson>,
     best_avg_absolute_score: f64,
-    best_scores: HashMap<u64, NonZeroU64>,
+    best_scores: HashMap<u64, Score>,
     best_avg_relative_score: f64,
+    columns: &[ListColumn],
+    markdown: bool,
 ) {
     // 結果を読み込んで表示
     let mut table_rows = vec![];
@@ -184,23 +476,52 @@ fn print_table(
         table_rows.push(convert_to_table_row(
             result,
             &best_scores,
-            settings.problem.objective,
+            &settings.problem,
+            &settings.git,
+            settings.display.number_format(),
             best_avg_absolute_score,
             best_avg_relative_score,
         ));
     }
 
+    println!("{}", build_table(&table_rows, columns, markdown));
+}
+
+/// 指定された列のみからなるテーブルを構築する
+/// `markdown` が指定された場合は、表が崩れないようセル内の `|` をエスケープする
+fn build_table(rows: &[ResultTableRow], columns: &[ListColumn], markdown: bool) -> String {
+    let mut builder = Builder::default();
+    builder.push_record(columns.iter().map(|c| c.header()));
+
+    for row in rows {
+        builder.push_record(columns.iter().map(|&c| {
+            if markdown {
+                row.cell(c).replace('|', "\\|")
+            } else {
+                row.cell(c).to_string()
+            }
+        }));
+    }
+
     // tabledを使ってテーブルを表示
-    let mut table = Table::new(table_rows);
+    let mut table = builder.build();
     table.with(Style::markdown());
-    table.modify(Columns::new(1..=4), Alignment::right());
-    println!("{table}");
+
+    for (i, column) in columns.iter().enumerate() {
+        if column.is_numeric() {
+            table.modify(Columns::one(i), Alignment::right());
+        }
+    }
+
+    table.to_string()
 }
 
 fn convert_to_table_row(
     result: AllResultJson,
-    best_scores: &HashMap<u64, NonZeroU64>,
-    objective: Objective,
+    best_scores: &HashMap<u64, Score>,
+    problem: &Problem,
+    git_settings: &Git,
+    format: NumberFormat,
     best_avg_absolute_score: f64,
     best_avg_relative_score: f64,
 ) -> ResultTableRow {
@@ -213,18 +534,18 @@ fn convert_to_table_row(
         ac_total.yellow()
     }
     .to_string();
-    let avg_score_f64 = if result.case_count > 0 {
-        result.total_score as f64 / result.case_count as f64
+    let avg_score_f64 = if result.scored_case_count() > 0 {
+        result.total_score / result.scored_case_count() as f64
     } else {
         0.0
     };
-    let avg_score = format!("{avg_score_f64:.2}");
+    let avg_score = format.average_score(avg_score_f64);
     let avg_score = if avg_score_f64 == best_avg_absolute_score {
         avg_score.bold().green().to_string()
     } else {
         avg_score
     };
-    let avg_relative_f64 = calc_average_relative_score(&result, best_scores, objective);
+    let avg_relative_f64 = calc_average_relative_score(&result, best_scores, problem);
     let avg_relative = format!("{avg_relative_f64:.3}");
     let avg_relative = if avg_relative_f64 == best_avg_relative_score {
         avg_relative.bold().green().to_string()
@@ -232,20 +553,217 @@ fn convert_to_table_row(
         avg_relative
     };
 
+    let total_score = format.score(result.total_score);
+    let avg_log10 = if result.scored_case_count() > 0 {
+        format!(
+            "{:.5}",
+            result.total_score_log10 / result.scored_case_count() as f64
+        )
+    } else {
+        "-".to_string()
+    };
+    let total_relative = format!("{:.3}", result.total_relative_score);
+
     let max_time = format!("{:.0} ms", result.max_execution_time * 1e3);
     let tag_display = result
         .tag_name
         .as_deref()
-        .unwrap_or("-")
-        .replace("pahcer/", "");
+        .map_or("-", |tag_name| git_settings.strip_tag_prefix(tag_name))
+        .to_string();
 
     ResultTableRow {
         time: time_str,
         ac_total,
         avg_score,
         avg_relative,
+        total_score,
+        avg_log10,
+        total_relative,
         max_time,
         tag: tag_display,
         comment: result.comment,
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn gen_row(tag: &str) -> ResultTableRow {
+        ResultTableRow {
+            time: "01/01 00:00:00".to_string(),
+            ac_total: "10/10".to_string(),
+            avg_score: "123.45".to_string(),
+            avg_relative: "100.000".to_string(),
+            total_score: "1,234".to_string(),
+            avg_log10: "2.09150".to_string(),
+            total_relative: "1000.000".to_string(),
+            max_time: "12 ms".to_string(),
+            tag: tag.to_string(),
+            comment: String::new(),
+        }
+    }
+
+    fn gen_result(tag_name: Option<&str>, date: &str) -> AllResultJson {
+        let start_time = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
+            .and_local_timezone(chrono::Local)
+            .unwrap();
+
+        AllResultJson {
+            start_time,
+            tag_name: tag_name.map(|s| s.to_string()),
+            ..AllResultJson::from_scores(&[])
+        }
+    }
+
+    fn gen_problem() -> Problem {
+        Problem {
+            problem_name: "test".to_string(),
+            objective: Objective::Max,
+            score_regex: crate::settings::ScoreRegex::Single(String::new()),
+            score_aggregation: Default::default(),
+            score_source: Default::default(),
+            relative_score_mode: Default::default(),
+            clamp_relative: false,
+            score_scan_max_bytes: None,
+            weights_file: None,
+        }
+    }
+
+    #[test]
+    fn test_convert_to_table_row_score_decimals() {
+        let mut result = gen_result(None, "2024-01-01");
+        result.case_count = 3;
+        result.total_score = 3703.7;
+        let format = NumberFormat {
+            group_digits: true,
+            score_decimals: 4,
+        };
+
+        let row = convert_to_table_row(
+            result,
+            &HashMap::new(),
+            &gen_problem(),
+            &Git::default(),
+            format,
+            0.0,
+            0.0,
+        );
+        assert_eq!(row.avg_score, "1,234.5667");
+    }
+
+    #[test]
+    fn test_list_filter() {
+        let date = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").ok();
+        let filter = ListFilter {
+            tag: Some("beam".to_string()),
+            since: date("2024-01-10"),
+            until: date("2024-01-20"),
+        };
+
+        assert!(filter.matches(&gen_result(Some("pahcer/beam-search"), "2024-01-10")));
+        assert!(filter.matches(&gen_result(Some("pahcer/beam"), "2024-01-20")));
+        assert!(!filter.matches(&gen_result(Some("pahcer/greedy"), "2024-01-15")));
+        assert!(!filter.matches(&gen_result(None, "2024-01-15")));
+        assert!(!filter.matches(&gen_result(Some("pahcer/beam"), "2024-01-09")));
+        assert!(!filter.matches(&gen_result(Some("pahcer/beam"), "2024-01-21")));
+        assert!(ListFilter::default().matches(&gen_result(None, "2024-01-01")));
+    }
+
+    #[test]
+    fn test_format_config_version_warning() {
+        let mut old = gen_result(None, "2024-01-01");
+        old.config_version = Some("0.1.0".to_string());
+        let mut current = gen_result(None, "2024-01-02");
+        current.config_version = Some("0.2.0".to_string());
+        let unknown = gen_result(None, "2024-01-03");
+
+        assert_eq!(
+            format_config_version_warning(&[old.clone(), current.clone(), unknown], "0.2.0")
+                .as_deref(),
+            Some("Warning: 1 results were produced with a config version different from the current one (0.2.0): 01/01 12:00:00 (config 0.1.0)")
+        );
+        assert_eq!(format_config_version_warning(&[current], "0.2.0"), None);
+    }
+
+    #[test]
+    fn test_aggregate_results() {
+        let cases = |scores: &[(u64, f64)]| {
+            let mut cases = AllResultJson::from_scores(scores).cases;
+
+            for case in cases.iter_mut() {
+                case.execution_time = case.score / 1000.0;
+            }
+
+            cases
+        };
+        let mut first = gen_result(Some("pahcer/first"), "2024-01-01");
+        first.cases = cases(&[(0, 100.0), (1, 0.0)]);
+        let mut second = gen_result(Some("pahcer/second"), "2024-01-02");
+        second.cases = cases(&[(0, 50.0), (1, 300.0), (2, 0.0)]);
+
+        let problem = gen_problem();
+        let best_scores = HashMap::from([(0, Score::new(200.0).unwrap())]);
+        let aggregated = aggregate_results(&[first, second], &best_scores, &problem);
+
+        assert_eq!(
+            aggregated.start_time,
+            gen_result(None, "2024-01-02").start_time
+        );
+        assert_eq!(aggregated.case_count, 3);
+        assert_eq!(aggregated.total_score, 400.0);
+        assert_eq!(aggregated.wa_seeds, vec![2]);
+        assert_eq!(aggregated.max_execution_time, 0.3);
+        assert_eq!(aggregated.tag_name, None);
+
+        // seed 0: 100 / 200 = 50, seed 1: ベストスコアなしのため100
+        assert_eq!(aggregated.total_relative_score, 150.0);
+    }
+
+    #[test]
+    fn test_result_summary_json() {
+        let mut result = gen_result(Some("pahcer/foo"), "2024-01-01");
+        result.case_count = 4;
+        result.total_score = 400.0;
+        result.wa_seeds = vec![3];
+
+        let problem = gen_problem();
+        let summary = ResultSummaryJson::new(&result, &HashMap::new(), &problem);
+        let json = serde_json::to_value(&summary).unwrap();
+
+        assert_eq!(json["tag_name"], "pahcer/foo");
+        assert_eq!(json["case_count"], 4);
+        assert_eq!(json["accepted_count"], 3);
+        assert_eq!(json["average_score"], 100.0);
+        assert_eq!(json["wa_seeds"], serde_json::json!([3]));
+        assert!(json.get("cases").is_none());
+    }
+
+    #[test]
+    fn test_build_table() {
+        let rows = vec![gen_row("foo"), gen_row("-")];
+        let columns = [ListColumn::Tag, ListColumn::TotalScore, ListColumn::MaxTime];
+        let table = build_table(&rows, &columns, false);
+
+        let expected = "| Tag | Total Score | Max Time |
+|-----|-------------|----------|
+| foo |       1,234 |    12 ms |
+| -   |       1,234 |    12 ms |";
+
+        assert_eq!(table, expected);
+    }
+
+    #[test]
+    fn test_build_table_markdown() {
+        let rows = vec![gen_row("a|b")];
+        let columns = [ListColumn::Tag];
+
+        assert_eq!(
+            build_table(&rows, &columns, true),
+            "| Tag  |\n|------|\n| a\\|b |"
+        );
+    }
+}
@@ -1,17 +1,132 @@
+mod key_listener;
 mod printer;
 
 use super::single::{SingleCaseRunner, TestCase, TestResult};
+use crate::settings::DisplaySettings;
 use anyhow::Result;
 use chrono::{DateTime, Local};
 use printer::Printer;
-use std::sync::{mpsc, Arc};
+use std::{
+    collections::HashMap,
+    io::IsTerminal as _,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex, Once,
+    },
+    time::{Duration, Instant},
+};
 use threadpool::ThreadPool;
 
+/// Flags of the running runner, through which the Ctrl-C handler interrupts it
+static ACTIVE_FLAGS: Mutex<Option<Arc<StopFlags>>> = Mutex::new(None);
+
+/// Requests to interrupt or stop a single run. Recreated for each run.
+#[derive(Debug, Default)]
+struct StopFlags {
+    /// Set when the run is interrupted by Ctrl-C
+    interrupted: AtomicBool,
+    /// Set when the user pressed 'q' to stop the run
+    stop_requested: AtomicBool,
+}
+
+impl StopFlags {
+    /// Returns whether the run has been interrupted by Ctrl-C or stopped by pressing 'q'.
+    fn is_set(&self) -> bool {
+        self.interrupted.load(Ordering::SeqCst) || self.stop_requested.load(Ordering::SeqCst)
+    }
+
+    /// Handles Ctrl-C.
+    fn interrupt(&self) {
+        // 2回目のCtrl-Cでは即座に終了する
+        // KeyListenerは破棄されないため、端末の設定をここで元に戻しておく
+        if self.interrupted.swap(true, Ordering::SeqCst) {
+            key_listener::restore_terminal();
+            std::process::exit(130);
+        }
+
+        eprintln!(
+            "Interrupted. Finishing with the completed cases... (press Ctrl-C again to abort)"
+        );
+    }
+
+    /// Handles 'q'. The running cases are run to the end.
+    fn request_stop(&self) {
+        if !self.stop_requested.swap(true, Ordering::SeqCst) {
+            eprintln!("Stopping after the running cases... (press Ctrl-C to abort them)");
+        }
+    }
+}
+
+/// Handles Ctrl-C. Exits immediately as the default SIGINT does if no run is in progress.
+fn interrupt() {
+    let flags = ACTIVE_FLAGS.lock().ok().and_then(|flags| flags.clone());
+
+    match flags {
+        Some(flags) => flags.interrupt(),
+        None => std::process::exit(130),
+    }
+}
+
+/// Installs the Ctrl-C handler (only once in the process).
+fn install_interrupt_handler() {
+    static INSTALL: Once = Once::new();
+
+    INSTALL.call_once(|| {
+        let result = ctrlc::set_handler(interrupt);
+
+        if let Err(e) = result {
+            eprintln!("Failed to set the Ctrl-C handler: {e}");
+        }
+    });
+}
+
+/// Registers the flags of the running runner to the Ctrl-C handler, and unregisters them when dropped.
+struct ActiveFlagsGuard;
+
+impl ActiveFlagsGuard {
+    fn new(flags: &Arc<StopFlags>) -> Self {
+        if let Ok(mut active) = ACTIVE_FLAGS.lock() {
+            *active = Some(flags.clone());
+        }
+
+        Self
+    }
+}
+
+impl Drop for ActiveFlagsGuard {
+    fn drop(&mut self) {
+        if let Ok(mut active) = ACTIVE_FLAGS.lock() {
+            *active = None;
+        }
+    }
+}
+
 /// The runner for multiple cases.
 pub(super) struct MultiCaseRunner {
     single_runner: SingleCaseRunner,
     test_cases: Vec<TestCase>,
     threads: usize,
+    retry: usize,
+    /// Whether to print the results in ascending order of seeds
+    ordered: bool,
+    /// Whether to stop dispatching new cases after the first failure
+    fail_fast: bool,
+    /// Set when a case failed in the fail-fast mode
+    failed: Arc<AtomicBool>,
+    /// Wall-clock time after which no new cases are dispatched
+    time_budget: Option<Duration>,
+    /// Time when the budget runs out (set only while running)
+    deadline: Option<Instant>,
+    /// Set when a case was skipped because the time budget ran out
+    budget_exceeded: Arc<AtomicBool>,
+    /// Whether to stop the run when 'q' is pressed (only if `handle_interrupts` is set)
+    listen_keys: bool,
+    /// Whether to handle Ctrl-C to finish with the completed cases
+    handle_interrupts: bool,
+    /// Set when the current run is interrupted or stopped (recreated for each run)
+    stop_flags: Arc<StopFlags>,
+    /// Weights of the seeds to calculate the weighted average relative score
+    weights: Option<HashMap<u64, f64>>,
     printer: Box<dyn Printer>,
 }
 
@@ -20,130 +135,577 @@ impl MultiCaseRunner {
         single_runner: SingleCaseRunner,
         test_cases: Vec<TestCase>,
         threads: usize,
+        retry: usize,
+        ordered: bool,
+        display: DisplaySettings,
     ) -> Self {
-        let printer = Box::new(printer::ConsolePrinter::new(test_cases.len()));
-        Self::new(single_runner, test_cases, threads, printer)
+        let printer =
+            Box::new(printer::ConsolePrinter::new(test_cases.len()).with_display(display));
+        let mut runner = Self::new(single_runner, test_cases, threads, retry, ordered, printer);
+        runner.listen_keys = true;
+        runner
+    }
+
+    pub(super) fn new_progress(
+        single_runner: SingleCaseRunner,
+        test_cases: Vec<TestCase>,
+        threads: usize,
+        retry: usize,
+        display: DisplaySettings,
+    ) -> Self {
+        let printer = Box::new(printer::ProgressPrinter::new(test_cases.len(), display));
+        Self::new(single_runner, test_cases, threads, retry, false, printer)
+    }
+
+    /// Creates a runner which prints nothing per case, and the summary only if `print_summary` is true.
+    pub(super) fn new_quiet(
+        single_runner: SingleCaseRunner,
+        test_cases: Vec<TestCase>,
+        threads: usize,
+        retry: usize,
+        print_summary: bool,
+        display: DisplaySettings,
+    ) -> Self {
+        let printer = Box::new(printer::QuietPrinter::new(
+            test_cases.len(),
+            print_summary,
+            display,
+        ));
+        Self::new(single_runner, test_cases, threads, retry, false, printer)
     }
 
     pub(super) fn new_json(
         single_runner: SingleCaseRunner,
         test_cases: Vec<TestCase>,
         threads: usize,
+        retry: usize,
     ) -> Self {
         let printer = Box::new(printer::JsonPrinter::new());
-        Self::new(single_runner, test_cases, threads, printer)
+        Self::new(single_runner, test_cases, threads, retry, false, printer)
     }
 
     fn new(
         single_runner: SingleCaseRunner,
         test_cases: Vec<TestCase>,
         threads: usize,
+        retry: usize,
+        ordered: bool,
         printer: Box<dyn Printer>,
     ) -> Self {
         Self {
             single_runner,
             test_cases,
             threads,
+            retry,
+            ordered,
+            fail_fast: false,
+            failed: Arc::new(AtomicBool::new(false)),
+            time_budget: None,
+            deadline: None,
+            budget_exceeded: Arc::new(AtomicBool::new(false)),
+            listen_keys: false,
+            handle_interrupts: false,
+            stop_flags: Arc::new(StopFlags::default()),
+            weights: None,
             printer,
         }
     }
 
-    pub(super) fn run(&mut self) -> Result<TestStats> {
-        let (rx, start_time) = self.start_tests();
-        self.collect_results(rx, start_time)
+    pub(super) fn with_fail_fast(mut self, fail_fast: bool) -> Self {
+        self.fail_fast = fail_fast;
+        self
+    }
+
+    /// Stops dispatching new cases once `time_budget` has elapsed since the start of the run.
+    pub(super) fn with_time_budget(mut self, time_budget: Option<Duration>) -> Self {
+        self.time_budget = time_budget;
+        self
     }
 
-    fn start_tests(&mut self) -> (mpsc::Receiver<TestResult>, DateTime<Local>) {
+    /// Handles Ctrl-C (and 'q' on the console) to finish with the completed cases.
+    /// The Ctrl-C handler is process-wide, so this is meant for the command line only.
+    pub(super) fn with_interrupt_handling(mut self, handle_interrupts: bool) -> Self {
+        self.handle_interrupts = handle_interrupts;
+        self
+    }
+
+    /// Sets the weights of the seeds. Seeds not in `weights` are weighted 1.0.
+    pub(super) fn with_weights(mut self, weights: Option<HashMap<u64, f64>>) -> Self {
+        self.weights = weights;
+        self
+    }
+
+    /// Runs all the test cases.
+    /// If interrupted by Ctrl-C, stopped by pressing 'q' or out of the time budget,
+    /// returns the stats of the cases completed so far.
+    pub(super) fn run(&mut self) -> Result<TestStats> {
+        self.stop_flags = Arc::new(StopFlags::default());
+
+        let _active_flags = self.handle_interrupts.then(|| {
+            install_interrupt_handler();
+            ActiveFlagsGuard::new(&self.stop_flags)
+        });
+        let key_listener = self.start_key_listener();
+
         let start_time = Local::now();
-        let thread_cnt = match self.threads {
+        self.deadline = self.time_budget.map(|budget| Instant::now() + budget);
+        let rx = self.start_tests(&self.test_cases);
+        let mut results = self.collect_results(rx)?;
+        self.retry_failed_cases(&mut results)?;
+        self.deadline = None;
+        drop(key_listener);
+
+        let remaining = self.test_cases.len() - results.len();
+
+        if self.stop_flags.stop_requested.load(Ordering::SeqCst) {
+            eprintln!(
+                "Stopped by the user. Skipped the remaining {remaining} of {} seeds.",
+                self.test_cases.len()
+            );
+        } else if self.budget_exceeded.load(Ordering::SeqCst) {
+            let budget = self.time_budget.unwrap_or_default().as_secs();
+            eprintln!(
+                "Time budget of {budget}s exceeded. Skipped the remaining {remaining} of {} seeds.",
+                self.test_cases.len()
+            );
+        }
+
+        results.sort_unstable_by_key(|r| r.test_case().seed());
+
+        let mut stats = TestStats::new(results, start_time).with_threads(self.thread_count());
+        stats.interrupted = self.stop_flags.interrupted.load(Ordering::SeqCst);
+        stats.stopped = self.stop_flags.stop_requested.load(Ordering::SeqCst);
+
+        if let Some(weights) = &self.weights {
+            stats = stats.with_weights(weights);
+        }
+
+        if !stats.results.is_empty() {
+            self.printer.print_summary(&mut std::io::stdout(), &stats)?;
+        }
+
+        Ok(stats)
+    }
+
+    /// Starts listening to 'q' to stop the run, if the console is interactive.
+    fn start_key_listener(&self) -> Option<key_listener::KeyListener> {
+        if !self.handle_interrupts
+            || !self.listen_keys
+            || !std::io::stdin().is_terminal()
+            || !std::io::stdout().is_terminal()
+        {
+            return None;
+        }
+
+        let stop_flags = self.stop_flags.clone();
+        let interrupt_flags = self.stop_flags.clone();
+        let listener = key_listener::KeyListener::start(
+            move || stop_flags.request_stop(),
+            move || interrupt_flags.interrupt(),
+        );
+
+        match listener {
+            Ok(listener) => {
+                eprintln!("Press 'q' to stop after the running cases.");
+                Some(listener)
+            }
+            Err(e) => {
+                eprintln!("Failed to listen to the keyboard: {e}");
+                None
+            }
+        }
+    }
+
+    /// Returns the number of threads to run the cases with. 0 means the number of physical CPUs.
+    fn thread_count(&self) -> usize {
+        match self.threads {
             0 => num_cpus::get_physical(),
             n => n,
-        };
-        let thread_cnt = thread_cnt.min(self.test_cases.len());
+        }
+    }
+
+    /// Starts running the test cases in parallel.
+    /// Each result is sent with the index of its test case in `test_cases`.
+    fn start_tests(&self, test_cases: &[TestCase]) -> mpsc::Receiver<(usize, TestResult)> {
+        let thread_cnt = self.thread_count().min(test_cases.len());
 
         let threadpool = ThreadPool::new(thread_cnt);
         let (tx, rx) = mpsc::channel();
         let single_runner = Arc::new(self.single_runner.clone());
+        let fail_fast = self.fail_fast;
+        let deadline = self.deadline;
 
         // 送信側
-        for &test_case in self.test_cases.iter() {
+        for (i, &test_case) in test_cases.iter().enumerate() {
             let tx = tx.clone();
             let runner = single_runner.clone();
+            let failed = self.failed.clone();
+            let budget_exceeded = self.budget_exceeded.clone();
+            let stop_flags = self.stop_flags.clone();
             threadpool.execute(move || {
+                // 中断後やfail-fastモードで失敗した後は新たなケースを実行しない
+                if stop_flags.is_set() || failed.load(Ordering::SeqCst) {
+                    return;
+                }
+
+                // 時間の上限を過ぎた後も新たなケースは実行しない（実行中のケースは最後まで実行させる）
+                if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                    budget_exceeded.store(true, Ordering::SeqCst);
+                    return;
+                }
+
                 let result = runner.run(test_case);
-                tx.send(result).expect("Failed to send result");
+
+                // 実行中のケースは打ち切らず、最後まで実行させる
+                if fail_fast && result.score().is_err() {
+                    failed.store(true, Ordering::SeqCst);
+                }
+
+                // 実行中に中断されたケースはCtrl-Cによって異常終了している可能性があるため破棄する
+                // 'q' による停止の場合は最後まで実行されているため残す
+                if stop_flags.interrupted.load(Ordering::SeqCst) {
+                    return;
+                }
+
+                tx.send((i, result)).expect("Failed to send result");
             });
         }
 
-        (rx, start_time)
+        rx
     }
 
     fn collect_results(
         &mut self,
-        rx: mpsc::Receiver<TestResult>,
-        start_time: DateTime<Local>,
-    ) -> Result<TestStats> {
+        rx: mpsc::Receiver<(usize, TestResult)>,
+    ) -> Result<Vec<TestResult>> {
         let mut results = Vec::with_capacity(self.test_cases.len());
         let mut stdio = std::io::stdout();
 
-        for result in rx {
+        if !self.ordered {
+            for (_, result) in rx {
+                self.printer.print_case(&mut stdio, &result)?;
+                results.push(result);
+            }
+
+            return Ok(results);
+        }
+
+        // 各ケースがseed順で何番目に出力されるか
+        let mut order = (0..self.test_cases.len()).collect::<Vec<_>>();
+        order.sort_by_key(|&i| self.test_cases[i].seed());
+        let mut ranks = vec![0; self.test_cases.len()];
+
+        for (rank, &i) in order.iter().enumerate() {
+            ranks[i] = rank;
+        }
+
+        // 先に完了したケースはバッファに溜めておき、先頭から連続している分だけ出力する
+        let mut buffer = (0..self.test_cases.len())
+            .map(|_| None)
+            .collect::<Vec<Option<TestResult>>>();
+        let mut next_rank = 0;
+
+        for (i, result) in rx {
+            buffer[ranks[i]] = Some(result);
+
+            while let Some(result) = buffer.get_mut(next_rank).and_then(|r| r.take()) {
+                self.printer.print_case(&mut stdio, &result)?;
+                results.push(result);
+                next_rank += 1;
+            }
+        }
+
+        // 中断された場合は歯抜けになるため、残りをまとめて出力する
+        for result in buffer.into_iter().skip(next_rank).flatten() {
             self.printer.print_case(&mut stdio, &result)?;
             results.push(result);
         }
 
-        results.sort_unstable_by_key(|r| r.test_case().seed());
+        Ok(results)
+    }
 
-        let stats = TestStats::new(results, start_time);
+    /// Runs the cases of `results` once more without printing them, to check that the solver is deterministic.
+    /// Returns the pairs of the first and second results whose scores differ, in seed order.
+    pub(super) fn find_nondeterministic_cases(
+        &self,
+        results: &[TestResult],
+    ) -> Vec<(TestResult, TestResult)> {
+        let test_cases = results.iter().map(|r| *r.test_case()).collect::<Vec<_>>();
+        let rx = self.start_tests(&test_cases);
 
-        self.printer.print_summary(&mut stdio, &stats)?;
+        let mut mismatches = rx
+            .into_iter()
+            .filter(|(i, result)| result.score() != results[*i].score())
+            .map(|(i, result)| (results[i].clone(), result))
+            .collect::<Vec<_>>();
+        mismatches.sort_unstable_by_key(|(result, _)| result.test_case().seed());
+        mismatches
+    }
 
-        Ok(stats)
+    /// Re-runs the failed cases up to `retry` times, replacing them with the successful results.
+    fn retry_failed_cases(&mut self, results: &mut [TestResult]) -> Result<()> {
+        let mut stdio = std::io::stdout();
+
+        for _ in 0..self.retry {
+            if self.stop_flags.is_set()
+                || self.failed.load(Ordering::SeqCst)
+                || self.budget_exceeded.load(Ordering::SeqCst)
+            {
+                break;
+            }
+
+            let failed_indices = results
+                .iter()
+                .enumerate()
+                .filter_map(|(i, r)| r.score().is_err().then_some(i))
+                .collect::<Vec<_>>();
+
+            if failed_indices.is_empty() {
+                break;
+            }
+
+            let failed_cases = failed_indices
+                .iter()
+                .map(|&i| *results[i].test_case())
+                .collect::<Vec<_>>();
+            let rx = self.start_tests(&failed_cases);
+
+            for (i, result) in rx {
+                // 再度失敗した場合は元の結果を残す
+                if result.score().is_err() {
+                    continue;
+                }
+
+                self.printer.print_case(&mut stdio, &result)?;
+                results[failed_indices[i]] = result;
+            }
+        }
+
+        Ok(())
     }
 }
 
+/// The results of all the test cases and their statistics.
 #[derive(Debug, Clone)]
-pub(super) struct TestStats {
+pub struct TestStats {
     pub(super) results: Vec<TestResult>,
-    pub(super) score_sum: u64,
+    pub(super) score_sum: f64,
     pub(super) score_sum_log10: f64,
     pub(super) relative_score_sum: f64,
+    /// Distribution of the scores (failed cases are counted as 0, unscored cases are left out)
+    pub(super) score_distribution: Option<Distribution>,
+    /// Distribution of the relative scores (failed cases are counted as 0, unscored cases are left out)
+    pub(super) relative_score_distribution: Option<Distribution>,
     pub(super) start_time: DateTime<Local>,
+    /// Number of threads the cases were run with (0 if unknown)
+    pub(super) threads: usize,
+    /// Weighted average relative score (`None` if the seeds are not weighted)
+    pub(super) weighted_relative_score: Option<f64>,
+    /// Whether the run was interrupted by Ctrl-C before all the cases were run
+    pub(super) interrupted: bool,
+    /// Whether the run was stopped by pressing 'q' before all the cases were run
+    pub(super) stopped: bool,
 }
 
 impl TestStats {
     pub(crate) fn new(results: Vec<TestResult>, start_time: DateTime<Local>) -> Self {
-        let score_sum = results
-            .iter()
-            .filter_map(|r| r.score().as_ref().ok().map(|s| s.get()))
-            .sum();
+        let score_sum = results.iter().map(|r| r.score_or_zero()).sum::<f64>();
         let score_sum_log10 = results
             .iter()
-            .filter_map(|r| r.score_log10().ok())
+            .filter_map(|r| r.score_log10())
             .sum::<f64>()
             .max(0.0);
         let relative_score_sum = results
             .iter()
-            .filter_map(|r| r.relative_score().as_ref().ok())
+            .map(|r| r.relative_score_or_zero())
             .sum::<f64>()
             .max(0.0);
 
+        let scores = results
+            .iter()
+            .filter(|r| r.has_score())
+            .map(|r| r.score_or_zero())
+            .collect();
+        let score_distribution = Distribution::new(scores);
+        let relative_scores = results
+            .iter()
+            .filter(|r| r.has_score())
+            .map(|r| r.relative_score_or_zero())
+            .collect();
+        let relative_score_distribution = Distribution::new(relative_scores);
+
         Self {
             results,
             score_sum,
             score_sum_log10,
             relative_score_sum,
+            score_distribution,
+            relative_score_distribution,
             start_time,
+            threads: 0,
+            weighted_relative_score: None,
+            interrupted: false,
+            stopped: false,
         }
     }
+
+    pub(super) const fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Weights the relative scores by `weights`. Seeds not in `weights` are weighted 1.0.
+    /// If the total weight is 0, the relative score is left unweighted.
+    pub(super) fn with_weights(mut self, weights: &HashMap<u64, f64>) -> Self {
+        let mut weighted_sum = 0.0;
+        let mut weight_sum = 0.0;
+
+        for result in self.results.iter().filter(|r| r.has_score()) {
+            let weight = weights
+                .get(&result.test_case().seed())
+                .copied()
+                .unwrap_or(1.0);
+            weighted_sum += weight * result.relative_score_or_zero();
+            weight_sum += weight;
+        }
+
+        self.weighted_relative_score = (weight_sum > 0.0).then(|| weighted_sum / weight_sum);
+        self
+    }
+
+    /// Results of the test cases
+    pub fn results(&self) -> &[TestResult] {
+        &self.results
+    }
+
+    /// Number of the cases to average the scores over, i.e. all but the ones accepted without any score
+    pub(super) fn scored_count(&self) -> usize {
+        self.results.iter().filter(|r| r.has_score()).count()
+    }
+
+    /// Average score of the cases (failed cases are counted as 0, unscored cases are left out)
+    pub fn average_score(&self) -> f64 {
+        self.score_sum / self.scored_count().max(1) as f64
+    }
+
+    /// Average score in log10 scale (failed cases are counted as 0, unscored cases are left out)
+    pub(super) fn average_score_log10(&self) -> f64 {
+        self.score_sum_log10 / self.scored_count().max(1) as f64
+    }
+
+    /// Average relative score of the cases (failed cases are counted as 0, unscored cases are left out).
+    /// Weighted by the seed weights if they are given.
+    pub fn average_relative_score(&self) -> f64 {
+        self.weighted_relative_score
+            .unwrap_or(self.relative_score_sum / self.scored_count().max(1) as f64)
+    }
+
+    /// Whether the average relative score is weighted
+    pub fn is_weighted(&self) -> bool {
+        self.weighted_relative_score.is_some()
+    }
+
+    /// Whether the run was interrupted by Ctrl-C before all the cases were run.
+    /// Stopping the run by pressing 'q' is not an interruption.
+    pub const fn interrupted(&self) -> bool {
+        self.interrupted
+    }
+
+    /// Time when the run started
+    pub const fn start_time(&self) -> DateTime<Local> {
+        self.start_time
+    }
+}
+
+#[cfg(test)]
+impl TestStats {
+    /// Creates stats for tests from pairs of a seed and a score. Cases scored 0 are Wrong Answers.
+    pub(super) fn from_scores(scores: &[(u64, f64)], start_time: DateTime<Local>) -> Self {
+        use super::single::{CaseError, Score};
+
+        let results = scores
+            .iter()
+            .map(|&(seed, score)| {
+                TestResult::for_seed(seed, Score::new(score).ok_or(CaseError::WrongAnswer))
+            })
+            .collect();
+
+        Self::new(results, start_time)
+    }
+}
+
+/// Descriptive statistics of a set of values.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) struct Distribution {
+    pub(super) min: f64,
+    pub(super) median: f64,
+    pub(super) p90: f64,
+    pub(super) p99: f64,
+    pub(super) max: f64,
+    pub(super) std_dev: f64,
+}
+
+impl Distribution {
+    /// Returns `None` if `values` is empty.
+    pub(super) fn new(mut values: Vec<f64>) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+
+        values.sort_unstable_by(|a, b| a.total_cmp(b));
+
+        let mean = values.iter().sum::<f64>() / values.len() as f64;
+        let variance =
+            values.iter().map(|v| (v - mean) * (v - mean)).sum::<f64>() / values.len() as f64;
+
+        Some(Self {
+            min: values[0],
+            median: Self::percentile(&values, 50.0),
+            p90: Self::percentile(&values, 90.0),
+            p99: Self::percentile(&values, 99.0),
+            max: values[values.len() - 1],
+            std_dev: variance.sqrt(),
+        })
+    }
+
+    /// Formats the statistics in one line, formatting each value with `format`.
+    pub(super) fn format(&self, format: impl Fn(f64) -> String) -> String {
+        format!(
+            "min {} / median {} / p90 {} / p99 {} / max {} / stddev {}",
+            format(self.min),
+            format(self.median),
+            format(self.p90),
+            format(self.p99),
+            format(self.max),
+            format(self.std_dev)
+        )
+    }
+
+    /// Returns the note appended to a score distribution line to tell that failed cases are counted as 0.
+    pub(super) fn failed_note(failed_count: usize) -> String {
+        if failed_count == 0 {
+            String::new()
+        } else {
+            format!(" ({failed_count} failed counted as 0)")
+        }
+    }
+
+    /// Calculates the percentile of sorted values by linear interpolation.
+    fn percentile(sorted_values: &[f64], percent: f64) -> f64 {
+        let pos = (sorted_values.len() - 1) as f64 * percent / 100.0;
+        let lower = pos.floor() as usize;
+        let upper = pos.ceil() as usize;
+        let ratio = pos - lower as f64;
+        sorted_values[lower] * (1.0 - ratio) + sorted_values[upper] * ratio
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::runner::single::{Objective, TestStep};
+    use crate::runner::single::{Objective, Score, ScoreAggregation, TestStep};
     use printer::MockPrinter;
     use regex::Regex;
-    use std::num::NonZero;
 
     thread_local!(static SCORE_REGEX: Regex = Regex::new(r"^\s*Score\s*=\s*(?P<score>\d+)\s*$").unwrap());
 
@@ -158,11 +720,15 @@ mod test {
             None,
             true,
         )];
-        let single_runner = SingleCaseRunner::new(steps, SCORE_REGEX.with(|r| r.clone()));
+        let single_runner = SingleCaseRunner::new(
+            steps,
+            SCORE_REGEX.with(|r| r.clone()),
+            ScoreAggregation::Last,
+        );
         let test_cases = vec![
-            TestCase::new(0, NonZero::new(100), Objective::Max),
-            TestCase::new(1, NonZero::new(200), Objective::Max),
-            TestCase::new(2, NonZero::new(50), Objective::Max),
+            TestCase::new(0, Score::new(100.0), Objective::Max),
+            TestCase::new(1, Score::new(200.0), Objective::Max),
+            TestCase::new(2, Score::new(50.0), Objective::Max),
             TestCase::new(3, None, Objective::Max),
         ];
 
@@ -175,13 +741,212 @@ mod test {
             .expect_print_summary()
             .times(1)
             .returning(|_, _| Ok(()));
-        let mut runner = MultiCaseRunner::new(single_runner, test_cases, 0, Box::new(printer));
+        let mut runner =
+            MultiCaseRunner::new(single_runner, test_cases, 0, 0, false, Box::new(printer));
 
+        // 前回の実行での停止要求は次の実行に持ち越さない
+        runner.stop_flags.request_stop();
         let stats = runner.run().unwrap();
 
         assert_eq!(stats.results.len(), 4);
-        assert_eq!(stats.score_sum, 400);
+        assert!(!stats.interrupted());
+        assert_eq!(stats.score_sum, 400.0);
         assert_eq!(stats.score_sum_log10, 8.0);
         assert_eq!(stats.relative_score_sum, 450.0);
+        assert_eq!(stats.average_relative_score(), 112.5);
+        assert!(!stats.is_weighted());
+
+        // 重みのないseedは1として扱う
+        let stats = stats.with_weights(&HashMap::from([(2, 0.0), (3, 2.0)]));
+        assert_eq!(stats.average_relative_score(), 87.5);
+        assert!(stats.is_weighted());
+    }
+
+    #[test]
+    fn test_multi_case_runner_ordered() {
+        // seedが小さいケースほど遅く終わるステップ
+        let steps = vec![TestStep::new(
+            "sh".to_string(),
+            vec![
+                "-c".to_string(),
+                "sleep 0.$((3 - {SEED})); echo 'Score = 100'".to_string(),
+            ],
+            None,
+            None,
+            None,
+            None,
+            true,
+        )];
+        let single_runner = SingleCaseRunner::new(
+            steps,
+            SCORE_REGEX.with(|r| r.clone()),
+            ScoreAggregation::Last,
+        );
+        let test_cases = vec![
+            TestCase::new(2, None, Objective::Max),
+            TestCase::new(0, None, Objective::Max),
+            TestCase::new(1, None, Objective::Max),
+        ];
+
+        let printed_seeds = Arc::new(std::sync::Mutex::new(vec![]));
+        let mut printer = MockPrinter::new();
+        let seeds = printed_seeds.clone();
+        printer.expect_print_case().times(3).returning(move |_, r| {
+            seeds.lock().unwrap().push(r.test_case().seed());
+            Ok(())
+        });
+        printer
+            .expect_print_summary()
+            .times(1)
+            .returning(|_, _| Ok(()));
+        let mut runner =
+            MultiCaseRunner::new(single_runner, test_cases, 3, 0, true, Box::new(printer));
+
+        let stats = runner.run().unwrap();
+
+        assert_eq!(stats.results.len(), 3);
+        assert_eq!(*printed_seeds.lock().unwrap(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_multi_case_runner_fail_fast() {
+        // seed 1のみ失敗するステップ
+        let steps = vec![TestStep::new(
+            "sh".to_string(),
+            vec![
+                "-c".to_string(),
+                "test {SEED} -ne 1 && echo 'Score = 100'".to_string(),
+            ],
+            None,
+            None,
+            None,
+            None,
+            true,
+        )];
+        let single_runner = SingleCaseRunner::new(
+            steps,
+            SCORE_REGEX.with(|r| r.clone()),
+            ScoreAggregation::Last,
+        );
+        let test_cases = (0..5)
+            .map(|seed| TestCase::new(seed, None, Objective::Max))
+            .collect();
+
+        let mut printer = MockPrinter::new();
+        printer
+            .expect_print_case()
+            .times(2)
+            .returning(|_, _| Ok(()));
+        printer
+            .expect_print_summary()
+            .times(1)
+            .returning(|_, _| Ok(()));
+        let mut runner =
+            MultiCaseRunner::new(single_runner, test_cases, 1, 0, false, Box::new(printer))
+                .with_fail_fast(true);
+
+        let stats = runner.run().unwrap();
+
+        assert_eq!(stats.results.len(), 2);
+        assert!(stats.results[1].score().is_err());
+    }
+
+    #[test]
+    fn test_multi_case_runner_time_budget() {
+        let steps = vec![TestStep::new(
+            "sh".to_string(),
+            vec![
+                "-c".to_string(),
+                "sleep 0.3; echo 'Score = 100'".to_string(),
+            ],
+            None,
+            None,
+            None,
+            None,
+            true,
+        )];
+        let single_runner = SingleCaseRunner::new(
+            steps,
+            SCORE_REGEX.with(|r| r.clone()),
+            ScoreAggregation::Last,
+        );
+        let test_cases = (0..5)
+            .map(|seed| TestCase::new(seed, None, Objective::Max))
+            .collect();
+
+        // 1ケース目の実行中に上限を過ぎるため、残りのケースは実行されない
+        let mut printer = MockPrinter::new();
+        printer
+            .expect_print_case()
+            .times(1)
+            .returning(|_, _| Ok(()));
+        printer
+            .expect_print_summary()
+            .times(1)
+            .returning(|_, _| Ok(()));
+        let mut runner =
+            MultiCaseRunner::new(single_runner, test_cases, 1, 0, false, Box::new(printer))
+                .with_time_budget(Some(Duration::from_millis(100)));
+
+        let stats = runner.run().unwrap();
+
+        assert_eq!(stats.results.len(), 1);
+        assert!(stats.results[0].score().is_ok());
+    }
+
+    #[test]
+    fn test_distribution() {
+        let dist = Distribution::new(vec![40.0, 10.0, 30.0, 20.0, 50.0]).unwrap();
+        assert_eq!(dist.min, 10.0);
+        assert_eq!(dist.median, 30.0);
+        assert_eq!(dist.p90, 46.0);
+        assert_eq!(dist.max, 50.0);
+        assert_eq!(dist.std_dev, 200.0f64.sqrt());
+
+        assert_eq!(Distribution::new(vec![]), None);
+    }
+
+    #[test]
+    fn test_multi_case_runner_retry() {
+        // 初回のみ失敗するステップ
+        let marker = std::env::temp_dir().join(format!("pahcer_retry_{}", std::process::id()));
+        let _ = std::fs::remove_file(&marker);
+        let script = format!(
+            "if [ -e {0} ]; then echo 'Score = 100'; else touch {0}; exit 1; fi",
+            marker.display()
+        );
+        let steps = vec![TestStep::new(
+            "sh".to_string(),
+            vec!["-c".to_string(), script],
+            None,
+            None,
+            None,
+            None,
+            true,
+        )];
+        let single_runner = SingleCaseRunner::new(
+            steps,
+            SCORE_REGEX.with(|r| r.clone()),
+            ScoreAggregation::Last,
+        );
+        let test_cases = vec![TestCase::new(0, None, Objective::Max)];
+
+        let mut printer = MockPrinter::new();
+        printer
+            .expect_print_case()
+            .times(2)
+            .returning(|_, _| Ok(()));
+        printer
+            .expect_print_summary()
+            .times(1)
+            .returning(|_, _| Ok(()));
+        let mut runner =
+            MultiCaseRunner::new(single_runner, test_cases, 0, 2, false, Box::new(printer));
+
+        let stats = runner.run().unwrap();
+        let _ = std::fs::remove_file(&marker);
+
+        assert_eq!(stats.results.len(), 1);
+        assert_eq!(stats.score_sum, 100.0);
     }
 }
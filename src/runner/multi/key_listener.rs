@@ -0,0 +1,148 @@
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    terminal,
+};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::JoinHandle,
+    time::Duration,
+};
+
+/// Interval to check the key input
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A thread listening to the keys during the run. Stops listening and restores the terminal when dropped.
+pub(super) struct KeyListener {
+    finished: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl KeyListener {
+    /// Starts listening to the keys. Calls `on_stop` when 'q' is pressed and `on_interrupt` on Ctrl-C.
+    pub(super) fn start(
+        on_stop: impl Fn() + Send + 'static,
+        on_interrupt: impl Fn() + Send + 'static,
+    ) -> std::io::Result<Self> {
+        enable_raw_mode()?;
+
+        let finished = Arc::new(AtomicBool::new(false));
+        let finished_clone = finished.clone();
+
+        let handle = std::thread::spawn(move || {
+            while !finished_clone.load(Ordering::SeqCst) {
+                // 読み込みに失敗した場合は監視を諦める（テストの実行には影響させない）
+                match event::poll(POLL_INTERVAL) {
+                    Ok(true) => {}
+                    Ok(false) => continue,
+                    Err(_) => break,
+                }
+
+                let key = match event::read() {
+                    Ok(Event::Key(key)) => key,
+                    Ok(_) => continue,
+                    Err(_) => break,
+                };
+
+                if is_stop_key(&key) {
+                    on_stop();
+                } else if is_interrupt_key(&key) {
+                    on_interrupt();
+                }
+            }
+        });
+
+        Ok(Self {
+            finished,
+            handle: Some(handle),
+        })
+    }
+}
+
+impl Drop for KeyListener {
+    fn drop(&mut self) {
+        self.finished.store(true, Ordering::SeqCst);
+
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+
+        restore_terminal();
+    }
+}
+
+/// Restores the terminal from the raw mode (does nothing if it is not in the raw mode).
+pub(super) fn restore_terminal() {
+    if let Err(e) = terminal::disable_raw_mode() {
+        eprintln!("Failed to restore the terminal mode: {e}");
+    }
+}
+
+/// Enables reading the keys one by one.
+/// Newlines in the output and the signal of Ctrl-C are kept working during the run.
+fn enable_raw_mode() -> std::io::Result<()> {
+    terminal::enable_raw_mode()?;
+
+    #[cfg(unix)]
+    {
+        // SAFETY: 標準入力が端末であることは呼び出し元で確認しており、termiosはこのスコープ内でのみ使用する
+        unsafe {
+            let mut termios = std::mem::zeroed::<libc::termios>();
+
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut termios) == 0 {
+                termios.c_oflag |= libc::OPOST;
+                termios.c_lflag |= libc::ISIG;
+                libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &termios);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether the key stops the run
+fn is_stop_key(key: &KeyEvent) -> bool {
+    key.kind == KeyEventKind::Press
+        && !key
+            .modifiers
+            .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+        && matches!(key.code, KeyCode::Char('q') | KeyCode::Char('Q'))
+}
+
+/// Whether the key is Ctrl-C (on Windows, Ctrl-C arrives as a key input in the raw mode)
+fn is_interrupt_key(key: &KeyEvent) -> bool {
+    key.kind == KeyEventKind::Press
+        && key.modifiers.contains(KeyModifiers::CONTROL)
+        && key.code == KeyCode::Char('c')
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_is_stop_key() {
+        assert!(is_stop_key(&KeyEvent::new(
+            KeyCode::Char('q'),
+            KeyModifiers::NONE
+        )));
+        assert!(!is_stop_key(&KeyEvent::new(
+            KeyCode::Char('q'),
+            KeyModifiers::CONTROL
+        )));
+        assert!(!is_stop_key(&KeyEvent::new(
+            KeyCode::Char('a'),
+            KeyModifiers::NONE
+        )));
+        assert!(is_interrupt_key(&KeyEvent::new(
+            KeyCode::Char('c'),
+            KeyModifiers::CONTROL
+        )));
+        assert!(!is_interrupt_key(&KeyEvent::new(
+            KeyCode::Char('c'),
+            KeyModifiers::NONE
+        )));
+    }
+}
@@ -1,11 +1,17 @@
-use crate::util::format_float_with_commas;
+use crate::util::{serialize_score, NumberFormat};
 
-use super::{TestResult, TestStats};
+use super::{Distribution, TestResult, TestStats};
+use crate::runner::single::CaseErrorKind;
+use crate::settings::{DisplaySettings, RelativeScoreColors};
 use anyhow::Result;
 use colored::Colorize as _;
-use num_format::{Locale, ToFormattedString as _};
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
 use serde::Serialize;
-use std::{io::Write, num::NonZero};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    io::Write,
+    num::NonZero,
+};
 
 #[cfg_attr(test, mockall::automock)]
 pub(super) trait Printer {
@@ -17,19 +23,58 @@ pub(super) struct ConsolePrinter {
     testcase_count: usize,
     completed_count: usize,
     score_width: usize,
-    score_sum: u64,
+    score_sum: f64,
     relative_score_sum: f64,
+    /// 出力済みのケースのスコアと相対スコア（リトライ時の二重計上を防ぐため）
+    printed_scores: PrintedScores,
+    number_format: NumberFormat,
+    relative_score_colors: RelativeScoreColors,
+}
+
+/// 出力済みのケースのseedごとのスコアと相対スコア（スコアのないケースは `None` ）
+#[derive(Debug, Default)]
+struct PrintedScores(HashMap<u64, Option<(f64, f64)>>);
+
+impl PrintedScores {
+    /// ケースの結果を記録し、スコアと相対スコアの合計の差分と、新しいケースか否かを返す
+    /// リトライされたケースは前回の結果を差し替える
+    fn insert(&mut self, result: &TestResult) -> (f64, f64, bool) {
+        let scores = result
+            .has_score()
+            .then(|| (result.score_or_zero(), result.relative_score_or_zero()));
+        let (score, relative_score) = scores.unwrap_or_default();
+
+        match self.0.insert(result.test_case().seed(), scores) {
+            Some(old) => {
+                let (old_score, old_relative_score) = old.unwrap_or_default();
+                (
+                    score - old_score,
+                    relative_score - old_relative_score,
+                    false,
+                )
+            }
+            None => (score, relative_score, true),
+        }
+    }
+
+    /// 平均の計算に用いるケース数（スコアのないケースを除く）
+    fn scored_count(&self) -> usize {
+        self.0.values().filter(|s| s.is_some()).count()
+    }
 }
 
 impl Printer for ConsolePrinter {
     fn print_case(&mut self, writer: &mut dyn Write, result: &TestResult) -> Result<()> {
-        self.completed_count += 1;
+        let (score_delta, relative_score_delta, is_new) = self.printed_scores.insert(result);
+
+        if is_new {
+            self.completed_count += 1;
+        }
+
         assert!(self.completed_count <= self.testcase_count);
 
-        let score = result.score().as_ref().map(|s| s.get()).unwrap_or(0);
-        let relative_score = result.relative_score().as_ref().copied().unwrap_or(0.0);
-        self.score_sum += score;
-        self.relative_score_sum += relative_score;
+        self.score_sum += score_delta;
+        self.relative_score_sum += relative_score_delta;
 
         if self.completed_count == 1 {
             self.print_header(writer)?;
@@ -38,27 +83,38 @@ impl Printer for ConsolePrinter {
         let digit = self.testcase_count.to_string().len().max(3);
 
         let nonzero2 = NonZero::new(2).unwrap();
-        let score = score.to_formatted_string(&Locale::en);
-        let average_score = format_float_with_commas(
-            self.score_sum as f64 / self.completed_count as f64,
-            nonzero2,
-        );
-        let execution_time = result
-            .execution_time()
-            .as_millis()
-            .to_formatted_string(&Locale::en);
-        let average_relative_score = self.relative_score_sum / self.completed_count as f64;
+        let format = self.number_format;
+        let scored_count = self.printed_scores.scored_count().max(1) as f64;
+        let relative_score = result.relative_score_or_zero();
+        let score = if result.has_score() {
+            format.score(result.score_or_zero())
+        } else {
+            "-".to_string()
+        };
+        let average_score = format.float(self.score_sum / scored_count, nonzero2);
+        let execution_time = format.integer(result.execution_time().as_millis());
+        let average_relative_score = self.relative_score_sum / scored_count;
         self.score_width = self.score_width.max(score.len());
         let score_width = self.score_width;
         let average_score_width = score_width + 3;
 
+        // 失敗したケースは行全体を黄色で表示するため、相対スコアのみの色付けは成功時に限る
+        let relative_score_cell = format!("{relative_score:8.3}");
+        let relative_score_cell = if !result.has_score() {
+            format!("{:>8}", "-")
+        } else if result.score().is_ok() {
+            self.colorize_relative_score(relative_score, relative_score_cell)
+        } else {
+            relative_score_cell
+        };
+
         let record = format!(
-            "| {:digit$} / {:digit$} | {:04} | {:>score_width$} | {:8.3} | {:>average_score_width$} | {:8.3} | {:>6} ms |",
+            "| {:digit$} / {:digit$} | {:04} | {:>score_width$} | {} | {:>average_score_width$} | {:8.3} | {:>6} ms |",
             self.completed_count,
             self.testcase_count,
             result.test_case().seed(),
             score,
-            relative_score,
+            relative_score_cell,
             average_score,
             average_relative_score,
             execution_time,
@@ -77,20 +133,23 @@ impl Printer for ConsolePrinter {
 
     fn print_summary(&mut self, writer: &mut dyn Write, stats: &TestStats) -> Result<()> {
         let nonzero2 = NonZero::new(2).unwrap();
-        let average_score = format_float_with_commas(
-            stats.score_sum as f64 / stats.results.len() as f64,
-            nonzero2,
-        );
-        let average_score_log10 = stats.score_sum_log10 / stats.results.len() as f64;
-        let average_relative_score = stats.relative_score_sum / stats.results.len() as f64;
+        let format = self.number_format;
+        let average_score = format.average_score(stats.average_score());
+        let average_score_log10 = stats.average_score_log10();
+        let average_relative_score = stats.average_relative_score();
         let ac_count =
             stats.results.len() - stats.results.iter().filter(|r| r.score().is_err()).count();
 
         writeln!(writer, "Average Score          : {average_score}")?;
         writeln!(writer, "Average Score (log10)  : {average_score_log10:.5}")?;
+        let weighted = if stats.is_weighted() {
+            " (weighted)"
+        } else {
+            ""
+        };
         writeln!(
             writer,
-            "Average Relative Score : {average_relative_score:.3}"
+            "Average Relative Score : {average_relative_score:.3}{weighted}"
         )?;
 
         let ac = format!("{} / {}", ac_count, stats.results.len());
@@ -101,6 +160,10 @@ impl Printer for ConsolePrinter {
         };
         writeln!(writer, "Accepted               : {ac}")?;
 
+        if let Some(error_counts) = format_error_counts(&stats.results) {
+            writeln!(writer, "Errors                 : {error_counts}")?;
+        }
+
         let max_time = stats
             .results
             .iter()
@@ -110,9 +173,51 @@ impl Printer for ConsolePrinter {
         writeln!(
             writer,
             "Max Execution Time     : {} ms",
-            max_time.to_formatted_string(&Locale::en)
+            format.integer(max_time)
         )?;
 
+        // ソルバ以外のステップ（入力生成や採点など）も含めた所要時間と比較できるようにする
+        let total_execution_time = stats
+            .results
+            .iter()
+            .map(|r| r.execution_time().as_millis())
+            .sum::<u128>();
+        let total_wall_time = stats
+            .results
+            .iter()
+            .map(|r| r.wall_time().as_millis())
+            .sum::<u128>();
+        writeln!(
+            writer,
+            "Total Time             : {} ms (execution) / {} ms (wall)",
+            format.integer(total_execution_time),
+            format.integer(total_wall_time)
+        )?;
+
+        // 失敗したケースは両方の分布で0として集計されるため、その件数を併記する
+        let failed_count = stats.results.iter().filter(|r| r.score().is_err()).count();
+        let failed_note = Distribution::failed_note(failed_count);
+
+        if let Some(dist) = &stats.score_distribution {
+            let format = |x| format.float(x, nonzero2);
+            writeln!(
+                writer,
+                "Score Distribution     : {}{}",
+                dist.format(format),
+                failed_note
+            )?;
+        }
+
+        if let Some(dist) = &stats.relative_score_distribution {
+            let format = |x| format!("{x:.3}");
+            writeln!(
+                writer,
+                "Relative Distribution  : {}{}",
+                dist.format(format),
+                failed_note
+            )?;
+        }
+
         Ok(())
     }
 }
@@ -125,8 +230,30 @@ impl ConsolePrinter {
             testcase_count,
             completed_count: 0,
             score_width: 7,
-            score_sum: 0,
+            score_sum: 0.0,
             relative_score_sum: 0.0,
+            printed_scores: PrintedScores::default(),
+            number_format: NumberFormat::default(),
+            relative_score_colors: RelativeScoreColors::default(),
+        }
+    }
+
+    /// 数値の表示形式と相対スコアの色付けの閾値を設定する
+    pub(super) const fn with_display(mut self, display: DisplaySettings) -> Self {
+        self.number_format = display.number_format();
+        self.relative_score_colors = display.relative_score_colors;
+        self
+    }
+
+    fn colorize_relative_score(&self, relative_score: f64, cell: String) -> String {
+        let colors = &self.relative_score_colors;
+
+        if relative_score > colors.green_above {
+            cell.green().to_string()
+        } else if relative_score < colors.red_below {
+            cell.red().to_string()
+        } else {
+            cell.yellow().to_string()
         }
     }
 
@@ -136,7 +263,7 @@ impl ConsolePrinter {
         // スコア列の幅を決定する（スコアの桁数 + 余裕分3桁）
         self.score_width = self
             .score_width
-            .max(self.score_sum.to_formatted_string(&Locale::en).len() + 3);
+            .max(self.number_format.score(self.score_sum).len() + 3);
 
         let test_width = (self.testcase_count.to_string().len() * 2 + 3).max(9);
         let score_width1 = self.score_width + 11;
@@ -169,32 +296,180 @@ impl ConsolePrinter {
     }
 }
 
+/// 失敗の種類ごとのケース数を文字列化する（失敗したケースがない場合は `None` ）
+/// 主要な種類は0件でも表示し、それ以外は1件以上の場合のみ表示する
+fn format_error_counts(results: &[TestResult]) -> Option<String> {
+    let mut counts = BTreeMap::new();
+
+    for kind in [
+        CaseErrorKind::WrongAnswer,
+        CaseErrorKind::Tle,
+        CaseErrorKind::Mle,
+        CaseErrorKind::RuntimeError,
+    ] {
+        counts.insert(kind, 0);
+    }
+
+    for result in results {
+        if let Err(e) = result.score() {
+            *counts.entry(e.kind()).or_insert(0) += 1;
+        }
+    }
+
+    if results.iter().all(|r| r.score().is_ok()) {
+        return None;
+    }
+
+    let counts = counts
+        .into_iter()
+        .map(|(kind, count)| format!("{}: {count}", kind.label()))
+        .collect::<Vec<_>>();
+    Some(counts.join(", "))
+}
+
+/// 表の代わりにプログレスバーを標準エラー出力に表示するプリンタ
+pub(super) struct ProgressPrinter {
+    bar: ProgressBar,
+    score_sum: f64,
+    relative_score_sum: f64,
+    /// 出力済みのケースのスコアと相対スコア（リトライ時の二重計上を防ぐため）
+    printed_scores: PrintedScores,
+    summary_printer: ConsolePrinter,
+}
+
+impl ProgressPrinter {
+    pub(super) fn new(testcase_count: usize, display: DisplaySettings) -> Self {
+        Self::with_draw_target(testcase_count, display, ProgressDrawTarget::stderr())
+    }
+
+    fn with_draw_target(
+        testcase_count: usize,
+        display: DisplaySettings,
+        target: ProgressDrawTarget,
+    ) -> Self {
+        let style = ProgressStyle::with_template(
+            "[{elapsed_precise}] {bar:40.cyan/blue} {pos:>4}/{len:4} (ETA {eta}) {msg}",
+        )
+        .expect("Invalid progress bar template")
+        .progress_chars("=> ");
+        let bar = ProgressBar::with_draw_target(Some(testcase_count as u64), target);
+        bar.set_style(style);
+
+        Self {
+            bar,
+            score_sum: 0.0,
+            relative_score_sum: 0.0,
+            printed_scores: PrintedScores::default(),
+            summary_printer: ConsolePrinter::new(testcase_count).with_display(display),
+        }
+    }
+}
+
+impl Printer for ProgressPrinter {
+    fn print_case(&mut self, writer: &mut dyn Write, result: &TestResult) -> Result<()> {
+        // リトライされたケースは進捗に含めず、前回の結果を差し替える
+        let (score_delta, relative_score_delta, is_new) = self.printed_scores.insert(result);
+
+        if is_new {
+            self.bar.inc(1);
+        }
+
+        self.score_sum += score_delta;
+        self.relative_score_sum += relative_score_delta;
+
+        let scored_count = self.printed_scores.scored_count().max(1) as f64;
+        self.bar.set_message(format!(
+            "Average Score: {} / Relative: {:.3}",
+            self.summary_printer
+                .number_format
+                .float(self.score_sum / scored_count, NonZero::new(2).unwrap()),
+            self.relative_score_sum / scored_count
+        ));
+
+        // 失敗したケースのみプログレスバーの上に出力する
+        if let Err(e) = result.score() {
+            let message = format!("Seed {:04}: {}", result.test_case().seed(), e);
+            self.bar
+                .suspend(|| writeln!(writer, "{}", message.yellow()))?;
+        }
+
+        Ok(())
+    }
+
+    fn print_summary(&mut self, writer: &mut dyn Write, stats: &TestStats) -> Result<()> {
+        self.bar.finish_and_clear();
+        self.summary_printer.print_summary(writer, stats)
+    }
+}
+
+/// 各ケースの結果を出力しないプリンタ
+pub(super) struct QuietPrinter {
+    summary_printer: Option<ConsolePrinter>,
+}
+
+impl QuietPrinter {
+    pub(super) fn new(
+        testcase_count: usize,
+        print_summary: bool,
+        display: DisplaySettings,
+    ) -> Self {
+        Self {
+            summary_printer: print_summary
+                .then(|| ConsolePrinter::new(testcase_count).with_display(display)),
+        }
+    }
+}
+
+impl Printer for QuietPrinter {
+    fn print_case(&mut self, _writer: &mut dyn Write, _result: &TestResult) -> Result<()> {
+        Ok(())
+    }
+
+    fn print_summary(&mut self, writer: &mut dyn Write, stats: &TestStats) -> Result<()> {
+        match &mut self.summary_printer {
+            Some(printer) => printer.print_summary(writer, stats),
+            None => Ok(()),
+        }
+    }
+}
+
 pub(super) struct JsonPrinter {
     completed_count: usize,
+    printed_seeds: HashSet<u64>,
 }
 
 impl JsonPrinter {
     pub(super) fn new() -> Self {
-        Self { completed_count: 0 }
+        Self {
+            completed_count: 0,
+            printed_seeds: HashSet::new(),
+        }
     }
 }
 
 impl Printer for JsonPrinter {
     fn print_case(&mut self, writer: &mut dyn Write, result: &TestResult) -> Result<()> {
-        self.completed_count += 1;
+        // リトライされたケースは進捗に含めない
+        if self.printed_seeds.insert(result.test_case().seed()) {
+            self.completed_count += 1;
+        }
 
         let record = JsonRecord {
+            record_type: "case",
             progress: self.completed_count,
             seed: result.test_case().seed(),
-            score: result.score().as_ref().map(|s| s.get()).unwrap_or(0),
-            relative_score: result.relative_score().as_ref().copied().unwrap_or(0.0),
+            score: result.score_or_zero(),
+            relative_score: result.relative_score_or_zero(),
+            unscored: !result.has_score(),
             execution_time: result.execution_time().as_secs_f64(),
+            wall_time: result.wall_time().as_secs_f64(),
             error_message: result
                 .score()
                 .as_ref()
                 .err()
                 .map(|e| e.to_string())
                 .unwrap_or_default(),
+            error_type: result.score().as_ref().err().map(|e| e.kind()),
         };
 
         writeln!(writer, "{}", serde_json::to_string(&record)?)?;
@@ -202,27 +477,74 @@ impl Printer for JsonPrinter {
         Ok(())
     }
 
-    fn print_summary(&mut self, _writer: &mut dyn Write, _stats: &TestStats) -> Result<()> {
-        // do nothing
+    fn print_summary(&mut self, writer: &mut dyn Write, stats: &TestStats) -> Result<()> {
+        let case_count = stats.results.len();
+        let accepted_count = stats.results.iter().filter(|r| r.score().is_ok()).count();
+        let max_execution_time = stats
+            .results
+            .iter()
+            .map(|r| r.execution_time().as_secs_f64())
+            .fold(0.0, f64::max);
+
+        let record = JsonSummaryRecord {
+            record_type: "summary",
+            case_count,
+            total_score: stats.score_sum,
+            average_score: stats.average_score(),
+            average_score_log10: stats.average_score_log10(),
+            average_relative_score: stats.average_relative_score(),
+            accepted_count,
+            max_execution_time,
+        };
+
+        writeln!(writer, "{}", serde_json::to_string(&record)?)?;
+
         Ok(())
     }
 }
 
 #[derive(Serialize)]
 struct JsonRecord {
+    #[serde(rename = "type")]
+    record_type: &'static str,
     progress: usize,
     seed: u64,
-    score: u64,
+    #[serde(serialize_with = "serialize_score")]
+    score: f64,
     relative_score: f64,
+    /// 期待される出力との一致のみで正解としたケース（スコアは0として出力する）
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    unscored: bool,
     execution_time: f64,
+    wall_time: f64,
     error_message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error_type: Option<CaseErrorKind>,
+}
+
+/// 全ケース終了後に出力される集計結果
+#[derive(Serialize)]
+struct JsonSummaryRecord {
+    #[serde(rename = "type")]
+    record_type: &'static str,
+    case_count: usize,
+    #[serde(serialize_with = "serialize_score")]
+    total_score: f64,
+    average_score: f64,
+    average_score_log10: f64,
+    average_relative_score: f64,
+    accepted_count: usize,
+    max_execution_time: f64,
 }
 
 #[cfg(test)]
 mod test {
-    use crate::runner::{multi::TestCase, single::Objective};
+    use crate::runner::{
+        multi::TestCase,
+        single::{CaseError, Objective, Score},
+    };
     use chrono::Local;
-    use std::{num::NonZero, time::Duration};
+    use std::time::Duration;
 
     use super::*;
 
@@ -245,15 +567,19 @@ mod test {
             "| Progress  | Seed |     Case Score      |     Average Score      |   Exec.   |
 |           |      |  Score   | Relative |    Score    | Relative |   Time    |
 |-----------|------|----------|----------|-------------|----------|-----------|
-|   1 /   3 | 0000 |    1,000 | 1000.000 |    1,000.00 | 1000.000 |  1,234 ms |
-|   2 /   3 | 0001 |      500 |  500.000 |      750.00 |  750.000 | 12,345 ms |
+|   1 /   3 | 0000 |    1,000 | \u{1b}[32m1000.000\u{1b}[0m |    1,000.00 | 1000.000 |  1,234 ms |
+|   2 /   3 | 0001 |      500 | \u{1b}[32m 500.000\u{1b}[0m |      750.00 |  750.000 | 12,345 ms |
 \u{1b}[33m|   3 /   3 | 0002 |        0 |    0.000 |      500.00 |  500.000 |      1 ms |\u{1b}[0m
 \u{1b}[33merror\u{1b}[0m
 Average Score          : 500.00
 Average Score (log10)  : 1.89966
 Average Relative Score : 500.000
 Accepted               : \u{1b}[1;33m2 / 3\u{1b}[0m
+Errors                 : WA: 0, TLE: 0, MLE: 0, RE: 1
 Max Execution Time     : 12,345 ms
+Total Time             : 13,580 ms (execution) / 13,580 ms (wall)
+Score Distribution     : min 0.00 / median 500.00 / p90 900.00 / p99 990.00 / max 1,000.00 / stddev 408.25 (1 failed counted as 0)
+Relative Distribution  : min 0.000 / median 500.000 / p90 900.000 / p99 990.000 / max 1000.000 / stddev 408.248 (1 failed counted as 0)
 ";
 
         println!("[EXPECTED]");
@@ -268,6 +594,122 @@ Max Execution Time     : 12,345 ms
         assert_eq!(expected, actual);
     }
 
+    #[test]
+    fn test_console_printer_relative_score_colors() {
+        colored::control::set_override(true);
+        let printer = ConsolePrinter::new(1).with_display(DisplaySettings {
+            relative_score_colors: RelativeScoreColors {
+                green_above: 100.0,
+                red_below: 90.0,
+            },
+            ..Default::default()
+        });
+
+        let colorize = |x: f64| printer.colorize_relative_score(x, x.to_string());
+        assert_eq!(colorize(100.5), "\u{1b}[32m100.5\u{1b}[0m");
+        assert_eq!(colorize(100.0), "\u{1b}[33m100\u{1b}[0m");
+        assert_eq!(colorize(90.0), "\u{1b}[33m90\u{1b}[0m");
+        assert_eq!(colorize(89.9), "\u{1b}[31m89.9\u{1b}[0m");
+    }
+
+    #[test]
+    fn test_console_printer_retried_case() {
+        colored::control::set_override(true);
+        let mut printer = ConsolePrinter::new(2);
+        let mut buf = Box::new(vec![]);
+
+        let failed = TestResult::new(
+            TestCase::new(0, None, Objective::Max),
+            Err(CaseError::RuntimeError("error".to_string())),
+            Duration::from_millis(1),
+        );
+        let retried = TestResult::new(
+            TestCase::new(0, None, Objective::Max),
+            Ok(Score::new(1000.0).unwrap()),
+            Duration::from_millis(1),
+        );
+
+        printer.print_case(&mut buf, &failed).unwrap();
+        printer.print_case(&mut buf, &retried).unwrap();
+
+        assert_eq!(printer.completed_count, 1);
+        assert_eq!(printer.score_sum, 1000.0);
+        assert_eq!(printer.relative_score_sum, 100.0);
+    }
+
+    #[test]
+    fn test_progress_printer() {
+        colored::control::set_override(true);
+        let mut printer = ProgressPrinter::with_draw_target(
+            3,
+            DisplaySettings::default(),
+            ProgressDrawTarget::hidden(),
+        );
+
+        let test_results = gen_test_results();
+        let mut buf = Box::new(vec![]);
+
+        for result in test_results.iter() {
+            printer.print_case(&mut buf, result).unwrap();
+        }
+
+        assert_eq!(printer.bar.position(), 3);
+        assert_eq!(printer.score_sum, 1500.0);
+        assert_eq!(
+            String::from_utf8(*buf).unwrap(),
+            "\u{1b}[33mSeed 0002: error\u{1b}[0m\n"
+        );
+    }
+
+    #[test]
+    fn test_format_error_counts() {
+        let results = [
+            TestResult::for_seed(0, Ok(Score::new(1.0).unwrap())),
+            TestResult::for_seed(1, Err(CaseError::WrongAnswer)),
+            TestResult::for_seed(2, Err(CaseError::WrongAnswer)),
+            TestResult::for_seed(3, Err(CaseError::Tle)),
+            TestResult::for_seed(4, Err(CaseError::ScoreNotFound)),
+        ];
+
+        assert_eq!(
+            format_error_counts(&results).as_deref(),
+            Some("WA: 2, TLE: 1, MLE: 0, RE: 0, NoScore: 1")
+        );
+        assert_eq!(format_error_counts(&results[..1]), None);
+    }
+
+    #[test]
+    fn test_quiet_printer() {
+        let test_results = gen_test_results();
+        let stats = TestStats::new(test_results.clone(), Local::now());
+
+        let mut printer = QuietPrinter::new(3, false, DisplaySettings::default());
+        let mut buf = vec![];
+
+        for result in test_results.iter() {
+            printer.print_case(&mut buf, result).unwrap();
+        }
+        printer.print_summary(&mut buf, &stats).unwrap();
+        assert!(buf.is_empty());
+
+        let mut printer = QuietPrinter::new(3, true, DisplaySettings::default());
+        printer.print_summary(&mut buf, &stats).unwrap();
+        let actual = String::from_utf8(buf).unwrap();
+        assert!(actual.starts_with("Average Score          : 500.00\n"));
+
+        let display = DisplaySettings {
+            group_digits: false,
+            score_decimals: 0,
+            ..Default::default()
+        };
+        let mut printer = QuietPrinter::new(3, true, display);
+        let mut buf = vec![];
+        printer.print_summary(&mut buf, &stats).unwrap();
+        let actual = String::from_utf8(buf).unwrap();
+        assert!(actual.starts_with("Average Score          : 500\n"));
+        assert!(actual.contains("Max Execution Time     : 12345 ms\n"));
+    }
+
     #[test]
     fn test_json_printer() {
         let mut printer = JsonPrinter::new();
@@ -279,10 +721,14 @@ Max Execution Time     : 12,345 ms
         for result in test_results.iter() {
             printer.print_case(&mut buf, result).unwrap();
         }
+        printer
+            .print_summary(&mut buf, &TestStats::new(test_results, Local::now()))
+            .unwrap();
 
-        let expected = r##"{"progress":1,"seed":0,"score":1000,"relative_score":1000.0,"execution_time":1.234,"error_message":""}
-{"progress":2,"seed":1,"score":500,"relative_score":500.0,"execution_time":12.345,"error_message":""}
-{"progress":3,"seed":2,"score":0,"relative_score":0.0,"execution_time":0.001,"error_message":"error"}
+        let expected = r##"{"type":"case","progress":1,"seed":0,"score":1000,"relative_score":1000.0,"execution_time":1.234,"wall_time":1.234,"error_message":""}
+{"type":"case","progress":2,"seed":1,"score":500,"relative_score":500.0,"execution_time":12.345,"wall_time":12.345,"error_message":""}
+{"type":"case","progress":3,"seed":2,"score":0,"relative_score":0.0,"execution_time":0.001,"wall_time":0.001,"error_message":"error","error_type":"runtime_error"}
+{"type":"summary","case_count":3,"total_score":1500,"average_score":500.0,"average_score_log10":1.8996566681120062,"average_relative_score":500.0,"accepted_count":2,"max_execution_time":12.345}
 "##;
 
         println!("[EXPECTED]");
@@ -300,18 +746,18 @@ Max Execution Time     : 12,345 ms
     fn gen_test_results() -> Vec<TestResult> {
         vec![
             TestResult::new(
-                TestCase::new(0, NonZero::new(100), Objective::Max),
-                Ok(NonZero::new(1000).unwrap()),
+                TestCase::new(0, Score::new(100.0), Objective::Max),
+                Ok(Score::new(1000.0).unwrap()),
                 Duration::from_millis(1234),
             ),
             TestResult::new(
-                TestCase::new(1, NonZero::new(100), Objective::Max),
-                Ok(NonZero::new(500).unwrap()),
+                TestCase::new(1, Score::new(100.0), Objective::Max),
+                Ok(Score::new(500.0).unwrap()),
                 Duration::from_millis(12345),
             ),
             TestResult::new(
-                TestCase::new(2, NonZero::new(100), Objective::Max),
-                Err("error".to_string()),
+                TestCase::new(2, Score::new(100.0), Objective::Max),
+                Err(CaseError::RuntimeError("error".to_string())),
                 Duration::from_millis(1),
             ),
         ]
@@ -0,0 +1,96 @@
+use super::{build_single_runner, build_test_cases, compile, io, multi};
+use crate::{git, settings::Settings, util::CurrentDirGuard};
+use anyhow::{Context as _, Result};
+use std::path::{Path, PathBuf};
+
+/// 一時的なworktree。破棄時にworktreeを削除する
+struct Worktree {
+    path: PathBuf,
+}
+
+impl Worktree {
+    fn new(tag_name: &str) -> Result<Self> {
+        let path = std::env::temp_dir().join(format!("pahcer-rerun-{}", std::process::id()));
+        git::add_worktree(&path, tag_name)
+            .with_context(|| format!("Failed to check out {tag_name} into {}.", path.display()))?;
+
+        Ok(Self { path })
+    }
+}
+
+impl Drop for Worktree {
+    fn drop(&mut self) {
+        if let Err(e) = git::remove_worktree(&self.path) {
+            eprintln!("Failed to remove the worktree {}: {e}", self.path.display());
+        }
+    }
+}
+
+/// タグ付けされた過去のコードを一時的なworktreeにチェックアウトし、現在の設定でテストを実行する
+/// 作業ツリー・ベストスコア・実行結果のファイルには一切変更を加えない
+pub(super) fn rerun(settings: &Settings, tag: &str, links: &[String]) -> Result<multi::TestStats> {
+    let tag_name = git::resolve_tag(tag, &settings.git)?;
+    let best_scores = io::load_best_scores(io::get_best_score_path(settings))?;
+
+    // worktreeより先にカレントディレクトリを元に戻す必要があるため、宣言順に注意
+    let worktree = Worktree::new(&tag_name)?;
+    println!("Checked out {tag_name} into {}", worktree.path.display());
+
+    for link in links {
+        link_path(Path::new(link), &worktree.path)?;
+    }
+
+    let _current_dir = CurrentDirGuard::new(&worktree.path)?;
+
+    compile(&settings.test.compile_steps, &settings.test.out_dir)?;
+
+    let single_runner = build_single_runner(settings)?;
+    let seeds = (settings.test.start_seed..settings.test.end_seed).collect();
+    let test_cases = build_test_cases(seeds, &best_scores, settings);
+
+    let mut runner = multi::MultiCaseRunner::new_console(
+        single_runner,
+        test_cases,
+        settings.test.threads,
+        0,
+        true,
+        settings.display,
+    )
+    .with_interrupt_handling(true)
+    .with_weights(super::load_weights(settings)?);
+
+    runner.run()
+}
+
+/// gitで管理されていないファイル（ローカルテスタなど）をworktreeから参照できるようにする
+fn link_path(path: &Path, worktree_dir: &Path) -> Result<()> {
+    let destination = worktree_dir.join(path);
+
+    // 存在しないパスや、既にworktreeに含まれているパスは無視する
+    if !path.exists() || destination.exists() {
+        return Ok(());
+    }
+
+    let source = std::fs::canonicalize(path)?;
+
+    if let Some(parent) = destination.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    symlink(&source, &destination)
+        .with_context(|| format!("Failed to link {} into the worktree.", path.display()))
+}
+
+#[cfg(unix)]
+fn symlink(source: &Path, destination: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(source, destination)
+}
+
+#[cfg(windows)]
+fn symlink(source: &Path, destination: &Path) -> std::io::Result<()> {
+    if source.is_dir() {
+        std::os::windows::fs::symlink_dir(source, destination)
+    } else {
+        std::os::windows::fs::symlink_file(source, destination)
+    }
+}
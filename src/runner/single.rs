@@ -1,58 +1,236 @@
+mod interactive;
+mod memory;
+
+use crate::util::{deserialize_program, format_command, serialize_score};
 use anyhow::{Context, Result};
 use clap::ValueEnum;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::{BTreeMap, HashMap},
     ffi::OsStr,
     fmt::Display,
-    num::NonZeroU64,
-    path::Path,
+    io::{Read, Write as _},
+    path::{Path, PathBuf},
+    process::{Child, ExitStatus, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, LazyLock,
+    },
+    thread::JoinHandle,
     time::{Duration, Instant},
 };
 
+/// Interval to poll a child process which has a time limit
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Maximum number of the last stderr lines included in the error message of a failed step
+const STDERR_TAIL_LINES: usize = 20;
+
+/// Maximum number of characters of stderr included in the error message of a failed step
+const STDERR_TAIL_CHARS: usize = 2000;
+
+/// Time to wait for the output of a killed process to be closed.
+/// A grandchild process may keep holding the pipes, so the readers are not waited for indefinitely.
+const KILLED_OUTPUT_GRACE: Duration = Duration::from_millis(500);
+
+/// Maximum number of lines of the diff included in the error message of a mismatched output
+const DIFF_MAX_LINES: usize = 40;
+
+/// Placeholder of the seed with a format spec: an optional zero-padded width and an optional hex radix
+/// (e.g. `{SEED:06}`, `{SEED:x}`, `{SEED:08X}`)
+static FORMATTED_SEED_PLACEHOLDER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{SEED:(?:0(?P<width>\d{1,2}))?(?P<radix>[xX])?\}").unwrap());
+
+/// Placeholder of the seed formatted by `test.seed_format`
+const CASE_PLACEHOLDER: &str = "{CASE}";
+
+/// Placeholder of an environment variable (e.g. `${CONTEST_DIR}`)
+static ENV_VAR_PLACEHOLDER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\$\{(?P<name>[A-Za-z_][A-Za-z0-9_]*)\}").unwrap());
+
+/// Placeholder of a tuning parameter given by `--param` (e.g. `{PARAM:temp}`)
+static PARAM_PLACEHOLDER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{PARAM:(?P<name>[A-Za-z_][A-Za-z0-9_]*)\}").unwrap());
+
+/// Prefix of the environment variables which give the values of the parameters not given by `--param`
+const PARAM_ENV_PREFIX: &str = "PAHCER_PARAM_";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub(crate) struct TestStep {
+    #[serde(deserialize_with = "deserialize_program")]
     program: String,
     args: Vec<String>,
     current_dir: Option<String>,
     stdin: Option<String>,
+    /// Text written to stdin instead of the file specified by `stdin`
+    stdin_text: Option<String>,
     stdout: Option<String>,
     stderr: Option<String>,
     measure_time: bool,
+    timeout_ms: Option<u64>,
+    memory_limit_mb: Option<u64>,
+    /// Maximum number of bytes captured from each of stdout and stderr
+    max_output_bytes: Option<u64>,
+    env: Option<HashMap<String, String>>,
+    /// The solver which interacts with this step (the tester) through pipes
+    solver: Option<interactive::SolverCommand>,
+    /// Path to the file which stdout is expected to match
+    expected: Option<String>,
+    /// Whether to extract the score even if the process exits with a non-zero status
+    #[serde(default)]
+    ignore_exit_status: bool,
+}
+
+/// The score of a test case. It is always positive and finite.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Score(f64);
+
+impl Score {
+    /// Returns `None` if the score is 0 or below (or not finite), which is treated as Wrong Answer.
+    pub(crate) fn new(score: f64) -> Option<Self> {
+        (score.is_finite() && score > 0.0).then_some(Self(score))
+    }
+
+    pub const fn get(self) -> f64 {
+        self.0
+    }
+}
+
+impl Serialize for Score {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_score(&self.0, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Score {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let score = f64::deserialize(deserializer)?;
+        Score::new(score).ok_or_else(|| serde::de::Error::custom("score must be positive"))
+    }
+}
+
+impl TestStep {
+    /// Checks that the settings of the step are consistent.
+    pub(crate) fn validate(&self) -> Result<()> {
+        anyhow::ensure!(
+            self.stdin.is_none() || self.stdin_text.is_none(),
+            "stdin and stdin_text cannot be specified at the same time. program: {}",
+            self.program
+        );
+        anyhow::ensure!(
+            self.solver.is_none() || self.expected.is_none(),
+            "expected cannot be specified when solver is specified. program: {}",
+            self.program
+        );
+
+        Ok(())
+    }
+
+    pub(super) fn program(&self) -> &str {
+        &self.program
+    }
+
+    /// Replaces the `{CASE}` placeholders with `seed_format`, whose seed placeholders are expanded for each seed later.
+    pub(super) fn expand_seed_format(&mut self, seed_format: &str) {
+        let expand = |s: &mut String| *s = s.replace(CASE_PLACEHOLDER, seed_format);
+
+        expand(&mut self.program);
+        self.args.iter_mut().for_each(expand);
+
+        for s in [
+            &mut self.current_dir,
+            &mut self.stdin,
+            &mut self.stdin_text,
+            &mut self.stdout,
+            &mut self.stderr,
+            &mut self.expected,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            expand(s);
+        }
+
+        if let Some(env) = &mut self.env {
+            env.values_mut().for_each(expand);
+        }
+
+        if let Some(solver) = &mut self.solver {
+            solver.expand_seed_format(seed_format);
+        }
+    }
+
+    /// Expands the `{PARAM:name}` placeholders in the arguments of the step and its solver.
+    pub(super) fn expand_params(&mut self, params: &HashMap<String, String>) -> Result<()> {
+        for arg in self.args.iter_mut() {
+            *arg = expand_params(arg, params)?;
+        }
+
+        if let Some(solver) = &mut self.solver {
+            solver.expand_params(params)?;
+        }
+
+        Ok(())
+    }
+
+    fn memory_limit_bytes(&self) -> Option<u64> {
+        self.memory_limit_mb
+            .map(|mb| mb.saturating_mul(1024 * 1024))
+    }
 }
 
+/// A seed to run, with the reference score to calculate the relative score.
 #[derive(Debug, Clone, Copy)]
-pub(super) struct TestCase {
+pub struct TestCase {
     seed: u64,
-    reference_score: Option<NonZeroU64>,
+    reference_score: Option<Score>,
     objective: Objective,
+    relative_score_mode: RelativeScoreMode,
+    /// Whether to cap the relative score at 100
+    clamp_relative: bool,
 }
 
 impl TestCase {
     pub(super) const fn new(
         seed: u64,
-        reference_score: Option<NonZeroU64>,
+        reference_score: Option<Score>,
         objective: Objective,
     ) -> Self {
         Self {
             seed,
             reference_score,
             objective,
+            relative_score_mode: RelativeScoreMode::Linear,
+            clamp_relative: false,
         }
     }
 
-    pub(super) fn calc_relative_score(&self, new_score: NonZeroU64) -> f64 {
+    pub(super) const fn with_relative_score_mode(
+        mut self,
+        relative_score_mode: RelativeScoreMode,
+    ) -> Self {
+        self.relative_score_mode = relative_score_mode;
+        self
+    }
+
+    pub(super) const fn with_clamp_relative(mut self, clamp_relative: bool) -> Self {
+        self.clamp_relative = clamp_relative;
+        self
+    }
+
+    pub(super) fn calc_relative_score(&self, new_score: Score) -> f64 {
         let Some(old_score) = self.reference_score else {
             return 100.0;
         };
 
-        match self.objective {
-            Objective::Max => new_score.get() as f64 / old_score.get() as f64 * 100.0,
-            Objective::Min => old_score.get() as f64 / new_score.get() as f64 * 100.0,
-        }
+        let relative_score = self
+            .relative_score_mode
+            .calc(new_score, old_score, self.objective);
+        clamp_relative_score(relative_score, self.clamp_relative)
     }
 
-    pub(super) fn is_best(&self, new_score: Option<NonZeroU64>) -> bool {
+    pub(super) fn is_best(&self, new_score: Option<Score>) -> bool {
         let Some(new_score) = new_score else {
             return false;
         };
@@ -67,23 +245,34 @@ impl TestCase {
         }
     }
 
-    pub(super) const fn seed(&self) -> u64 {
+    pub const fn seed(&self) -> u64 {
         self.seed
     }
 }
 
+/// The result of a test case.
 #[derive(Debug, Clone)]
-pub(super) struct TestResult {
+pub struct TestResult {
     test_case: TestCase,
-    score: Result<NonZeroU64, String>,
-    relative_score: Result<f64, String>,
+    score: Result<Score, CaseError>,
+    relative_score: Result<f64, CaseError>,
     execution_time: Duration,
+    /// Wall-clock time of all the steps, including the ones without `measure_time`
+    wall_time: Duration,
+    /// Tail of the captured output when the score regex did not match, to help diagnose the regex
+    unmatched_output: Option<String>,
+    /// Labeled sub-scores extracted by the named score patterns
+    sub_scores: HashMap<String, f64>,
+    /// Wall-clock time of each executed step, as pairs of the step index and the elapsed time
+    step_times: Vec<(usize, Duration)>,
+    /// Whether the case was accepted by the expected output without printing any score
+    unscored: bool,
 }
 
 impl TestResult {
     pub(super) fn new(
         test_case: TestCase,
-        score: Result<NonZeroU64, String>,
+        score: Result<Score, CaseError>,
         execution_time: Duration,
     ) -> Self {
         let relative_score = score.clone().map(|s| test_case.calc_relative_score(s));
@@ -93,33 +282,248 @@ impl TestResult {
             score,
             relative_score,
             execution_time,
+            wall_time: execution_time,
+            unmatched_output: None,
+            sub_scores: HashMap::new(),
+            step_times: vec![],
+            unscored: false,
+        }
+    }
+
+    /// Creates the result of a case accepted by the expected output without printing any score.
+    /// Such a case is left out of the score totals, the averages and the best scores.
+    pub(super) fn new_unscored(test_case: TestCase, execution_time: Duration) -> Self {
+        // `score` holds a placeholder only to mark the case as accepted; it is never aggregated
+        Self {
+            relative_score: Ok(0.0),
+            unscored: true,
+            ..Self::new(test_case, Ok(Score(1.0)), execution_time)
         }
     }
 
-    pub(super) const fn test_case(&self) -> &TestCase {
+    pub(super) const fn with_wall_time(mut self, wall_time: Duration) -> Self {
+        self.wall_time = wall_time;
+        self
+    }
+
+    pub(super) fn with_unmatched_output(mut self, unmatched_output: String) -> Self {
+        self.unmatched_output = Some(unmatched_output);
+        self
+    }
+
+    pub(super) fn with_sub_scores(mut self, sub_scores: HashMap<String, f64>) -> Self {
+        self.sub_scores = sub_scores;
+        self
+    }
+
+    pub(super) fn with_step_times(mut self, step_times: Vec<(usize, Duration)>) -> Self {
+        self.step_times = step_times;
+        self
+    }
+
+    pub const fn test_case(&self) -> &TestCase {
         &self.test_case
     }
 
-    pub(super) fn score(&self) -> &Result<NonZeroU64, String> {
+    /// Returns the score, or the error if the case failed.
+    /// Cases accepted without any score (see [`TestResult::has_score`]) hold a placeholder,
+    /// so use [`TestResult::scored`] to aggregate the scores.
+    pub fn score(&self) -> &Result<Score, CaseError> {
         &self.score
     }
 
-    /// Returns the score in log10 scale.
-    pub(super) fn score_log10(&self) -> Result<f64, &String> {
-        self.score.as_ref().map(|s| (s.get() as f64).log10())
+    /// Returns the score if the case was accepted with a score.
+    pub fn scored(&self) -> Option<Score> {
+        match &self.score {
+            Ok(score) if !self.unscored => Some(*score),
+            _ => None,
+        }
+    }
+
+    /// Returns the score to aggregate: 0 for failed cases and cases without any score.
+    pub(super) fn score_or_zero(&self) -> f64 {
+        self.scored().map_or(0.0, |s| s.get())
+    }
+
+    /// Returns the score in log10 scale if the case was accepted with a score.
+    pub(super) fn score_log10(&self) -> Option<f64> {
+        self.scored().map(|s| s.get().log10())
     }
 
-    pub(super) fn relative_score(&self) -> &Result<f64, String> {
+    pub fn relative_score(&self) -> &Result<f64, CaseError> {
         &self.relative_score
     }
 
-    pub(super) const fn execution_time(&self) -> Duration {
+    /// Returns the relative score to aggregate: 0 for failed cases and cases without any score.
+    pub(super) fn relative_score_or_zero(&self) -> f64 {
+        self.relative_score.as_ref().copied().unwrap_or(0.0)
+    }
+
+    /// Whether the score was extracted from the output (`false` if accepted without any score).
+    pub const fn has_score(&self) -> bool {
+        !self.unscored
+    }
+
+    pub const fn execution_time(&self) -> Duration {
         self.execution_time
     }
+
+    pub const fn wall_time(&self) -> Duration {
+        self.wall_time
+    }
+
+    pub(super) fn unmatched_output(&self) -> Option<&str> {
+        self.unmatched_output.as_deref()
+    }
+
+    pub const fn sub_scores(&self) -> &HashMap<String, f64> {
+        &self.sub_scores
+    }
+
+    pub(super) fn step_times(&self) -> &[(usize, Duration)] {
+        &self.step_times
+    }
+}
+
+#[cfg(test)]
+impl TestResult {
+    /// Creates a result of `seed` for tests, with no reference score and zero execution time.
+    pub(crate) fn for_seed(seed: u64, score: Result<Score, CaseError>) -> Self {
+        Self::new(
+            TestCase::new(seed, None, Objective::Max),
+            score,
+            Duration::ZERO,
+        )
+    }
+}
+
+/// The reason why a test case failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CaseError {
+    /// Time limit exceeded
+    Tle,
+    /// Memory limit exceeded
+    Mle,
+    /// The score was zero or negative
+    WrongAnswer,
+    /// The score regex did not match any line of the outputs
+    ScoreNotFound,
+    /// The output exceeded `max_output_bytes`
+    OutputTooLarge,
+    /// The stdout of a step did not match the expected output. Holds the diff.
+    OutputMismatch(String),
+    /// A step failed for any other reason (e.g. a non-zero exit status)
+    RuntimeError(String),
+}
+
+impl CaseError {
+    pub(super) const fn kind(&self) -> CaseErrorKind {
+        match self {
+            Self::Tle => CaseErrorKind::Tle,
+            Self::Mle => CaseErrorKind::Mle,
+            Self::WrongAnswer => CaseErrorKind::WrongAnswer,
+            Self::ScoreNotFound => CaseErrorKind::ScoreNotFound,
+            Self::OutputTooLarge => CaseErrorKind::OutputTooLarge,
+            Self::RuntimeError(_) => CaseErrorKind::RuntimeError,
+            Self::OutputMismatch(_) => CaseErrorKind::WrongAnswer,
+        }
+    }
+}
+
+impl Display for CaseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Tle => write!(f, "TLE"),
+            Self::Mle => write!(f, "MLE"),
+            Self::WrongAnswer => write!(f, "Wrong Answer"),
+            Self::ScoreNotFound => write!(f, "Score not found"),
+            Self::OutputTooLarge => write!(f, "Output too large"),
+            Self::RuntimeError(message) => write!(f, "{message}"),
+            Self::OutputMismatch(diff) => write!(f, "Wrong Answer (output mismatch)\n{diff}"),
+        }
+    }
+}
+
+/// The type of [`CaseError`] without its details, recorded as a tag in the JSON log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum CaseErrorKind {
+    WrongAnswer,
+    Tle,
+    Mle,
+    RuntimeError,
+    ScoreNotFound,
+    OutputTooLarge,
+}
+
+impl CaseErrorKind {
+    /// Short label shown in the summary
+    pub(super) const fn label(self) -> &'static str {
+        match self {
+            Self::Tle => "TLE",
+            Self::Mle => "MLE",
+            Self::WrongAnswer => "WA",
+            Self::ScoreNotFound => "NoScore",
+            Self::OutputTooLarge => "OLE",
+            Self::RuntimeError => "RE",
+        }
+    }
+}
+
+/// The error returned when a step is killed because it exceeded its time limit.
+/// Holds the execution time measured up to the kill point.
+#[derive(Debug, Clone, Copy)]
+struct TimeLimitExceeded(Duration);
+
+impl Display for TimeLimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Time limit exceeded ({} ms)", self.0.as_millis())
+    }
+}
+
+impl std::error::Error for TimeLimitExceeded {}
+
+/// The error returned when a step seems to have failed because it exceeded its memory limit.
+/// Holds the measured execution time.
+#[derive(Debug, Clone, Copy)]
+struct MemoryLimitExceeded(Duration);
+
+impl Display for MemoryLimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Memory limit exceeded")
+    }
 }
 
+impl std::error::Error for MemoryLimitExceeded {}
+
+/// The error returned when a step is killed because its output exceeded `max_output_bytes`.
+/// Holds the measured execution time.
+#[derive(Debug, Clone, Copy)]
+struct OutputTooLarge(Duration);
+
+impl Display for OutputTooLarge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Output too large")
+    }
+}
+
+impl std::error::Error for OutputTooLarge {}
+
+/// The error returned when the stdout of a step does not match the expected output.
+/// Holds the unified diff of the expected and actual outputs, and the measured execution time.
+#[derive(Debug, Clone)]
+struct OutputMismatch(String, Duration);
+
+impl Display for OutputMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Output mismatch\n{}", self.0)
+    }
+}
+
+impl std::error::Error for OutputMismatch {}
+
 /// The direction to optimize the score
-#[derive(Debug, Clone, Copy, ValueEnum, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
 pub(crate) enum Objective {
     /// Maximize the score
     Max,
@@ -136,48 +540,295 @@ impl Display for Objective {
     }
 }
 
+/// Caps the relative score at 100 if `clamp` is true.
+pub(crate) fn clamp_relative_score(relative_score: f64, clamp: bool) -> f64 {
+    if clamp {
+        relative_score.min(100.0)
+    } else {
+        relative_score
+    }
+}
+
+/// How to calculate the relative score against the reference score
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum RelativeScoreMode {
+    /// Ratio of the scores
+    #[default]
+    Linear,
+    /// Ratio of the scores in log10 scale (`log10(1 + score)`)
+    Log,
+}
+
+impl RelativeScoreMode {
+    /// Returns the relative score of `new_score` in percent, where `reference_score` is 100.
+    pub(crate) fn calc(
+        self,
+        new_score: Score,
+        reference_score: Score,
+        objective: Objective,
+    ) -> f64 {
+        let (new_score, reference_score) = match self {
+            RelativeScoreMode::Linear => (new_score.get(), reference_score.get()),
+            // 1未満のスコアでも正の値となるよう1を加えてから対数を取る
+            RelativeScoreMode::Log => (
+                new_score.get().ln_1p() / std::f64::consts::LN_10,
+                reference_score.get().ln_1p() / std::f64::consts::LN_10,
+            ),
+        };
+
+        match objective {
+            Objective::Max => new_score / reference_score * 100.0,
+            Objective::Min => reference_score / new_score * 100.0,
+        }
+    }
+}
+
+/// Stream which a captured output was written to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// Output of a step captured to extract the score, tagged with its stream
+pub(super) type CapturedOutput = (OutputStream, Vec<u8>);
+
+/// Output stream to extract the score from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum ScoreSource {
+    /// Both stdout and stderr
+    #[default]
+    Any,
+    /// Only stdout
+    Stdout,
+    /// Only stderr
+    Stderr,
+}
+
+impl ScoreSource {
+    /// Returns whether the score is extracted from `stream`.
+    const fn accepts(self, stream: OutputStream) -> bool {
+        matches!(
+            (self, stream),
+            (ScoreSource::Any, _)
+                | (ScoreSource::Stdout, OutputStream::Stdout)
+                | (ScoreSource::Stderr, OutputStream::Stderr)
+        )
+    }
+}
+
+/// How to combine the scores when the score pattern matches multiple times
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum ScoreAggregation {
+    /// Use the last matched score
+    #[default]
+    Last,
+    /// Use the sum of all the matched scores
+    Sum,
+    /// Use the maximum of all the matched scores
+    Max,
+    /// Use the minimum of all the matched scores
+    Min,
+}
+
+impl ScoreAggregation {
+    /// Returns `None` if `scores` is empty.
+    fn aggregate(self, scores: impl Iterator<Item = f64>) -> Option<f64> {
+        match self {
+            ScoreAggregation::Last => scores.last(),
+            ScoreAggregation::Sum => scores.reduce(|a, b| a + b),
+            ScoreAggregation::Max => scores.reduce(f64::max),
+            ScoreAggregation::Min => scores.reduce(f64::min),
+        }
+    }
+}
+
+/// Expands the `{PARAM:name}` placeholders in `s` with `params`,
+/// falling back to the environment variable `PAHCER_PARAM_<name>`.
+/// Returns an error if the value of a parameter is given by neither of them.
+fn expand_params(s: &str, params: &HashMap<String, String>) -> Result<String> {
+    let mut missing = None;
+    let expanded = PARAM_PLACEHOLDER.replace_all(s, |caps: &regex::Captures| {
+        let name = &caps["name"];
+        let value = params
+            .get(name)
+            .cloned()
+            .or_else(|| std::env::var(format!("{PARAM_ENV_PREFIX}{name}")).ok());
+
+        value.unwrap_or_else(|| {
+            missing.get_or_insert_with(|| name.to_string());
+            caps[0].to_string()
+        })
+    });
+
+    if let Some(name) = missing {
+        anyhow::bail!(
+            "The value of the parameter {name} is not given. Specify it with --param {name}=<VALUE> or the environment variable {PARAM_ENV_PREFIX}{name}."
+        );
+    }
+
+    Ok(expanded.into_owned())
+}
+
 #[derive(Debug, Clone)]
 pub(super) struct SingleCaseRunner {
     steps: Vec<TestStep>,
     score_pattern: Regex,
+    score_aggregation: ScoreAggregation,
+    /// Output stream to extract the score from
+    score_source: ScoreSource,
+    /// Maximum number of bytes from the end of each output to scan for the score
+    max_scan_bytes: Option<usize>,
+    /// Named patterns whose scores are recorded as sub-scores
+    sub_score_patterns: Vec<(String, Regex)>,
 }
 
 impl SingleCaseRunner {
-    pub(super) const fn new(steps: Vec<TestStep>, score_pattern: Regex) -> Self {
+    pub(super) const fn new(
+        steps: Vec<TestStep>,
+        score_pattern: Regex,
+        score_aggregation: ScoreAggregation,
+    ) -> Self {
         Self {
             steps,
             score_pattern,
+            score_aggregation,
+            score_source: ScoreSource::Any,
+            max_scan_bytes: None,
+            sub_score_patterns: vec![],
+        }
+    }
+
+    pub(super) const fn with_score_source(mut self, score_source: ScoreSource) -> Self {
+        self.score_source = score_source;
+        self
+    }
+
+    pub(super) const fn with_max_scan_bytes(mut self, max_scan_bytes: Option<usize>) -> Self {
+        self.max_scan_bytes = max_scan_bytes;
+        self
+    }
+
+    pub(super) fn with_sub_score_patterns(mut self, patterns: Vec<(String, Regex)>) -> Self {
+        self.sub_score_patterns = patterns;
+        self
+    }
+
+    /// Sets the format of the `{CASE}` placeholders in the steps.
+    pub(super) fn with_seed_format(mut self, seed_format: &str) -> Self {
+        for step in self.steps.iter_mut() {
+            step.expand_seed_format(seed_format);
         }
+
+        self
     }
 
     pub(super) fn run(&self, test_case: TestCase) -> TestResult {
-        let result = self.run_steps(test_case.seed);
+        let since = Instant::now();
+        let mut step_times = vec![];
+        let result = self.run_steps(test_case.seed, &mut step_times);
+        let wall_time = since.elapsed();
 
-        match result {
+        let result = match result {
             Ok((outputs, execution_time)) => {
-                let score = self.extract_score(&outputs);
+                let score = self.extract_score(&outputs, &self.score_pattern);
+                let sub_scores = self.extract_sub_scores(&outputs);
 
                 // 0点以下の場合はWrong Answerとして扱う
-                let score = match score {
-                    Some(score) => match NonZeroU64::new(score as u64) {
-                        Some(score) => Ok(score),
-                        None => Err("Wrong Answer".to_string()),
-                    },
-                    None => Err("Score not found".to_string()),
+                let result = match score {
+                    Some(score) => {
+                        let score = Score::new(score).ok_or(CaseError::WrongAnswer);
+                        TestResult::new(test_case, score, execution_time)
+                    }
+                    // スコアを出力しない問題では、期待される出力との一致をもって正解とする
+                    // 正規表現の設定ミスも診断できるよう、出力の末尾を保持しておく
+                    None if self.has_expected_output() => {
+                        let output = Self::tail_lines(&self.score_outputs(&outputs).concat());
+                        TestResult::new_unscored(test_case, execution_time)
+                            .with_unmatched_output(output)
+                    }
+                    // 終了ステータスを無視するステップは不正な出力で異常終了しうるため、スコアがなければWAとする
+                    None if self.ignores_exit_status() => {
+                        let output = Self::tail_lines(&self.score_outputs(&outputs).concat());
+                        TestResult::new(test_case, Err(CaseError::WrongAnswer), execution_time)
+                            .with_unmatched_output(output)
+                    }
+                    None => {
+                        // 正規表現の設定ミスを診断できるよう、出力の末尾を保持しておく
+                        let output = Self::tail_lines(&self.score_outputs(&outputs).concat());
+                        TestResult::new(test_case, Err(CaseError::ScoreNotFound), execution_time)
+                            .with_unmatched_output(output)
+                    }
                 };
-                TestResult::new(test_case, score, execution_time)
+
+                result.with_sub_scores(sub_scores)
             }
-            Err(e) => TestResult::new(test_case, Err(format!("{e:#}")), Duration::ZERO),
-        }
+            Err(e) => {
+                if let Some(&TimeLimitExceeded(execution_time)) = e.downcast_ref() {
+                    TestResult::new(test_case, Err(CaseError::Tle), execution_time)
+                } else if let Some(&MemoryLimitExceeded(execution_time)) = e.downcast_ref() {
+                    TestResult::new(test_case, Err(CaseError::Mle), execution_time)
+                } else if let Some(&OutputTooLarge(execution_time)) = e.downcast_ref() {
+                    TestResult::new(test_case, Err(CaseError::OutputTooLarge), execution_time)
+                } else if let Some(OutputMismatch(diff, execution_time)) = e.downcast_ref() {
+                    let error = CaseError::OutputMismatch(diff.clone());
+                    TestResult::new(test_case, Err(error), *execution_time)
+                } else {
+                    let message = format!("{e:#}");
+                    TestResult::new(
+                        test_case,
+                        Err(CaseError::RuntimeError(message)),
+                        Duration::ZERO,
+                    )
+                }
+            }
+        };
+
+        result.with_wall_time(wall_time).with_step_times(step_times)
     }
 
-    fn run_steps(&self, seed: u64) -> Result<(Vec<Vec<u8>>, Duration)> {
+    /// Runs all the steps for `seed`, recording the wall-clock time of each executed step in `step_times`.
+    /// Returns the captured outputs tagged with their streams, and the measured execution time.
+    fn run_steps(
+        &self,
+        seed: u64,
+        step_times: &mut Vec<(usize, Duration)>,
+    ) -> Result<(Vec<CapturedOutput>, Duration)> {
         let mut outputs = vec![];
         let mut execution_time = Duration::ZERO;
 
-        for step in self.steps.iter() {
-            let cmd = Self::build_cmd(step, seed)?;
-            let elapsed = Self::run_cmd(cmd, step, seed, &mut outputs)?;
+        for (i, step) in self.steps.iter().enumerate() {
+            let since = Instant::now();
+            let result = match &step.solver {
+                Some(solver) => Self::run_interactive(step, solver, seed, &mut outputs),
+                None => {
+                    let cmd = Self::build_cmd(step, seed)?;
+                    Self::run_cmd(cmd, step, seed, &mut outputs)
+                }
+            };
+            step_times.push((i, since.elapsed()));
+            let elapsed = match result {
+                Ok(elapsed) => elapsed,
+                Err(e) => {
+                    // 計測対象のステップであれば失敗までの時間を加算してから返す
+                    let total = |elapsed: Duration| match step.measure_time {
+                        true => execution_time + elapsed,
+                        false => execution_time,
+                    };
+
+                    if let Some(&TimeLimitExceeded(elapsed)) = e.downcast_ref() {
+                        return Err(TimeLimitExceeded(total(elapsed)).into());
+                    } else if let Some(&MemoryLimitExceeded(elapsed)) = e.downcast_ref() {
+                        return Err(MemoryLimitExceeded(total(elapsed)).into());
+                    } else if let Some(&OutputTooLarge(elapsed)) = e.downcast_ref() {
+                        return Err(OutputTooLarge(total(elapsed)).into());
+                    } else if let Some(OutputMismatch(diff, elapsed)) = e.downcast_ref() {
+                        return Err(OutputMismatch(diff.clone(), total(*elapsed)).into());
+                    }
+
+                    return Err(e);
+                }
+            };
 
             if step.measure_time {
                 execution_time += elapsed;
@@ -187,8 +838,113 @@ impl SingleCaseRunner {
         Ok((outputs, execution_time))
     }
 
+    /// Expands the leading `~` to the home directory and `${VAR}` to the value of the environment variable.
+    /// Undefined variables are left as they are.
+    fn expand_env(s: &str) -> String {
+        let home = std::env::home_dir();
+        let s = match (s.strip_prefix('~'), home) {
+            (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with(['/', '\\']) => {
+                format!("{}{rest}", home.display())
+            }
+            _ => s.to_string(),
+        };
+
+        ENV_VAR_PLACEHOLDER
+            .replace_all(&s, |caps: &regex::Captures| {
+                std::env::var(&caps["name"]).unwrap_or_else(|_| caps[0].to_string())
+            })
+            .into_owned()
+    }
+
+    /// Describes the commands of the test steps for `seed` with the placeholders expanded.
+    /// Nothing is executed; this is used by `--dry-run`.
+    pub(super) fn describe_steps(&self, seed: u64) -> String {
+        let mut description = String::new();
+
+        for (i, step) in self.steps.iter().enumerate() {
+            let args = step
+                .args
+                .iter()
+                .map(|s| Self::replace_placeholder(s, seed))
+                .collect::<Vec<_>>();
+            let program = Self::replace_placeholder(&step.program, seed);
+            description += &format!("  [{}] {}\n", i + 1, format_command(&program, &args));
+
+            let fields = [
+                ("current_dir", &step.current_dir),
+                ("stdin", &step.stdin),
+                ("expected", &step.expected),
+            ];
+
+            for (name, value) in fields {
+                if let Some(value) = value {
+                    let value = Self::replace_placeholder(value, seed);
+                    description += &format!("      {name}: {value}\n");
+                }
+            }
+
+            for (name, value) in [("stdout", &step.stdout), ("stderr", &step.stderr)] {
+                if let Some(value) = value {
+                    let value = Self::output_path(value, seed);
+                    description += &format!("      {name}: {value}\n");
+                }
+            }
+
+            if let Some(text) = &step.stdin_text {
+                let text = Self::replace_placeholder(text, seed);
+                description += &format!("      stdin_text: {text:?}\n");
+            }
+
+            if let Some(env) = &step.env {
+                let env = env.iter().collect::<BTreeMap<_, _>>();
+
+                for (key, value) in env {
+                    let value = Self::replace_placeholder(value, seed);
+                    description += &format!("      env: {key}={value}\n");
+                }
+            }
+
+            if let Some(solver) = &step.solver {
+                description += &solver.describe(seed);
+            }
+        }
+
+        description
+    }
+
+    /// Returns the paths of the files read (`stdin`) and written (`stdout` / `stderr`) by the test steps for `seed`.
+    pub(super) fn io_files(&self, seed: u64) -> Vec<PathBuf> {
+        let mut files = vec![];
+
+        for step in &self.steps {
+            if let Some(stdin) = &step.stdin {
+                files.push(PathBuf::from(Self::replace_placeholder(stdin, seed)));
+            }
+
+            for output in [&step.stdout, &step.stderr].into_iter().flatten() {
+                files.push(PathBuf::from(Self::output_path(output, seed)));
+            }
+
+            if let Some(expected) = &step.expected {
+                files.push(PathBuf::from(Self::replace_placeholder(expected, seed)));
+            }
+        }
+
+        files
+    }
+
+    /// Whether any step ignores its exit status.
+    fn ignores_exit_status(&self) -> bool {
+        self.steps.iter().any(|step| step.ignore_exit_status)
+    }
+
+    /// Whether any step compares its stdout with the expected output.
+    fn has_expected_output(&self) -> bool {
+        self.steps.iter().any(|step| step.expected.is_some())
+    }
+
     fn build_cmd(step: &TestStep, seed: u64) -> Result<std::process::Command, anyhow::Error> {
-        let mut cmd = std::process::Command::new(&step.program);
+        let mut cmd = std::process::Command::new(Self::replace_placeholder(&step.program, seed));
         cmd.args(step.args.iter().map(|s| Self::replace_placeholder(s, seed)));
 
         if let Some(dir) = &step.current_dir {
@@ -196,11 +952,29 @@ impl SingleCaseRunner {
             cmd.current_dir(dir);
         }
 
+        if let Some(env) = &step.env {
+            cmd.envs(
+                env.iter()
+                    .map(|(key, value)| (key, Self::replace_placeholder(value, seed))),
+            );
+        }
+
         if let Some(stdin) = &step.stdin {
             let stdin = Self::replace_placeholder(stdin, seed);
             let file = std::fs::File::open(&stdin)
                 .with_context(|| format!("Failed to open input file ({})", &stdin))?;
             cmd.stdin(file);
+        } else if step.stdin_text.is_some() {
+            cmd.stdin(Stdio::piped());
+        } else {
+            cmd.stdin(Stdio::null());
+        }
+
+        cmd.stdout(Stdio::piped());
+        cmd.stderr(Stdio::piped());
+
+        if let Some(limit) = step.memory_limit_bytes() {
+            memory::prepare(&mut cmd, limit);
         }
 
         Ok(cmd)
@@ -210,42 +984,288 @@ impl SingleCaseRunner {
         mut cmd: std::process::Command,
         step: &TestStep,
         seed: u64,
-        outputs: &mut Vec<Vec<u8>>,
+        outputs: &mut Vec<CapturedOutput>,
     ) -> Result<Duration, anyhow::Error> {
         let since = Instant::now();
-        let output = cmd
-            .output()
+        let mut child = cmd
+            .spawn()
             .with_context(|| format!("Failed to run. command: {cmd:?}"))?;
-        let execution_time = since.elapsed();
 
-        if let Some(stdout) = &step.stdout {
-            let stdout = Self::replace_placeholder(stdout, seed);
-            Self::write_output(Path::new(&stdout), &output.stdout)
-                .with_context(|| format!("Failed to write stdout to {stdout}"))?;
+        if let Some(limit) = step.memory_limit_bytes() {
+            memory::apply(&child, limit)
+                .with_context(|| format!("Failed to set the memory limit. command: {cmd:?}"))?;
         }
 
-        if let Some(stderr) = &step.stderr {
-            let stderr = Self::replace_placeholder(stderr, seed);
-            Self::write_output(Path::new(&stderr), &output.stderr)
-                .with_context(|| format!("Failed to write stderr to {stderr}"))?;
+        if let (Some(text), Some(mut stdin)) = (&step.stdin_text, child.stdin.take()) {
+            let text = Self::replace_placeholder(text, seed);
+
+            // パイプのバッファを超える入力でもデッドロックしないよう別スレッドで書き込む
+            // プロセスが入力を読み切らずに終了した場合の書き込みエラーは無視する
+            std::thread::spawn(move || {
+                let _ = stdin.write_all(text.as_bytes());
+            });
         }
 
-        outputs.push(output.stdout);
-        outputs.push(output.stderr);
+        let output_exceeded = Arc::new(AtomicBool::new(false));
+        let limit = step.max_output_bytes;
+        let stdout_reader = Self::spawn_reader(child.stdout.take(), limit, &output_exceeded);
+        let stderr_reader = Self::spawn_reader(child.stderr.take(), limit, &output_exceeded);
+
+        let timeout = step.timeout_ms.map(Duration::from_millis);
+        let output_exceeded = limit.is_some().then_some(output_exceeded.as_ref());
+        let status = Self::wait_with_timeout(&mut child, since, timeout, output_exceeded)
+            .with_context(|| format!("Failed to wait for the process. command: {cmd:?}"))?;
+        let execution_time = since.elapsed();
+
+        // 制限時間を超過した場合も、打ち切りまでの出力はファイルに書き出す
+        // 孫プロセスがパイプを保持し続けている可能性があるため、readerは一定時間だけ待つ
+        let Some(status) = status else {
+            let stdout = Self::join_killed_reader(stdout_reader);
+            let stderr = Self::join_killed_reader(stderr_reader);
+            Self::write_outputs(step, seed, stdout.as_deref(), stderr.as_deref())?;
+            return Err(TimeLimitExceeded(execution_time).into());
+        };
+
+        let output = std::process::Output {
+            status,
+            stdout: Self::join_reader(stdout_reader)?,
+            stderr: Self::join_reader(stderr_reader)?,
+        };
+
+        Self::write_outputs(step, seed, Some(&output.stdout), Some(&output.stderr))?;
+
+        // 上限までの出力はファイルに書き出した上でエラーとする
+        if output_exceeded.is_some_and(|e| e.load(Ordering::SeqCst)) {
+            return Err(OutputTooLarge(execution_time).into());
+        }
+
+        let is_mle = step.memory_limit_mb.is_some()
+            && memory::is_memory_limit_exceeded(output.status, &output.stderr);
+
+        let stdout_index = outputs.len();
+        outputs.push((OutputStream::Stdout, output.stdout));
+        outputs.push((OutputStream::Stderr, output.stderr));
+
+        if is_mle {
+            return Err(MemoryLimitExceeded(execution_time).into());
+        }
 
         // Perform the status check after file output operations to ensure stdout and stderr
         // are captured and saved even if the command execution fails. This ordering is critical
         // for debugging and logging purposes.
-        anyhow::ensure!(
-            output.status.success(),
-            "Failed to run ({}). command: {:?}",
-            output.status,
-            cmd
-        );
+        if !step.ignore_exit_status {
+            Self::check_status(
+                output.status,
+                &format!("{cmd:?}"),
+                outputs.last().map_or(&[], |(_, s)| s),
+            )?;
+        }
+
+        if let Some(expected) = &step.expected {
+            let path = Self::replace_placeholder(expected, seed);
+            let expected = std::fs::read(&path)
+                .with_context(|| format!("Failed to read the expected output {path}"))?;
+            Self::compare_output(&expected, &outputs[stdout_index].1)
+                .map_err(|diff| OutputMismatch(diff, execution_time))?;
+        }
 
         Ok(execution_time)
     }
 
+    /// Compares the actual output with the expected one, ignoring trailing whitespaces of each line,
+    /// trailing empty lines and the difference of line endings.
+    /// Returns the unified diff if they differ.
+    fn compare_output(expected: &[u8], actual: &[u8]) -> std::result::Result<(), String> {
+        let expected = Self::normalize_output(expected);
+        let actual = Self::normalize_output(actual);
+
+        if expected == actual {
+            return Ok(());
+        }
+
+        let diff = similar::TextDiff::from_lines(&expected, &actual)
+            .unified_diff()
+            .header("expected", "actual")
+            .to_string();
+        let lines = diff.lines().collect::<Vec<_>>();
+        let mut diff = lines[..lines.len().min(DIFF_MAX_LINES)].join("\n");
+
+        if lines.len() > DIFF_MAX_LINES {
+            let omitted = lines.len() - DIFF_MAX_LINES;
+            diff.push_str(&format!("\n... ({omitted} lines omitted)"));
+        }
+
+        Err(diff)
+    }
+
+    fn normalize_output(output: &[u8]) -> String {
+        let output = String::from_utf8_lossy(output);
+        let mut lines = output.lines().map(str::trim_end).collect::<Vec<_>>();
+
+        while lines.last().is_some_and(|line| line.is_empty()) {
+            lines.pop();
+        }
+
+        lines.iter().map(|line| format!("{line}\n")).collect()
+    }
+
+    /// Returns an error with the tail of stderr if the process failed.
+    fn check_status(status: ExitStatus, cmd: &str, stderr: &[u8]) -> Result<()> {
+        if status.success() {
+            return Ok(());
+        }
+
+        let stderr = Self::tail_lines(stderr);
+        let mut message = format!("Failed to run ({status}). command: {cmd}");
+
+        if !stderr.is_empty() {
+            message.push_str(&format!("\n--- stderr (tail) ---\n{stderr}"));
+        }
+
+        anyhow::bail!(message)
+    }
+
+    /// Returns the last lines of the output, truncated to keep the error message readable.
+    fn tail_lines(output: &[u8]) -> String {
+        let output = String::from_utf8_lossy(output);
+        let lines = output.trim_end().lines().collect::<Vec<_>>();
+        let skipped_lines = lines.len().saturating_sub(STDERR_TAIL_LINES);
+        let tail = lines[skipped_lines..].join("\n");
+
+        let char_count = tail.chars().count();
+        let tail = if char_count > STDERR_TAIL_CHARS {
+            let skipped_chars = char_count - STDERR_TAIL_CHARS;
+            format!(
+                "...{}",
+                tail.chars().skip(skipped_chars).collect::<String>()
+            )
+        } else {
+            tail
+        };
+
+        if skipped_lines > 0 {
+            format!("... ({skipped_lines} lines omitted)\n{tail}")
+        } else {
+            tail
+        }
+    }
+
+    /// Waits for the child process to exit.
+    /// Returns `None` if the process was killed because it did not finish within `timeout`.
+    fn wait_with_timeout(
+        child: &mut Child,
+        since: Instant,
+        timeout: Option<Duration>,
+        output_exceeded: Option<&AtomicBool>,
+    ) -> std::io::Result<Option<ExitStatus>> {
+        if timeout.is_none() && output_exceeded.is_none() {
+            return child.wait().map(Some);
+        }
+
+        loop {
+            if let Some(status) = child.try_wait()? {
+                return Ok(Some(status));
+            }
+
+            // 出力が上限を超えた場合は強制終了する（出力サイズの超過は呼び出し元で判定する）
+            if output_exceeded.is_some_and(|e| e.load(Ordering::SeqCst)) {
+                child.kill()?;
+                return child.wait().map(Some);
+            }
+
+            if timeout.is_some_and(|timeout| since.elapsed() >= timeout) {
+                child.kill()?;
+                child.wait()?;
+                return Ok(None);
+            }
+
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    /// Reads the pipe to the end in another thread.
+    /// If `limit` is specified, only the first `limit` bytes are captured and `exceeded` is set
+    /// when the output exceeds it.
+    fn spawn_reader(
+        pipe: Option<impl Read + Send + 'static>,
+        limit: Option<u64>,
+        exceeded: &Arc<AtomicBool>,
+    ) -> JoinHandle<std::io::Result<Vec<u8>>> {
+        let exceeded = Arc::clone(exceeded);
+
+        std::thread::spawn(move || {
+            let mut buf = vec![];
+
+            let Some(mut pipe) = pipe else {
+                return Ok(buf);
+            };
+
+            match limit {
+                Some(limit) => {
+                    // 上限を超えたかを判定するため1バイト多く読む
+                    pipe.by_ref().take(limit + 1).read_to_end(&mut buf)?;
+
+                    if buf.len() as u64 > limit {
+                        buf.truncate(limit as usize);
+                        exceeded.store(true, Ordering::SeqCst);
+                    }
+                }
+                None => {
+                    pipe.read_to_end(&mut buf)?;
+                }
+            }
+
+            Ok(buf)
+        })
+    }
+
+    fn join_reader(reader: JoinHandle<std::io::Result<Vec<u8>>>) -> Result<Vec<u8>> {
+        let buf = reader
+            .join()
+            .map_err(|_| anyhow::anyhow!("Failed to read the output"))?
+            .context("Failed to read the output")?;
+        Ok(buf)
+    }
+
+    /// Joins the reader of a killed process if it finishes within [`KILLED_OUTPUT_GRACE`].
+    /// Returns `None` if the pipe is still open or the output could not be read.
+    fn join_killed_reader(reader: JoinHandle<std::io::Result<Vec<u8>>>) -> Option<Vec<u8>> {
+        let since = Instant::now();
+
+        while !reader.is_finished() {
+            if since.elapsed() >= KILLED_OUTPUT_GRACE {
+                return None;
+            }
+
+            std::thread::sleep(POLL_INTERVAL);
+        }
+
+        Self::join_reader(reader).ok()
+    }
+
+    /// Writes the captured outputs to the `stdout` / `stderr` files of the step, if specified.
+    /// Outputs which are `None` are not written.
+    fn write_outputs(
+        step: &TestStep,
+        seed: u64,
+        stdout: Option<&[u8]>,
+        stderr: Option<&[u8]>,
+    ) -> Result<()> {
+        if let (Some(path), Some(stdout)) = (&step.stdout, stdout) {
+            let path = Self::output_path(path, seed);
+            Self::write_output(Path::new(&path), stdout)
+                .with_context(|| format!("Failed to write stdout to {path}"))?;
+        }
+
+        if let (Some(path), Some(stderr)) = (&step.stderr, stderr) {
+            let path = Self::output_path(path, seed);
+            Self::write_output(Path::new(&path), stderr)
+                .with_context(|| format!("Failed to write stderr to {path}"))?;
+        }
+
+        Ok(())
+    }
+
     fn create_parent_dir_all(path: impl AsRef<OsStr>) -> Result<()> {
         if let Some(parent) = std::path::Path::new(&path).parent() {
             std::fs::create_dir_all(parent)
@@ -255,6 +1275,21 @@ impl SingleCaseRunner {
         Ok(())
     }
 
+    /// Expands the placeholders in the path of `stdout` or `stderr`.
+    /// If the path is a directory (or ends with a separator), the output goes to `{SEED04}.txt` in it.
+    fn output_path(path: &str, seed: u64) -> String {
+        let path = Self::replace_placeholder(path, seed);
+
+        if path.ends_with(['/', std::path::MAIN_SEPARATOR]) || Path::new(&path).is_dir() {
+            Path::new(&path)
+                .join(format!("{seed:04}.txt"))
+                .to_string_lossy()
+                .into_owned()
+        } else {
+            path
+        }
+    }
+
     fn write_output(path: impl AsRef<OsStr>, contents: &[u8]) -> Result<()> {
         let path = Path::new(&path);
         Self::create_parent_dir_all(path)?;
@@ -263,22 +1298,99 @@ impl SingleCaseRunner {
         Ok(())
     }
 
-    fn extract_score(&self, outputs: &[Vec<u8>]) -> Option<f64> {
+    /// Returns the outputs of the stream specified by `score_source`.
+    fn score_outputs<'a>(&self, outputs: &'a [CapturedOutput]) -> Vec<&'a [u8]> {
         outputs
             .iter()
-            .filter_map(|s| {
-                let s = String::from_utf8_lossy(s);
-                self.score_pattern
-                    .captures_iter(&s)
-                    .filter_map(|m| m.name("score").and_then(|s| s.as_str().parse().ok()))
-                    .last()
+            .filter(|(stream, _)| self.score_source.accepts(*stream))
+            .map(|(_, output)| output.as_slice())
+            .collect()
+    }
+
+    /// Extracts the score from the outputs of the stream specified by `score_source` line by line.
+    /// Large or binary outputs are handled without converting the whole output into a string.
+    fn extract_score(&self, outputs: &[CapturedOutput], pattern: &Regex) -> Option<f64> {
+        let outputs = self.score_outputs(outputs);
+
+        // 最後の一致のみが必要な場合は末尾から走査し、見つかった時点で打ち切る
+        if self.score_aggregation == ScoreAggregation::Last {
+            return outputs.iter().rev().find_map(|output| {
+                self.scan_lines(output)
+                    .rev()
+                    .find_map(|line| Self::find_scores(line, pattern).pop())
+            });
+        }
+
+        let scores = outputs.iter().flat_map(|output| {
+            self.scan_lines(output)
+                .flat_map(|line| Self::find_scores(line, pattern))
+        });
+
+        self.score_aggregation.aggregate(scores)
+    }
+
+    /// Extracts the sub-scores by each named pattern. Patterns without a match are omitted.
+    fn extract_sub_scores(&self, outputs: &[CapturedOutput]) -> HashMap<String, f64> {
+        self.sub_score_patterns
+            .iter()
+            .filter_map(|(name, pattern)| {
+                self.extract_score(outputs, pattern)
+                    .map(|score| (name.clone(), score))
             })
-            .next_back()
+            .collect()
+    }
+
+    /// Splits the output into lines, keeping only the last `max_scan_bytes` bytes.
+    fn scan_lines<'a>(&self, output: &'a [u8]) -> impl DoubleEndedIterator<Item = &'a [u8]> {
+        let output = match self.max_scan_bytes {
+            Some(max) if output.len() > max => {
+                let start = output.len() - max;
+
+                // 途中で切れた行は読み飛ばす
+                if output[start - 1] == b'\n' {
+                    &output[start..]
+                } else {
+                    match output[start..].iter().position(|&b| b == b'\n') {
+                        Some(pos) => &output[start + pos + 1..],
+                        None => &[],
+                    }
+                }
+            }
+            _ => output,
+        };
+
+        output.split(|&b| b == b'\n')
+    }
+
+    fn find_scores(line: &[u8], pattern: &Regex) -> Vec<f64> {
+        let line = String::from_utf8_lossy(line);
+        pattern
+            .captures_iter(&line)
+            .filter_map(|m| m.name("score").and_then(|s| s.as_str().parse().ok()))
+            .collect()
     }
 
+    /// Expands the placeholders in `s`.
+    /// `~` and environment variables are expanded first, so that seed placeholders in their values are also expanded.
     fn replace_placeholder(s: &str, seed: u64) -> String {
-        s.replace("{SEED}", &seed.to_string())
+        let s = Self::expand_env(s)
+            .replace("{SEED}", &seed.to_string())
             .replace("{SEED04}", &format!("{seed:04}"))
+            .replace("{SEED08}", &format!("{seed:08}"));
+
+        FORMATTED_SEED_PLACEHOLDER
+            .replace_all(&s, |caps: &regex::Captures| {
+                let width = caps
+                    .name("width")
+                    .map_or(0, |w| w.as_str().parse::<usize>().unwrap());
+
+                match caps.name("radix").map(|r| r.as_str()) {
+                    Some("x") => format!("{seed:0width$x}"),
+                    Some(_) => format!("{seed:0width$X}"),
+                    None => format!("{seed:0width$}"),
+                }
+            })
+            .into_owned()
     }
 }
 
@@ -304,42 +1416,87 @@ mod test {
                 args,
                 current_dir,
                 stdin,
+                stdin_text: None,
                 stdout,
                 stderr,
                 measure_time,
+                timeout_ms: None,
+                memory_limit_mb: None,
+                max_output_bytes: None,
+                env: None,
+                solver: None,
+                expected: None,
+                ignore_exit_status: false,
             }
         }
     }
 
     #[test]
     fn test_calc_relative_score() {
-        let non_zero_100 = NonZeroU64::new(100).unwrap();
-        let non_zero_200 = NonZeroU64::new(200).unwrap();
+        let score_100 = Score::new(100.0).unwrap();
+        let score_200 = Score::new(200.0).unwrap();
 
-        let test_case = TestCase::new(0, Some(NonZeroU64::new(100).unwrap()), Objective::Max);
-        assert_eq!(test_case.calc_relative_score(non_zero_100), 100.0);
-        assert_eq!(test_case.calc_relative_score(non_zero_200), 200.0);
+        let test_case = TestCase::new(0, Score::new(100.0), Objective::Max);
+        assert_eq!(test_case.calc_relative_score(score_100), 100.0);
+        assert_eq!(test_case.calc_relative_score(score_200), 200.0);
 
-        let test_case = TestCase::new(0, Some(NonZeroU64::new(100).unwrap()), Objective::Min);
-        assert_eq!(test_case.calc_relative_score(non_zero_100), 100.0);
-        assert_eq!(test_case.calc_relative_score(non_zero_200), 50.0);
+        let test_case = TestCase::new(0, Score::new(100.0), Objective::Min);
+        assert_eq!(test_case.calc_relative_score(score_100), 100.0);
+        assert_eq!(test_case.calc_relative_score(score_200), 50.0);
+    }
+
+    #[test]
+    fn test_calc_relative_score_clamp() {
+        let score_50 = Score::new(50.0).unwrap();
+        let score_200 = Score::new(200.0).unwrap();
+
+        let test_case =
+            TestCase::new(0, Score::new(100.0), Objective::Max).with_clamp_relative(true);
+        assert_eq!(test_case.calc_relative_score(score_200), 100.0);
+        assert_eq!(test_case.calc_relative_score(score_50), 50.0);
+
+        let test_case =
+            TestCase::new(0, Score::new(100.0), Objective::Min).with_clamp_relative(true);
+        assert_eq!(test_case.calc_relative_score(score_50), 100.0);
+    }
+
+    #[test]
+    fn test_calc_relative_score_log() {
+        let test_case = TestCase::new(0, Score::new(999.0), Objective::Max)
+            .with_relative_score_mode(RelativeScoreMode::Log);
+        let relative_score = test_case.calc_relative_score(Score::new(99.0).unwrap());
+        assert!((relative_score - 200.0 / 3.0).abs() < 1e-9);
+
+        let test_case = TestCase::new(0, Score::new(999.0), Objective::Min)
+            .with_relative_score_mode(RelativeScoreMode::Log);
+        let relative_score = test_case.calc_relative_score(Score::new(99.0).unwrap());
+        assert!((relative_score - 150.0).abs() < 1e-9);
     }
 
     #[test]
     fn test_is_best() {
-        let non_zero_50 = NonZeroU64::new(50);
-        let non_zero_100 = NonZeroU64::new(100);
-        let non_zero_200 = NonZeroU64::new(200);
+        let score_50 = Score::new(50.0);
+        let score_100 = Score::new(100.0);
+        let score_200 = Score::new(200.0);
 
-        let test_case = TestCase::new(0, non_zero_100, Objective::Max);
-        assert!(!test_case.is_best(non_zero_50));
-        assert!(test_case.is_best(non_zero_100));
-        assert!(test_case.is_best(non_zero_200));
+        let test_case = TestCase::new(0, score_100, Objective::Max);
+        assert!(!test_case.is_best(score_50));
+        assert!(test_case.is_best(score_100));
+        assert!(test_case.is_best(score_200));
 
-        let test_case = TestCase::new(0, non_zero_100, Objective::Min);
-        assert!(test_case.is_best(non_zero_50));
-        assert!(test_case.is_best(non_zero_100));
-        assert!(!test_case.is_best(non_zero_200));
+        let test_case = TestCase::new(0, score_100, Objective::Min);
+        assert!(test_case.is_best(score_50));
+        assert!(test_case.is_best(score_100));
+        assert!(!test_case.is_best(score_200));
+    }
+
+    #[test]
+    fn test_score_new() {
+        assert_eq!(Score::new(12345.678).map(Score::get), Some(12345.678));
+        assert_eq!(Score::new(0.0), None);
+        assert_eq!(Score::new(-1.0), None);
+        assert_eq!(Score::new(f64::INFINITY), None);
+        assert_eq!(Score::new(f64::NAN), None);
     }
 
     #[test]
@@ -350,38 +1507,457 @@ mod test {
             SingleCaseRunner::replace_placeholder("{SEED04}", 42),
             "0042"
         );
+        assert_eq!(
+            SingleCaseRunner::replace_placeholder("{SEED08}", 42),
+            "00000042"
+        );
+        assert_eq!(
+            SingleCaseRunner::replace_placeholder("in/{SEED:06}.txt", 42),
+            "in/000042.txt"
+        );
+        assert_eq!(
+            SingleCaseRunner::replace_placeholder("{SEED:02}_{SEED04}", 12345),
+            "12345_12345"
+        );
+        assert_eq!(
+            SingleCaseRunner::replace_placeholder("{SEED:x}/{SEED:08X}", 255),
+            "ff/000000FF"
+        );
+    }
+
+    #[test]
+    fn test_expand_seed_format() {
+        let mut step = TestStep::new(
+            "./gen".to_string(),
+            vec!["{CASE}".to_string()],
+            None,
+            Some("in/{CASE}.txt".to_string()),
+            None,
+            None,
+            false,
+        );
+        step.expand_seed_format("case_{SEED:05}");
+        let runner = SingleCaseRunner::new(vec![step], get_regex(), ScoreAggregation::Last);
+
+        assert_eq!(
+            runner.describe_steps(42),
+            "  [1] ./gen case_00042\n      stdin: in/case_00042.txt\n"
+        );
+    }
+
+    #[test]
+    fn test_replace_placeholder_env() {
+        std::env::set_var("PAHCER_TEST_CONTEST_DIR", "/contest/{SEED04}");
+        assert_eq!(
+            SingleCaseRunner::replace_placeholder("${PAHCER_TEST_CONTEST_DIR}/in.txt", 42),
+            "/contest/0042/in.txt"
+        );
+
+        // 未定義の環境変数や波括弧のない形式はそのまま残す
+        assert_eq!(
+            SingleCaseRunner::replace_placeholder("${PAHCER_TEST_UNDEFINED} $HOME", 42),
+            "${PAHCER_TEST_UNDEFINED} $HOME"
+        );
+
+        let home = std::env::home_dir().unwrap();
+        assert_eq!(
+            SingleCaseRunner::replace_placeholder("~/tools/gen", 42),
+            format!("{}/tools/gen", home.display())
+        );
+        assert_eq!(SingleCaseRunner::replace_placeholder("a~/b", 42), "a~/b");
+        assert_eq!(SingleCaseRunner::replace_placeholder("~foo", 42), "~foo");
+    }
+
+    #[test]
+    fn test_expand_params() {
+        let params = HashMap::from([("temp".to_string(), "0.98".to_string())]);
+        std::env::set_var("PAHCER_PARAM_pahcer_test_iter", "1000");
+
+        assert_eq!(
+            expand_params("--temp={PARAM:temp} {SEED}", &params).unwrap(),
+            "--temp=0.98 {SEED}"
+        );
+        assert_eq!(
+            expand_params("{PARAM:pahcer_test_iter}", &params).unwrap(),
+            "1000"
+        );
+        assert!(expand_params("{PARAM:pahcer_test_undefined}", &params).is_err());
+    }
+
+    #[test]
+    fn test_output_path() {
+        assert_eq!(
+            SingleCaseRunner::output_path("out/{SEED04}.txt", 42),
+            "out/0042.txt"
+        );
+        assert_eq!(SingleCaseRunner::output_path("out/", 42), "out/0042.txt");
+
+        // 既存のディレクトリの場合は末尾の区切り文字がなくてもseedから命名する
+        let dir = std::env::temp_dir();
+        assert_eq!(
+            SingleCaseRunner::output_path(&dir.to_string_lossy(), 42),
+            dir.join("0042.txt").to_string_lossy()
+        );
     }
 
     #[test]
     fn run_test_ok() {
         let steps = vec![gen_teststep("echo", Some("Score = 1234"))];
-        let runner = SingleCaseRunner::new(steps, get_regex());
+        let runner = SingleCaseRunner::new(steps, get_regex(), ScoreAggregation::Last);
+        let result = runner.run(TEST_CASE);
+        assert_eq!(result.score(), &Ok(Score::new(1234.0).unwrap()));
+    }
+
+    #[test]
+    fn run_test_float_score() {
+        let steps = vec![gen_teststep("echo", Some("Score = 12345.678"))];
+        let regex = Regex::new(r"^\s*Score\s*=\s*(?P<score>[\d.]+)\s*$").unwrap();
+        let runner = SingleCaseRunner::new(steps, regex, ScoreAggregation::Last);
         let result = runner.run(TEST_CASE);
-        assert_eq!(result.score(), &Ok(NonZeroU64::new(1234).unwrap()));
+        assert_eq!(result.score(), &Ok(Score::new(12345.678).unwrap()));
+    }
+
+    #[test]
+    fn run_test_score_aggregation() {
+        let regex = Regex::new(r"(?m)^\s*Score\s*=\s*(?P<score>\d+)\s*$").unwrap();
+        let steps = vec![
+            gen_teststep("printf", Some("Score = 10\nScore = 30\n")),
+            gen_teststep("echo", Some("Score = 20")),
+        ];
+
+        for (aggregation, expected) in [
+            (ScoreAggregation::Last, 20.0),
+            (ScoreAggregation::Sum, 60.0),
+            (ScoreAggregation::Max, 30.0),
+            (ScoreAggregation::Min, 10.0),
+        ] {
+            let runner = SingleCaseRunner::new(steps.clone(), regex.clone(), aggregation);
+            let result = runner.run(TEST_CASE);
+            assert_eq!(result.score(), &Ok(Score::new(expected).unwrap()));
+
+            let step_indices = result.step_times().iter().map(|&(i, _)| i);
+            assert_eq!(step_indices.collect::<Vec<_>>(), vec![0, 1]);
+        }
+    }
+
+    #[test]
+    fn test_extract_score() {
+        let outputs = vec![
+            (OutputStream::Stdout, b"Score = 1\nScore = 2\n".to_vec()),
+            (
+                OutputStream::Stderr,
+                b"\xff\xfe binary\nScore = 3\n\x00\x01\n".to_vec(),
+            ),
+            (OutputStream::Stdout, vec![]),
+        ];
+
+        let runner = SingleCaseRunner::new(vec![], get_regex(), ScoreAggregation::Last);
+        assert_eq!(runner.extract_score(&outputs, &get_regex()), Some(3.0));
+
+        let runner = SingleCaseRunner::new(vec![], get_regex(), ScoreAggregation::Sum);
+        assert_eq!(runner.extract_score(&outputs, &get_regex()), Some(6.0));
+
+        // 末尾から10バイトのみ走査するため、途中で切れた "Score = 1" の行は無視される
+        let runner = SingleCaseRunner::new(vec![], get_regex(), ScoreAggregation::Sum)
+            .with_max_scan_bytes(Some(10));
+        assert_eq!(runner.extract_score(&outputs[..1], &get_regex()), Some(2.0));
+
+        let runner = SingleCaseRunner::new(vec![], get_regex(), ScoreAggregation::Sum)
+            .with_max_scan_bytes(Some(5));
+        assert_eq!(runner.extract_score(&outputs[..1], &get_regex()), None);
+    }
+
+    #[test]
+    fn test_extract_score_source() {
+        let outputs = vec![
+            (OutputStream::Stdout, b"Score = 1\n".to_vec()),
+            (OutputStream::Stderr, b"Score = 2\n".to_vec()),
+            (OutputStream::Stdout, b"Score = 3\n".to_vec()),
+            (OutputStream::Stderr, vec![]),
+        ];
+
+        let runner = SingleCaseRunner::new(vec![], get_regex(), ScoreAggregation::Last);
+        assert_eq!(runner.extract_score(&outputs, &get_regex()), Some(3.0));
+
+        let runner = SingleCaseRunner::new(vec![], get_regex(), ScoreAggregation::Last)
+            .with_score_source(ScoreSource::Stderr);
+        assert_eq!(runner.extract_score(&outputs, &get_regex()), Some(2.0));
+
+        let runner = SingleCaseRunner::new(vec![], get_regex(), ScoreAggregation::Sum)
+            .with_score_source(ScoreSource::Stdout);
+        assert_eq!(runner.extract_score(&outputs, &get_regex()), Some(4.0));
+    }
+
+    #[test]
+    fn run_test_sub_scores() {
+        let step = TestStep::new(
+            "sh".to_string(),
+            vec![
+                "-c".to_string(),
+                r"printf 'Placement = 123\nPenalty = 45\nScore = 78\n'".to_string(),
+            ],
+            None,
+            None,
+            None,
+            None,
+            true,
+        );
+        let patterns = ["Placement", "Penalty", "Bonus"]
+            .iter()
+            .map(|name| {
+                let regex = Regex::new(&format!(r"(?m)^{name} = (?P<score>\d+)$")).unwrap();
+                (name.to_string(), regex)
+            })
+            .collect();
+        let runner = SingleCaseRunner::new(vec![step], get_regex(), ScoreAggregation::Last)
+            .with_sub_score_patterns(patterns);
+        let result = runner.run(TEST_CASE);
+
+        assert_eq!(result.score(), &Ok(Score::new(78.0).unwrap()));
+        assert_eq!(
+            result.sub_scores(),
+            &HashMap::from([
+                ("Placement".to_string(), 123.0),
+                ("Penalty".to_string(), 45.0)
+            ])
+        );
     }
 
     #[test]
     fn run_test_score_zero() {
         let steps = vec![gen_teststep("echo", Some("Score = 0"))];
-        let runner = SingleCaseRunner::new(steps, get_regex());
+        let runner = SingleCaseRunner::new(steps, get_regex(), ScoreAggregation::Last);
         let result = runner.run(TEST_CASE);
 
         // 0点以下はWrong Answerとして扱う
         assert!(result.score.is_err());
     }
 
+    #[test]
+    fn test_describe_steps() {
+        let step = TestStep::new(
+            "./tester".to_string(),
+            vec!["./in/{SEED04}.txt".to_string(), "a b".to_string()],
+            Some("./work".to_string()),
+            None,
+            Some("./out/{SEED04}.txt".to_string()),
+            None,
+            true,
+        );
+        let runner = SCORE_REGEX
+            .with(|regex| SingleCaseRunner::new(vec![step], regex.clone(), ScoreAggregation::Last));
+
+        let expected = r#"  [1] ./tester ./in/0042.txt "a b"
+      current_dir: ./work
+      stdout: ./out/0042.txt
+"#;
+        assert_eq!(runner.describe_steps(42), expected);
+    }
+
+    #[test]
+    fn run_test_stdin_text() {
+        let mut step = TestStep::new(
+            "sh".to_string(),
+            vec!["-c".to_string(), "read x; echo Score = $x".to_string()],
+            None,
+            None,
+            None,
+            None,
+            true,
+        );
+        step.stdin_text = Some("{SEED}\n".to_string());
+        assert!(step.validate().is_ok());
+
+        let runner = SingleCaseRunner::new(vec![step.clone()], get_regex(), ScoreAggregation::Last);
+        let result = runner.run(TEST_CASE);
+        assert_eq!(result.score(), &Ok(Score::new(42.0).unwrap()));
+
+        step.stdin = Some("input.txt".to_string());
+        assert!(step.validate().is_err());
+    }
+
+    #[test]
+    fn run_test_max_output_bytes() {
+        let mut step = TestStep::new(
+            "sh".to_string(),
+            vec!["-c".to_string(), "yes | head -c 100000".to_string()],
+            None,
+            None,
+            None,
+            None,
+            true,
+        );
+        step.max_output_bytes = Some(100);
+
+        let runner = SingleCaseRunner::new(vec![step.clone()], get_regex(), ScoreAggregation::Last);
+        let result = runner.run(TEST_CASE);
+        assert_eq!(result.score(), &Err(CaseError::OutputTooLarge));
+        assert!(result.execution_time() > Duration::ZERO);
+
+        step.max_output_bytes = Some(200000);
+        let runner = SingleCaseRunner::new(vec![step], get_regex(), ScoreAggregation::Last);
+        let result = runner.run(TEST_CASE);
+        assert_ne!(result.score(), &Err(CaseError::OutputTooLarge));
+    }
+
+    #[test]
+    fn run_test_expected() {
+        let dir = std::env::temp_dir().join(format!("pahcer_expected_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("0042.txt"), "1 2\r\n3\n\n").unwrap();
+
+        let mut step = gen_teststep("printf", Some("1 2  \n3\n"));
+        step.expected = Some(format!("{}/{{SEED04}}.txt", dir.display()));
+        let runner = SingleCaseRunner::new(vec![step.clone()], get_regex(), ScoreAggregation::Last);
+        let result = runner.run(TEST_CASE);
+        assert!(result.score().is_ok());
+        assert_eq!(result.scored(), None);
+        assert_eq!(result.score_or_zero(), 0.0);
+        assert!(!result.has_score());
+
+        step.args = vec!["1 2\n4\n".to_string()];
+        let runner = SingleCaseRunner::new(vec![step], get_regex(), ScoreAggregation::Last);
+        let result = runner.run(TEST_CASE);
+        let error = result.score().as_ref().unwrap_err();
+        assert_eq!(error.kind(), CaseErrorKind::WrongAnswer);
+        assert!(error.to_string().contains("-3\n+4"));
+        assert!(result.execution_time() > Duration::ZERO);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn run_test_wall_time() {
+        let mut measured = gen_teststep("echo", Some("Score = 1"));
+        measured.measure_time = true;
+        let mut unmeasured = gen_teststep("sleep", Some("0.2"));
+        unmeasured.measure_time = false;
+        let runner = SingleCaseRunner::new(
+            vec![unmeasured, measured],
+            get_regex(),
+            ScoreAggregation::Last,
+        );
+        let result = runner.run(TEST_CASE);
+        assert!(result.execution_time() < Duration::from_millis(200));
+        assert!(result.wall_time() >= Duration::from_millis(200));
+    }
+
     #[test]
     fn run_test_fail() {
         let steps = vec![gen_teststep("false", None)];
-        let runner = SingleCaseRunner::new(steps, get_regex());
+        let runner = SingleCaseRunner::new(steps, get_regex(), ScoreAggregation::Last);
         let result = runner.run(TEST_CASE);
         assert!(result.score.is_err());
     }
 
+    #[test]
+    fn run_test_fail_with_stderr() {
+        let mut step = gen_teststep(
+            "sh",
+            Some("for i in $(seq 1 30); do echo line$i >&2; done; exit 3"),
+        );
+        step.args.insert(0, "-c".to_string());
+        let runner = SingleCaseRunner::new(vec![step], get_regex(), ScoreAggregation::Last);
+        let result = runner.run(TEST_CASE);
+        let message = result.score().as_ref().unwrap_err().to_string();
+        assert!(message.contains("exit status: 3"));
+        assert!(message.contains("(10 lines omitted)\nline11\n"));
+        assert!(message.ends_with("line30"));
+    }
+
+    #[test]
+    fn run_test_ignore_exit_status() {
+        let mut step = gen_teststep("sh", Some("echo 'Score = 5'; exit 1"));
+        step.args.insert(0, "-c".to_string());
+
+        let runner = SingleCaseRunner::new(vec![step.clone()], get_regex(), ScoreAggregation::Last);
+        let result = runner.run(TEST_CASE);
+        assert_eq!(
+            result.score().as_ref().unwrap_err().kind(),
+            CaseErrorKind::RuntimeError
+        );
+
+        step.ignore_exit_status = true;
+        let runner = SingleCaseRunner::new(vec![step.clone()], get_regex(), ScoreAggregation::Last);
+        let result = runner.run(TEST_CASE);
+        assert_eq!(result.score(), &Ok(Score::new(5.0).unwrap()));
+
+        // スコアが出力されない場合はWAとなる
+        step.args[1] = "exit 1".to_string();
+        let runner = SingleCaseRunner::new(vec![step], get_regex(), ScoreAggregation::Last);
+        let result = runner.run(TEST_CASE);
+        assert_eq!(result.score(), &Err(CaseError::WrongAnswer));
+    }
+
+    #[test]
+    fn test_tail_lines() {
+        assert_eq!(SingleCaseRunner::tail_lines(b""), "");
+        assert_eq!(SingleCaseRunner::tail_lines(b"a\nb\n"), "a\nb");
+
+        let long_line = "x".repeat(STDERR_TAIL_CHARS + 10);
+        let tail = SingleCaseRunner::tail_lines(long_line.as_bytes());
+        assert_eq!(tail.len(), STDERR_TAIL_CHARS + 3);
+        assert!(tail.starts_with("...x"));
+    }
+
+    #[test]
+    fn run_test_timeout() {
+        let mut step = gen_teststep("sleep", Some("10"));
+        step.timeout_ms = Some(100);
+        let runner = SingleCaseRunner::new(vec![step], get_regex(), ScoreAggregation::Last);
+        let result = runner.run(TEST_CASE);
+        assert_eq!(result.score(), &Err(CaseError::Tle));
+        assert!(result.execution_time() >= Duration::from_millis(100));
+        assert!(result.execution_time() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn run_test_timeout_writes_output() {
+        let dir = std::env::temp_dir().join(format!("pahcer_tle_output_{}", std::process::id()));
+        let mut step = gen_teststep("sh", None);
+        step.args = vec!["-c".to_string(), "echo partial; exec sleep 10".to_string()];
+        step.timeout_ms = Some(200);
+        step.stdout = Some(format!("{}/", dir.display()));
+        let runner = SingleCaseRunner::new(vec![step], get_regex(), ScoreAggregation::Last);
+        let result = runner.run(TEST_CASE);
+        let output = std::fs::read_to_string(dir.join(format!("{:04}.txt", TEST_CASE.seed())));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(result.score(), &Err(CaseError::Tle));
+        assert_eq!(output.unwrap(), "partial\n");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn run_test_memory_limit() {
+        let mut step = gen_teststep(
+            "sh",
+            Some("head -c 200000000 /dev/zero | tail -c 200000000 > /dev/null"),
+        );
+        step.args.insert(0, "-c".to_string());
+        step.memory_limit_mb = Some(64);
+        let runner = SingleCaseRunner::new(vec![step], get_regex(), ScoreAggregation::Last);
+        let result = runner.run(TEST_CASE);
+        assert_eq!(result.score(), &Err(CaseError::Mle));
+    }
+
+    #[test]
+    fn run_test_env() {
+        let mut step = gen_teststep("sh", Some("echo Score = $PAHCER_TEST_SCORE"));
+        step.args.insert(0, "-c".to_string());
+        step.env = Some(HashMap::from([(
+            "PAHCER_TEST_SCORE".to_string(),
+            "{SEED}".to_string(),
+        )]));
+        let runner = SingleCaseRunner::new(vec![step], get_regex(), ScoreAggregation::Last);
+        let result = runner.run(TEST_CASE);
+        assert_eq!(result.score(), &Ok(Score::new(42.0).unwrap()));
+    }
+
     #[test]
     fn run_test_invalid_output() {
         let steps = vec![gen_teststep("echo", Some("invalid_output"))];
-        let runner = SingleCaseRunner::new(steps, get_regex());
+        let runner = SingleCaseRunner::new(steps, get_regex(), ScoreAggregation::Last);
         let result = runner.run(TEST_CASE);
         assert!(result.score.is_err());
     }
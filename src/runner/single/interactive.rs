@@ -0,0 +1,288 @@
+use super::{
+    memory, CapturedOutput, MemoryLimitExceeded, OutputStream, OutputTooLarge, SingleCaseRunner,
+    TestStep, TimeLimitExceeded,
+};
+use crate::util::{deserialize_program, format_command};
+use anyhow::{ensure, Context as _, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    path::Path,
+    process::{Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+/// The solver of an interactive problem.
+/// Its stdin and stdout are connected to the stdout and stdin of the tester step.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct SolverCommand {
+    #[serde(deserialize_with = "deserialize_program")]
+    program: String,
+    #[serde(default)]
+    args: Vec<String>,
+    current_dir: Option<String>,
+    stderr: Option<String>,
+    env: Option<HashMap<String, String>>,
+}
+
+impl SolverCommand {
+    /// Replaces the `{CASE}` placeholders with `seed_format`.
+    pub(super) fn expand_seed_format(&mut self, seed_format: &str) {
+        let expand = |s: &mut String| *s = s.replace(super::CASE_PLACEHOLDER, seed_format);
+
+        expand(&mut self.program);
+        self.args.iter_mut().for_each(expand);
+
+        for s in [&mut self.current_dir, &mut self.stderr]
+            .into_iter()
+            .flatten()
+        {
+            expand(s);
+        }
+
+        if let Some(env) = &mut self.env {
+            env.values_mut().for_each(expand);
+        }
+    }
+
+    /// Expands the `{PARAM:name}` placeholders in the arguments.
+    pub(super) fn expand_params(&mut self, params: &HashMap<String, String>) -> Result<()> {
+        for arg in self.args.iter_mut() {
+            *arg = super::expand_params(arg, params)?;
+        }
+
+        Ok(())
+    }
+
+    /// Describes the solver command with the placeholders expanded, for `--dry-run`.
+    pub(super) fn describe(&self, seed: u64) -> String {
+        let args = self
+            .args
+            .iter()
+            .map(|s| SingleCaseRunner::replace_placeholder(s, seed))
+            .collect::<Vec<_>>();
+        let program = SingleCaseRunner::replace_placeholder(&self.program, seed);
+        let mut description = format!("      solver: {}\n", format_command(&program, &args));
+        if let Some(dir) = &self.current_dir {
+            let dir = SingleCaseRunner::replace_placeholder(dir, seed);
+            description += &format!("        current_dir: {dir}\n");
+        }
+
+        if let Some(stderr) = &self.stderr {
+            let stderr = SingleCaseRunner::output_path(stderr, seed);
+            description += &format!("        stderr: {stderr}\n");
+        }
+
+        description
+    }
+}
+
+impl SingleCaseRunner {
+    /// Runs the tester (`step`) and the solver concurrently, connecting them with pipes.
+    /// The stderr of both processes is captured to extract the score.
+    pub(super) fn run_interactive(
+        step: &TestStep,
+        solver_command: &SolverCommand,
+        seed: u64,
+        outputs: &mut Vec<CapturedOutput>,
+    ) -> Result<Duration> {
+        ensure!(
+            step.stdin.is_none() && step.stdin_text.is_none() && step.stdout.is_none(),
+            "stdin, stdin_text and stdout of the tester cannot be specified when solver is specified"
+        );
+
+        let (solver_stdin, tester_stdout) = std::io::pipe()?;
+        let (tester_stdin, solver_stdout) = std::io::pipe()?;
+
+        let mut tester_cmd = Self::build_cmd(step, seed)?;
+        tester_cmd.stdin(tester_stdin).stdout(tester_stdout);
+
+        let mut solver_cmd = Self::build_solver_cmd(solver_command, seed);
+        solver_cmd.stdin(solver_stdin).stdout(solver_stdout);
+
+        if let Some(limit) = step.memory_limit_bytes() {
+            memory::prepare(&mut solver_cmd, limit);
+        }
+
+        let since = Instant::now();
+        let mut tester = tester_cmd
+            .spawn()
+            .with_context(|| format!("Failed to run. command: {tester_cmd:?}"))?;
+        let mut solver = match solver_cmd.spawn() {
+            Ok(solver) => solver,
+            Err(e) => {
+                let _ = tester.kill();
+                let _ = tester.wait();
+                return Err(e).with_context(|| format!("Failed to run. command: {solver_cmd:?}"));
+            }
+        };
+
+        // 親プロセスがパイプの端を保持していると、片方が終了してもEOFが届かずデッドロックするため閉じる
+        let tester_cmd_debug = format!("{tester_cmd:?}");
+        let solver_cmd_debug = format!("{solver_cmd:?}");
+        drop(tester_cmd);
+        drop(solver_cmd);
+
+        if let Some(limit) = step.memory_limit_bytes() {
+            if let Err(e) = memory::apply(&solver, limit) {
+                let _ = solver.kill();
+                let _ = tester.kill();
+                return Err(e).with_context(|| {
+                    format!("Failed to set the memory limit. command: {solver_cmd_debug}")
+                });
+            }
+        }
+
+        let output_exceeded = Arc::new(AtomicBool::new(false));
+        let limit = step.max_output_bytes;
+        let tester_stderr_reader =
+            Self::spawn_reader(tester.stderr.take(), limit, &output_exceeded);
+        let solver_stderr_reader =
+            Self::spawn_reader(solver.stderr.take(), limit, &output_exceeded);
+
+        // どちらかが早期に終了しても、もう一方にはEOFまたはEPIPEが届くため待機し続けることはない
+        let timeout = step.timeout_ms.map(Duration::from_millis);
+        let output_exceeded = limit.is_some().then_some(output_exceeded.as_ref());
+        let solver_status = Self::wait_with_timeout(&mut solver, since, timeout, output_exceeded)
+            .with_context(|| {
+            format!("Failed to wait for the process. command: {solver_cmd_debug}")
+        })?;
+        let tester_status = match solver_status {
+            Some(_) => Self::wait_with_timeout(&mut tester, since, timeout, output_exceeded)
+                .with_context(|| {
+                    format!("Failed to wait for the process. command: {tester_cmd_debug}")
+                })?,
+            None => {
+                let _ = tester.kill();
+                let _ = tester.wait();
+                None
+            }
+        };
+        let execution_time = since.elapsed();
+
+        let (Some(solver_status), Some(tester_status)) = (solver_status, tester_status) else {
+            return Err(TimeLimitExceeded(execution_time).into());
+        };
+
+        let tester_stderr = Self::join_reader(tester_stderr_reader)?;
+        let solver_stderr = Self::join_reader(solver_stderr_reader)?;
+
+        if let Some(path) = &step.stderr {
+            let path = Self::output_path(path, seed);
+            Self::write_output(Path::new(&path), &tester_stderr)
+                .with_context(|| format!("Failed to write stderr to {path}"))?;
+        }
+
+        if let Some(path) = &solver_command.stderr {
+            let path = Self::output_path(path, seed);
+            Self::write_output(Path::new(&path), &solver_stderr)
+                .with_context(|| format!("Failed to write stderr to {path}"))?;
+        }
+
+        if output_exceeded.is_some_and(|e| e.load(Ordering::SeqCst)) {
+            return Err(OutputTooLarge(execution_time).into());
+        }
+
+        let is_mle = step.memory_limit_mb.is_some()
+            && memory::is_memory_limit_exceeded(solver_status, &solver_stderr);
+
+        let solver_result = Self::check_status(solver_status, &solver_cmd_debug, &solver_stderr);
+        let tester_result = Self::check_status(tester_status, &tester_cmd_debug, &tester_stderr);
+
+        outputs.push((OutputStream::Stderr, tester_stderr));
+        outputs.push((OutputStream::Stderr, solver_stderr));
+
+        if is_mle {
+            return Err(MemoryLimitExceeded(execution_time).into());
+        }
+
+        solver_result?;
+
+        // テスタは不正な出力に対して非0で終了しつつスコアを出力することがあるため、指定された場合は無視する
+        if !step.ignore_exit_status {
+            tester_result?;
+        }
+
+        Ok(execution_time)
+    }
+
+    fn build_solver_cmd(solver: &SolverCommand, seed: u64) -> Command {
+        let mut cmd = Command::new(Self::replace_placeholder(&solver.program, seed));
+        cmd.args(
+            solver
+                .args
+                .iter()
+                .map(|s| Self::replace_placeholder(s, seed)),
+        );
+
+        if let Some(dir) = &solver.current_dir {
+            cmd.current_dir(Self::replace_placeholder(dir, seed));
+        }
+
+        if let Some(env) = &solver.env {
+            cmd.envs(
+                env.iter()
+                    .map(|(key, value)| (key, Self::replace_placeholder(value, seed))),
+            );
+        }
+
+        cmd.stderr(Stdio::piped());
+        cmd
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::runner::single::{CaseError, Objective, ScoreAggregation, TestCase};
+    use regex::Regex;
+
+    fn gen_interactive_step(tester_script: &str, solver_script: &str) -> TestStep {
+        let mut step = TestStep::new(
+            "sh".to_string(),
+            vec!["-c".to_string(), tester_script.to_string()],
+            None,
+            None,
+            None,
+            None,
+            true,
+        );
+        step.timeout_ms = Some(5000);
+        step.solver = Some(SolverCommand {
+            program: "sh".to_string(),
+            args: vec!["-c".to_string(), solver_script.to_string()],
+            current_dir: None,
+            stderr: None,
+            env: None,
+        });
+        step
+    }
+
+    fn gen_runner(step: TestStep) -> SingleCaseRunner {
+        let regex = Regex::new(r"^\s*Score\s*=\s*(?P<score>\d+)\s*$").unwrap();
+        SingleCaseRunner::new(vec![step], regex, ScoreAggregation::Last)
+    }
+
+    #[test]
+    fn run_interactive() {
+        let step = gen_interactive_step(
+            r#"echo {SEED}; read x; echo "Score = $x" >&2"#,
+            "read n; echo $((n * 2))",
+        );
+        let result = gen_runner(step).run(TestCase::new(21, None, Objective::Max));
+        assert_eq!(result.score().as_ref().map(|s| s.get()), Ok(42.0));
+    }
+
+    #[test]
+    fn run_interactive_solver_exits_early() {
+        // ソルバが入力を読まずに終了してもデッドロックしない
+        let step = gen_interactive_step(r#"echo 1; read x; echo "Score = $x" >&2"#, "exit 1");
+        let result = gen_runner(step).run(TestCase::new(0, None, Objective::Max));
+        assert!(result.score().is_err());
+        assert_ne!(result.score(), &Err(CaseError::Tle));
+    }
+}
@@ -0,0 +1,135 @@
+use std::process::{Child, Command, ExitStatus};
+
+/// Messages which runtimes typically print when they run out of memory
+const OUT_OF_MEMORY_PATTERNS: [&str; 6] = [
+    "out of memory",
+    "memory exhausted",
+    "memory allocation",
+    "MemoryError",
+    "bad_alloc",
+    "OutOfMemoryError",
+];
+
+/// Sets the memory limit to the process which is going to be spawned.
+/// On Unix, `RLIMIT_AS` is set just before `exec` (note that macOS does not enforce it).
+#[cfg(unix)]
+pub(super) fn prepare(cmd: &mut Command, limit_bytes: u64) {
+    use std::os::unix::process::CommandExt as _;
+
+    let limit = libc::rlimit {
+        rlim_cur: limit_bytes as libc::rlim_t,
+        rlim_max: limit_bytes as libc::rlim_t,
+    };
+
+    // SAFETY: setrlimitはasync-signal-safeであり、クロージャ内でメモリ確保も行わない
+    unsafe {
+        cmd.pre_exec(move || {
+            if libc::setrlimit(libc::RLIMIT_AS, &limit) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+
+            Ok(())
+        });
+    }
+}
+
+#[cfg(not(unix))]
+pub(super) fn prepare(_cmd: &mut Command, _limit_bytes: u64) {}
+
+/// Applies the memory limit to the spawned process.
+/// On Windows, the process is assigned to a Job Object with a per-process memory limit.
+/// This is best-effort since the process starts running before it is assigned to the job.
+#[cfg(windows)]
+pub(super) fn apply(child: &Child, limit_bytes: u64) -> std::io::Result<()> {
+    use std::os::windows::io::AsRawHandle as _;
+    use windows_sys::Win32::{
+        Foundation::CloseHandle,
+        System::JobObjects::{
+            AssignProcessToJobObject, CreateJobObjectW, JobObjectExtendedLimitInformation,
+            SetInformationJobObject, JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+            JOB_OBJECT_LIMIT_PROCESS_MEMORY,
+        },
+    };
+
+    // SAFETY: Win32 APIに渡すポインタは全て呼び出しの間有効である
+    // Job Objectはハンドルを閉じ、プロセスが終了した後に破棄される
+    unsafe {
+        let job = CreateJobObjectW(std::ptr::null(), std::ptr::null());
+
+        if job.is_null() {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        let mut info: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = std::mem::zeroed();
+        info.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_PROCESS_MEMORY;
+        info.ProcessMemoryLimit = limit_bytes as usize;
+
+        let result = if SetInformationJobObject(
+            job,
+            JobObjectExtendedLimitInformation,
+            &info as *const _ as *const _,
+            std::mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+        ) == 0
+            || AssignProcessToJobObject(job, child.as_raw_handle()) == 0
+        {
+            Err(std::io::Error::last_os_error())
+        } else {
+            Ok(())
+        };
+
+        CloseHandle(job);
+        result
+    }
+}
+
+#[cfg(not(windows))]
+pub(super) fn apply(_child: &Child, _limit_bytes: u64) -> std::io::Result<()> {
+    Ok(())
+}
+
+/// Guesses whether the failed process ran out of memory from its exit status and stderr.
+/// Signals such as `SIGABRT` alone are not enough since assertions and panics also raise them.
+pub(super) fn is_memory_limit_exceeded(status: ExitStatus, stderr: &[u8]) -> bool {
+    if status.success() {
+        return false;
+    }
+
+    let stderr = String::from_utf8_lossy(stderr);
+    OUT_OF_MEMORY_PATTERNS.iter().any(|p| stderr.contains(p)) || is_no_memory_status(status)
+}
+
+/// `STATUS_NO_MEMORY` is reported only when the allocation itself failed.
+#[cfg(windows)]
+fn is_no_memory_status(status: ExitStatus) -> bool {
+    matches!(status.code(), Some(code) if code as u32 == 0xC000_0017)
+}
+
+#[cfg(not(windows))]
+fn is_no_memory_status(_status: ExitStatus) -> bool {
+    false
+}
+
+#[cfg(test)]
+#[cfg(unix)]
+mod test {
+    use super::*;
+    use std::os::unix::process::ExitStatusExt as _;
+
+    #[test]
+    fn test_is_memory_limit_exceeded() {
+        let aborted = ExitStatus::from_raw(libc::SIGABRT);
+        let failed = ExitStatus::from_raw(1 << 8);
+
+        assert!(is_memory_limit_exceeded(
+            aborted,
+            b"memory allocation of 1024 bytes failed"
+        ));
+        assert!(is_memory_limit_exceeded(failed, b"std::bad_alloc"));
+        // アサーション失敗などによるabortはMLEとみなさない
+        assert!(!is_memory_limit_exceeded(aborted, b"assertion failed"));
+        assert!(!is_memory_limit_exceeded(
+            ExitStatus::from_raw(0),
+            b"out of memory"
+        ));
+    }
+}
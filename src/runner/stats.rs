@@ -0,0 +1,220 @@
+use super::io::{self, AllResultJson};
+use super::multi::Distribution;
+use crate::settings::{Git, Settings};
+use crate::util::NumberFormat;
+use anyhow::Result;
+use colored::Colorize as _;
+use std::num::NonZero;
+use tabled::{
+    settings::{object::Columns, Alignment, Style},
+    Table, Tabled,
+};
+
+#[derive(Tabled)]
+struct CaseTableRow {
+    #[tabled(rename = "Seed")]
+    seed: String,
+    #[tabled(rename = "Score")]
+    score: String,
+    #[tabled(rename = "Relative Score")]
+    relative_score: String,
+    #[tabled(rename = "Exec. Time")]
+    execution_time: String,
+    #[tabled(rename = "Error")]
+    error_message: String,
+}
+
+/// タグ名または実行日時で指定された1回分のテスト結果の詳細を表示する関数
+pub(super) fn print_run_stats(settings: &Settings, run: &str) -> Result<()> {
+    let result = io::find_result_json(&settings.test.out_dir, run, &settings.git)?;
+    let format = settings.display.number_format();
+
+    let mut cases = result.cases.iter().collect::<Vec<_>>();
+    cases.sort_unstable_by_key(|c| c.seed);
+
+    let table_rows = cases.iter().map(|c| {
+        let error_message = if c.error_message.is_empty() {
+            String::new()
+        } else {
+            c.error_message.yellow().to_string()
+        };
+
+        CaseTableRow {
+            seed: format!("{:04}", c.seed),
+            score: format.score(c.score),
+            relative_score: format!("{:.3}", c.relative_score),
+            execution_time: format!("{:.0} ms", c.execution_time * 1e3),
+            error_message,
+        }
+    });
+
+    let mut table = Table::new(table_rows);
+    table.with(Style::markdown());
+    table.modify(Columns::new(1..=3), Alignment::right());
+    println!("{table}");
+    println!();
+
+    for line in summarize(&result, &settings.git, format) {
+        println!("{line}");
+    }
+
+    Ok(())
+}
+
+/// 実行結果の集計値を表示用の行に変換する
+fn summarize(result: &AllResultJson, git_settings: &Git, format: NumberFormat) -> Vec<String> {
+    let nonzero2 = NonZero::new(2).unwrap();
+    let case_count = result.scored_case_count().max(1) as f64;
+    let mut lines = vec![];
+
+    lines.push(format!(
+        "Time                   : {}",
+        result.start_time.format("%Y/%m/%d %H:%M:%S")
+    ));
+    lines.push(format!(
+        "Tag                    : {}",
+        result
+            .tag_name
+            .as_deref()
+            .map_or("-", |tag_name| git_settings.strip_tag_prefix(tag_name))
+    ));
+
+    if !result.comment.is_empty() {
+        lines.push(format!("Comment                : {}", result.comment));
+    }
+
+    // 過去のバージョンで出力されたファイルには実行環境の情報が存在しない
+    if let Some(hostname) = &result.hostname {
+        lines.push(format!("Host                   : {hostname}"));
+    }
+
+    if let Some(cpu_model) = &result.cpu_model {
+        lines.push(format!("CPU                    : {cpu_model}"));
+    }
+
+    if let Some(threads) = result.threads {
+        lines.push(format!("Threads                : {threads}"));
+    }
+
+    if let Some(version) = &result.pahcer_version {
+        lines.push(format!("Pahcer Version         : {version}"));
+    }
+
+    if let Some(version) = &result.config_version {
+        lines.push(format!("Config Version         : {version}"));
+    }
+
+    lines.push(format!(
+        "Average Score          : {}",
+        format.average_score(result.total_score / case_count)
+    ));
+    lines.push(format!(
+        "Average Score (log10)  : {:.5}",
+        result.total_score_log10 / case_count
+    ));
+    lines.push(format!(
+        "Average Relative Score : {:.3}",
+        result.total_relative_score / case_count
+    ));
+    lines.push(format!(
+        "Accepted               : {} / {}",
+        result.case_count - result.wa_seeds.len(),
+        result.case_count
+    ));
+    lines.push(format!(
+        "Max Execution Time     : {:.0} ms",
+        result.max_execution_time * 1e3
+    ));
+
+    let scores = result
+        .cases
+        .iter()
+        .filter(|c| !c.unscored)
+        .map(|c| c.score)
+        .collect();
+
+    // 失敗したケースはスコア0として保存されているため、両方の分布で0として集計される
+    let failed_count = result
+        .cases
+        .iter()
+        .filter(|c| !c.error_message.is_empty())
+        .count();
+    let failed_note = Distribution::failed_note(failed_count);
+
+    if let Some(dist) = Distribution::new(scores) {
+        let format = |x| format.float(x, nonzero2);
+        lines.push(format!(
+            "Score Distribution     : {}{}",
+            dist.format(format),
+            failed_note
+        ));
+    }
+
+    let relative_scores = result
+        .cases
+        .iter()
+        .filter(|c| !c.unscored)
+        .map(|c| c.relative_score)
+        .collect();
+
+    if let Some(dist) = Distribution::new(relative_scores) {
+        let format = |x| format!("{x:.3}");
+        lines.push(format!(
+            "Relative Distribution  : {}{}",
+            dist.format(format),
+            failed_note
+        ));
+    }
+
+    let execution_times = result
+        .cases
+        .iter()
+        .map(|c| c.execution_time * 1e3)
+        .collect();
+
+    if let Some(dist) = Distribution::new(execution_times) {
+        let format = |x| format!("{x:.0}");
+        lines.push(format!("Time Distribution (ms) : {}", dist.format(format)));
+    }
+
+    let mut wa_seeds = result.wa_seeds.clone();
+    wa_seeds.sort_unstable();
+    let wa_seeds = if wa_seeds.is_empty() {
+        "-".to_string()
+    } else {
+        wa_seeds
+            .iter()
+            .map(|s| format!("{s:04}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    lines.push(format!("WA Seeds               : {wa_seeds}"));
+
+    lines
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_summarize() {
+        let mut result = AllResultJson::from_scores(&[(0, 0.0), (1, 300.0), (2, 0.0)]);
+        result.tag_name = Some("pahcer/foo".to_string());
+        result.hostname = Some("localhost".to_string());
+        result.threads = Some(4);
+        result.wa_seeds = vec![2, 0];
+        result.cases[0].error_message = "TLE".to_string();
+        result.cases[2].error_message = "Wrong Answer".to_string();
+
+        let lines = summarize(&result, &Git::default(), NumberFormat::default());
+        assert!(lines.contains(&"Tag                    : foo".to_string()));
+        assert!(lines.contains(&"Average Score          : 100.00".to_string()));
+        assert!(lines.contains(&"Accepted               : 1 / 3".to_string()));
+        assert!(lines.contains(&"WA Seeds               : 0000, 0002".to_string()));
+        assert!(!lines.iter().any(|l| l.starts_with("Comment")));
+        assert!(lines.contains(&"Host                   : localhost".to_string()));
+        assert!(lines.contains(&"Threads                : 4".to_string()));
+        assert!(!lines.iter().any(|l| l.starts_with("CPU")));
+    }
+}
@@ -0,0 +1,120 @@
+use super::{run_tests, Interrupted, RunArgs};
+use anyhow::{Context as _, Result};
+use colored::Colorize as _;
+use notify::{EventKind, RecursiveMode, Watcher as _};
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    time::Duration,
+};
+
+/// ファイルの変更を監視し、変更される度にテストを実行する
+pub(super) fn watch(patterns: &[String], debounce: Duration, run_args: &RunArgs) -> Result<()> {
+    let patterns = patterns
+        .iter()
+        .map(|p| glob::Pattern::new(p).with_context(|| format!("Invalid glob pattern: {p}")))
+        .collect::<Result<Vec<_>>>()?;
+    let root = std::env::current_dir()?;
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx).context("Failed to start watching files.")?;
+    watcher
+        .watch(&root, RecursiveMode::Recursive)
+        .with_context(|| format!("Failed to watch {}.", root.display()))?;
+
+    loop {
+        // 前回の実行結果からの変化は、各実行のサマリに `Vs Previous Run` として表示される
+        if let Err(e) = run_tests(run_args.clone(), &mut None) {
+            if e.is::<Interrupted>() {
+                return Err(e);
+            }
+
+            eprintln!("{}", format!("Error: {e:?}").yellow().bold());
+        }
+
+        // 実行中にコンパイル結果などが書き込まれた分の通知は捨てる
+        while rx.try_recv().is_ok() {}
+
+        println!("Watching for changes... (Ctrl-C to quit)");
+        wait_for_changes(&rx, &root, &patterns, debounce)?;
+        println!();
+    }
+}
+
+/// 監視対象のファイルが変更されるまで待ち、変更が落ち着くまで `debounce` だけ待機する
+fn wait_for_changes(
+    rx: &Receiver<notify::Result<notify::Event>>,
+    root: &Path,
+    patterns: &[glob::Pattern],
+    debounce: Duration,
+) -> Result<()> {
+    let mut changed = false;
+
+    // 待機中のCtrl-Cはハンドラが即座に終了させるため、変更が検知されるまではブロックしてよい
+    loop {
+        let event = if changed {
+            rx.recv_timeout(debounce)
+        } else {
+            rx.recv().map_err(|_| RecvTimeoutError::Disconnected)
+        };
+
+        match event {
+            Ok(event) => {
+                let event = event.context("Failed to watch files.")?;
+                changed |= is_target_event(&event, root, patterns);
+            }
+            Err(RecvTimeoutError::Timeout) => return Ok(()),
+            Err(RecvTimeoutError::Disconnected) => {
+                anyhow::bail!("The file watcher stopped unexpectedly.")
+            }
+        }
+    }
+}
+
+/// 監視対象のファイルの変更を通知するイベントかどうか
+fn is_target_event(event: &notify::Event, root: &Path, patterns: &[glob::Pattern]) -> bool {
+    // コンパイル時などの読み込みで再実行されないよう、アクセスのみのイベントは無視する
+    if matches!(event.kind, EventKind::Access(_)) {
+        return false;
+    }
+
+    event
+        .paths
+        .iter()
+        .any(|path| is_target_path(path, root, patterns))
+}
+
+fn is_target_path(path: &Path, root: &Path, patterns: &[glob::Pattern]) -> bool {
+    let relative_path = path
+        .strip_prefix(root)
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| path.to_path_buf());
+
+    patterns.iter().any(|p| p.matches_path(&relative_path))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_is_target_path() {
+        let root = Path::new("/work");
+        let patterns = [
+            glob::Pattern::new("src/**/*.rs").unwrap(),
+            glob::Pattern::new("main.cpp").unwrap(),
+        ];
+
+        assert!(is_target_path(
+            Path::new("/work/src/bin/a.rs"),
+            root,
+            &patterns
+        ));
+        assert!(is_target_path(Path::new("/work/main.cpp"), root, &patterns));
+        assert!(!is_target_path(
+            Path::new("/work/pahcer/summary.md"),
+            root,
+            &patterns
+        ));
+    }
+}
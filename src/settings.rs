@@ -1,9 +1,12 @@
 use crate::runner::{
     compilie::CompileStep,
-    single::{Objective, TestStep},
+    single::{Objective, RelativeScoreMode, ScoreAggregation, ScoreSource, TestStep},
 };
-use anyhow::{Context, Result};
+use crate::util::{NumberFormat, DEFAULT_SCORE_DECIMALS};
+use anyhow::{ensure, Context, Result};
 use clap::{Args, ValueEnum};
+use colored::Colorize as _;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::{
     ffi::OsStr,
@@ -13,9 +16,13 @@ use std::{
 };
 
 pub(crate) const SETTING_FILE_PATH: &str = "pahcer_config.toml";
+/// タグ名のプレフィックスのデフォルト値
+const DEFAULT_TAG_PREFIX: &str = "pahcer/";
+/// `{CASE}` プレースホルダーの展開形式のデフォルト値
+const DEFAULT_SEED_FORMAT: &str = "{SEED04}";
 
 #[derive(Debug, Clone, Args)]
-pub(crate) struct InitArgs {
+pub struct InitArgs {
     /// Name of the problem
     #[clap(short = 'p', long = "problem")]
     problem_name: String,
@@ -39,13 +46,81 @@ enum Lang {
     Cpp,
     Python,
     Go,
+    Java,
+    Kotlin,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub(crate) struct Settings {
+pub struct Settings {
     pub(crate) general: General,
     pub(crate) problem: Problem,
     pub(crate) test: Test,
+    #[serde(default)]
+    pub(crate) git: Git,
+    #[serde(default)]
+    pub(crate) display: DisplaySettings,
+}
+
+impl Settings {
+    /// 設定値の整合性をチェックする
+    /// 実行に支障のない問題は警告を表示するに留める
+    pub(crate) fn validate(&self) -> Result<()> {
+        ensure!(
+            self.test.start_seed < self.test.end_seed,
+            "test.start_seed ({}) must be less than test.end_seed ({}).",
+            self.test.start_seed,
+            self.test.end_seed
+        );
+
+        self.problem.score_regex.validate()?;
+
+        ensure!(
+            !self.test.test_steps.is_empty(),
+            "test.test_steps must contain at least one step."
+        );
+
+        for (i, step) in self.test.test_steps.iter().enumerate() {
+            step.validate()
+                .with_context(|| format!("test.test_steps[{i}] is invalid."))?;
+        }
+
+        // seedを含まない形式では全てのケースでファイル名などが衝突してしまう
+        ensure!(
+            self.test.seed_format().contains("{SEED"),
+            "test.seed_format ({}) must contain a seed placeholder such as {{SEED}}.",
+            self.test.seed_format()
+        );
+
+        ensure!(
+            self.test.summary_max_rows != Some(0),
+            "test.summary_max_rows must be at least 1."
+        );
+
+        ensure!(
+            !self.git.tag_prefix().is_empty(),
+            "git.tag_prefix must not be empty."
+        );
+
+        let colors = &self.display.relative_score_colors;
+        ensure!(
+            colors.red_below <= colors.green_above,
+            "display.relative_score_colors.red_below ({}) must not exceed green_above ({}).",
+            colors.red_below,
+            colors.green_above
+        );
+
+        let cpu_count = num_cpus::get();
+
+        if self.test.threads > cpu_count {
+            let message = format!(
+                "Warning: test.threads ({}) exceeds the number of CPUs ({cpu_count}). The execution time may be unstable.",
+                self.test.threads
+            );
+            eprintln!("{}", message.yellow());
+        }
+
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -57,7 +132,136 @@ pub(crate) struct General {
 pub(crate) struct Problem {
     pub(crate) problem_name: String,
     pub(crate) objective: Objective,
-    pub(crate) score_regex: String,
+    pub(crate) score_regex: ScoreRegex,
+    #[serde(default)]
+    pub(crate) score_aggregation: ScoreAggregation,
+    /// スコアを抽出する出力先（標準出力・標準エラー出力）
+    #[serde(default)]
+    pub(crate) score_source: ScoreSource,
+    #[serde(default)]
+    pub(crate) relative_score_mode: RelativeScoreMode,
+    #[serde(default)]
+    pub(crate) clamp_relative: bool,
+    /// スコアを探索する、各出力の末尾からの最大バイト数
+    pub(crate) score_scan_max_bytes: Option<usize>,
+    /// seedごとの重みを記載したファイルのパス（平均相対スコアの重み付けに用いる）
+    pub(crate) weights_file: Option<String>,
+}
+
+/// スコアを抽出する正規表現
+/// 文字列の場合は単一のパターン、配列の場合は名前付きの複数のパターン（サブスコア）として扱う
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum ScoreRegex {
+    Single(String),
+    Patterns(Vec<ScorePattern>),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct ScorePattern {
+    pub(crate) name: String,
+    pub(crate) regex: String,
+    /// 集計に用いるスコアか否か（ちょうど1つのパターンで `true` とする）
+    #[serde(default)]
+    pub(crate) primary: bool,
+    /// このスコアの最適化の方向（省略時は `problem.objective` ）
+    /// primaryのパターンでは集計に、それ以外ではサブスコアの比較に用いる
+    pub(crate) objective: Option<Objective>,
+}
+
+impl Problem {
+    /// 集計に用いるスコアの最適化の方向を返す
+    /// primaryのパターンに `objective` が指定されている場合はそちらを優先する
+    pub(crate) fn primary_objective(&self) -> Objective {
+        self.score_regex
+            .objective_of_primary()
+            .unwrap_or(self.objective)
+    }
+
+    /// primaryでないパターンの名前と、そのサブスコアの最適化の方向の組を返す
+    pub(crate) fn sub_score_objectives(&self) -> Vec<(String, Objective)> {
+        match &self.score_regex {
+            ScoreRegex::Single(_) => vec![],
+            ScoreRegex::Patterns(patterns) => patterns
+                .iter()
+                .filter(|p| !p.primary)
+                .map(|p| (p.name.clone(), p.objective.unwrap_or(self.objective)))
+                .collect(),
+        }
+    }
+}
+
+impl ScoreRegex {
+    /// 集計に用いるスコアのパターンを返す
+    pub(crate) fn primary(&self) -> &str {
+        match self {
+            ScoreRegex::Single(regex) => regex,
+            ScoreRegex::Patterns(patterns) => patterns
+                .iter()
+                .find(|p| p.primary)
+                .map(|p| p.regex.as_str())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// primaryのパターンに指定された最適化の方向を返す
+    fn objective_of_primary(&self) -> Option<Objective> {
+        match self {
+            ScoreRegex::Single(_) => None,
+            ScoreRegex::Patterns(patterns) => patterns
+                .iter()
+                .find(|p| p.primary)
+                .and_then(|p| p.objective),
+        }
+    }
+
+    /// サブスコアとして記録する名前とパターンの組を返す（primaryのパターンも含む）
+    pub(crate) fn sub_score_patterns(&self) -> Result<Vec<(String, Regex)>> {
+        match self {
+            ScoreRegex::Single(_) => Ok(vec![]),
+            ScoreRegex::Patterns(patterns) => patterns
+                .iter()
+                .map(|p| Ok((p.name.clone(), Regex::new(&p.regex)?)))
+                .collect(),
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        match self {
+            ScoreRegex::Single(regex) => Self::validate_regex(regex, "problem.score_regex"),
+            ScoreRegex::Patterns(patterns) => {
+                let primary_count = patterns.iter().filter(|p| p.primary).count();
+                ensure!(
+                    primary_count == 1,
+                    "Exactly one pattern in problem.score_regex must have `primary = true` (found {primary_count})."
+                );
+
+                for (i, pattern) in patterns.iter().enumerate() {
+                    ensure!(
+                        patterns[..i].iter().all(|p| p.name != pattern.name),
+                        "The name `{}` in problem.score_regex is duplicated.",
+                        pattern.name
+                    );
+                    Self::validate_regex(
+                        &pattern.regex,
+                        &format!("problem.score_regex ({})", pattern.name),
+                    )?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+
+    fn validate_regex(regex: &str, key: &str) -> Result<()> {
+        let compiled = Regex::new(regex).with_context(|| format!("{key} is invalid: {regex}"))?;
+        ensure!(
+            compiled.capture_names().any(|name| name == Some("score")),
+            "{key} must contain a named capture group `score` (e.g. `(?P<score>\\d+)`): {regex}"
+        );
+
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -66,11 +270,139 @@ pub(crate) struct Test {
     pub(crate) end_seed: u64,
     pub(crate) threads: usize,
     pub(crate) out_dir: String,
+    /// ベストスコアのファイルのパス（省略時は `out_dir` 内の `best_scores.json` ）
+    #[serde(default)]
+    pub(crate) best_score_file: Option<String>,
+    /// `{CASE}` プレースホルダーの展開形式（省略時は `{SEED04}` ）
+    #[serde(default)]
+    pub(crate) seed_format: Option<String>,
+    /// サマリファイルに残す実行結果の最大行数（省略時は無制限）
+    #[serde(default)]
+    pub(crate) summary_max_rows: Option<usize>,
     pub(crate) compile_steps: Vec<CompileStep>,
     pub(crate) test_steps: Vec<TestStep>,
 }
 
-pub(crate) fn gen_setting_file(args: &InitArgs) -> Result<()> {
+impl Test {
+    pub(crate) fn seed_format(&self) -> &str {
+        self.seed_format.as_deref().unwrap_or(DEFAULT_SEED_FORMAT)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(crate) struct Git {
+    /// 変更をコミットする際のメッセージのテンプレート
+    pub(crate) commit_message: Option<String>,
+    /// タグの注釈メッセージのテンプレート
+    pub(crate) tag_message: Option<String>,
+    /// タグを作成した際にリモートへプッシュするかどうか
+    #[serde(default)]
+    pub(crate) push_on_tag: bool,
+    /// タグのプッシュ先のリモート名
+    pub(crate) remote: Option<String>,
+    /// タグ名のプレフィックス（省略時は `pahcer/` ）
+    #[serde(default)]
+    pub(crate) tag_prefix: Option<String>,
+    /// タグを付ける際に未コミットの変更をコミットするかどうか
+    #[serde(default)]
+    pub(crate) mode: GitMode,
+}
+
+/// `pahcer run --tag` でのGitの操作方法
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) enum GitMode {
+    /// 未コミットの変更を一時的にコミットしてタグを付け、コミットを取り消す
+    #[default]
+    Full,
+    /// 現在のHEADにタグを付けるのみとし、コミットは行わない
+    TagOnly,
+}
+
+impl Git {
+    pub(crate) fn tag_prefix(&self) -> &str {
+        self.tag_prefix.as_deref().unwrap_or(DEFAULT_TAG_PREFIX)
+    }
+
+    /// タグ名にプレフィックスを付ける
+    pub(crate) fn prefixed_tag(&self, tag: &str) -> String {
+        format!("{}{tag}", self.tag_prefix())
+    }
+
+    /// 表示用にタグ名からプレフィックスを取り除く
+    pub(crate) fn strip_tag_prefix<'a>(&self, tag_name: &'a str) -> &'a str {
+        tag_name.strip_prefix(self.tag_prefix()).unwrap_or(tag_name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub(crate) struct DisplaySettings {
+    /// 表やサマリの数値を3桁ごとにカンマで区切るかどうか
+    #[serde(default = "default_group_digits")]
+    pub(crate) group_digits: bool,
+    /// サマリの平均スコアの小数点以下の桁数
+    #[serde(default = "default_score_decimals")]
+    pub(crate) score_decimals: usize,
+    /// 表の相対スコアを色付けする閾値
+    #[serde(default)]
+    pub(crate) relative_score_colors: RelativeScoreColors,
+}
+
+impl Default for DisplaySettings {
+    fn default() -> Self {
+        Self {
+            group_digits: default_group_digits(),
+            score_decimals: default_score_decimals(),
+            relative_score_colors: RelativeScoreColors::default(),
+        }
+    }
+}
+
+impl DisplaySettings {
+    /// 数値の表示形式を返す
+    pub(crate) const fn number_format(&self) -> NumberFormat {
+        NumberFormat {
+            group_digits: self.group_digits,
+            score_decimals: self.score_decimals,
+        }
+    }
+}
+
+const fn default_group_digits() -> bool {
+    true
+}
+
+const fn default_score_decimals() -> usize {
+    DEFAULT_SCORE_DECIMALS
+}
+
+/// 相対スコアの色付けの閾値
+/// `green_above` より大きければ緑、 `red_below` 未満なら赤、それ以外は黄色で表示する
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub(crate) struct RelativeScoreColors {
+    #[serde(default = "default_green_above")]
+    pub(crate) green_above: f64,
+    #[serde(default = "default_red_below")]
+    pub(crate) red_below: f64,
+}
+
+impl Default for RelativeScoreColors {
+    fn default() -> Self {
+        Self {
+            green_above: default_green_above(),
+            red_below: default_red_below(),
+        }
+    }
+}
+
+const fn default_green_above() -> f64 {
+    100.0
+}
+
+const fn default_red_below() -> f64 {
+    90.0
+}
+
+pub fn gen_setting_file(args: &InitArgs) -> Result<()> {
     let mut writer = BufWriter::new(std::fs::File::create_new(SETTING_FILE_PATH).context(
         "Failed to create the setting file. Ensure that ./pahcer_config.toml does not exist.",
     )?);
@@ -103,6 +435,10 @@ fn get_run_step_settings(args: &InitArgs) -> &str {
         (Lang::Python, false) => include_str!("./settings/linux/python.toml"),
         (Lang::Go, true) => include_str!("./settings/linux/go_interactive.toml"),
         (Lang::Go, false) => include_str!("./settings/linux/go.toml"),
+        (Lang::Java, true) => include_str!("./settings/linux/java_interactive.toml"),
+        (Lang::Java, false) => include_str!("./settings/linux/java.toml"),
+        (Lang::Kotlin, true) => include_str!("./settings/linux/kotlin_interactive.toml"),
+        (Lang::Kotlin, false) => include_str!("./settings/linux/kotlin.toml"),
     }
 }
 
@@ -117,6 +453,10 @@ fn get_run_step_settings(args: &InitArgs) -> &str {
         (Lang::Python, false) => include_str!("./settings/macos/python.toml"),
         (Lang::Go, true) => include_str!("./settings/macos/go_interactive.toml"),
         (Lang::Go, false) => include_str!("./settings/macos/go.toml"),
+        (Lang::Java, true) => include_str!("./settings/macos/java_interactive.toml"),
+        (Lang::Java, false) => include_str!("./settings/macos/java.toml"),
+        (Lang::Kotlin, true) => include_str!("./settings/macos/kotlin_interactive.toml"),
+        (Lang::Kotlin, false) => include_str!("./settings/macos/kotlin.toml"),
     }
 }
 
@@ -131,6 +471,10 @@ fn get_run_step_settings(args: &InitArgs) -> &str {
         (Lang::Python, false) => include_str!("./settings/windows/python.toml"),
         (Lang::Go, true) => include_str!("./settings/windows/go_interactive.toml"),
         (Lang::Go, false) => include_str!("./settings/windows/go.toml"),
+        (Lang::Java, true) => include_str!("./settings/windows/java_interactive.toml"),
+        (Lang::Java, false) => include_str!("./settings/windows/java.toml"),
+        (Lang::Kotlin, true) => include_str!("./settings/windows/kotlin_interactive.toml"),
+        (Lang::Kotlin, false) => include_str!("./settings/windows/kotlin.toml"),
     }
 }
 
@@ -149,3 +493,166 @@ fn gen_gitignore(dir: impl AsRef<OsStr>) -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn gen_settings(score_regex: &str, start_seed: u64, end_seed: u64) -> Settings {
+        let toml = format!(
+            r#"
+[general]
+version = "0.0.0"
+
+[problem]
+problem_name = "test"
+objective = "Max"
+score_regex = '{score_regex}'
+
+[test]
+start_seed = {start_seed}
+end_seed = {end_seed}
+threads = 0
+out_dir = "./pahcer"
+compile_steps = []
+
+[[test.test_steps]]
+program = "./a.out"
+args = []
+measure_time = true
+"#
+        );
+        toml::from_str(&toml).unwrap()
+    }
+
+    #[test]
+    fn test_validate() {
+        let regex = r"^Score = (?P<score>\d+)$";
+        assert!(gen_settings(regex, 0, 100).validate().is_ok());
+        assert!(gen_settings(regex, 100, 100).validate().is_err());
+        assert!(gen_settings(r"^Score = (\d+)$", 0, 100).validate().is_err());
+        assert!(gen_settings(r"^Score = (?P<score>\d+$", 0, 100)
+            .validate()
+            .is_err());
+
+        let mut settings = gen_settings(regex, 0, 100);
+        settings.test.test_steps.clear();
+        assert!(settings.validate().is_err());
+
+        let mut settings = gen_settings(regex, 0, 100);
+        settings.test.seed_format = Some("case_{SEED:05x}".to_string());
+        assert!(settings.validate().is_ok());
+        settings.test.seed_format = Some("case".to_string());
+        assert!(settings.validate().is_err());
+    }
+
+    #[test]
+    fn test_tag_prefix() {
+        let mut settings = gen_settings(r"^Score = (?P<score>\d+)$", 0, 100);
+        assert_eq!(settings.git.prefixed_tag("foo"), "pahcer/foo");
+
+        settings.git.tag_prefix = Some("exp/alice/".to_string());
+        assert!(settings.validate().is_ok());
+        assert_eq!(settings.git.prefixed_tag("foo"), "exp/alice/foo");
+        assert_eq!(settings.git.strip_tag_prefix("exp/alice/foo"), "foo");
+        assert_eq!(settings.git.strip_tag_prefix("pahcer/foo"), "pahcer/foo");
+
+        settings.git.tag_prefix = Some(String::new());
+        assert!(settings.validate().is_err());
+    }
+
+    #[test]
+    fn test_score_regex_patterns() {
+        let pattern = |name: &str, primary: bool| ScorePattern {
+            name: name.to_string(),
+            regex: format!(r"^{name} = (?P<score>\d+)$"),
+            primary,
+            objective: None,
+        };
+
+        let mut settings = gen_settings(r"^Score = (?P<score>\d+)$", 0, 100);
+        settings.problem.score_regex =
+            ScoreRegex::Patterns(vec![pattern("Placement", true), pattern("Penalty", false)]);
+        assert!(settings.validate().is_ok());
+        assert_eq!(
+            settings.problem.score_regex.primary(),
+            r"^Placement = (?P<score>\d+)$"
+        );
+
+        let names = settings
+            .problem
+            .score_regex
+            .sub_score_patterns()
+            .unwrap()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["Placement", "Penalty"]);
+
+        // primaryが存在しない・複数ある・名前が重複している場合はエラー
+        settings.problem.score_regex =
+            ScoreRegex::Patterns(vec![pattern("Placement", false), pattern("Penalty", false)]);
+        assert!(settings.validate().is_err());
+        settings.problem.score_regex =
+            ScoreRegex::Patterns(vec![pattern("Placement", true), pattern("Penalty", true)]);
+        assert!(settings.validate().is_err());
+        settings.problem.score_regex = ScoreRegex::Patterns(vec![
+            pattern("Placement", true),
+            pattern("Placement", false),
+        ]);
+        assert!(settings.validate().is_err());
+    }
+
+    #[test]
+    fn test_primary_objective() {
+        let pattern = |name: &str, primary: bool, objective: Option<Objective>| ScorePattern {
+            name: name.to_string(),
+            regex: format!(r"^{name} = (?P<score>\d+)$"),
+            primary,
+            objective,
+        };
+
+        let mut settings = gen_settings(r"^Score = (?P<score>\d+)$", 0, 100);
+        settings.problem.objective = Objective::Max;
+        assert!(matches!(
+            settings.problem.primary_objective(),
+            Objective::Max
+        ));
+
+        // primaryのパターンの objective が優先される
+        settings.problem.score_regex = ScoreRegex::Patterns(vec![
+            pattern("Cost", true, Some(Objective::Min)),
+            pattern("Bonus", false, None),
+        ]);
+        assert!(settings.validate().is_ok());
+        assert!(matches!(
+            settings.problem.primary_objective(),
+            Objective::Min
+        ));
+
+        settings.problem.score_regex = ScoreRegex::Patterns(vec![
+            pattern("Cost", true, None),
+            pattern("Bonus", false, None),
+        ]);
+        assert!(matches!(
+            settings.problem.primary_objective(),
+            Objective::Max
+        ));
+
+        // primaryでないパターンの objective はサブスコアの比較に用いる
+        settings.problem.score_regex = ScoreRegex::Patterns(vec![
+            pattern("Cost", true, None),
+            pattern("Bonus", false, Some(Objective::Min)),
+            pattern("Penalty", false, None),
+        ]);
+        assert!(settings.validate().is_ok());
+        assert!(matches!(
+            settings.problem.primary_objective(),
+            Objective::Max
+        ));
+        assert!(matches!(
+            settings.problem.sub_score_objectives()[..],
+            [(_, Objective::Min), (_, Objective::Max)]
+        ));
+    }
+}
@@ -1,38 +1,192 @@
-use std::num::NonZeroUsize;
+use std::{
+    collections::HashMap,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+};
 
-use num_format::{Locale, ToFormattedString as _};
+use num_format::{Locale, ToFormattedString};
+use serde::{Deserialize, Deserializer, Serializer};
 
-/// 浮動小数点数 `x` を、整数部を3桁区切りしつつ小数点以下を `decimals` 桁に丸めて文字列化します。
-/// 負の0 (`-0.0`) を含む負数でも符号を正しく付加し、大きな整数部も `i64` の範囲で処理します。
-pub(crate) fn format_float_with_commas(x: f64, decimals: NonZeroUsize) -> String {
-    // 桁数（>= 1）
-    let decimals = decimals.get();
+/// スコアの表示に用いる小数点以下の最大桁数
+const MAX_SCORE_DECIMALS: usize = 6;
 
-    // 符号を保持
-    let is_negative = x.is_sign_negative();
+/// 平均スコアの表示に用いる小数点以下の桁数のデフォルト値
+pub(crate) const DEFAULT_SCORE_DECIMALS: usize = 2;
 
-    // 絶対値を指定桁数で文字列化
-    // ここで decimals は必ず 1 以上
-    let abs_str = format!("{:.*}", decimals, x.abs());
+/// 数値の表示形式（設定ファイルの `display` から作成します）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct NumberFormat {
+    /// 数値を3桁ごとに区切って表示するか
+    pub(crate) group_digits: bool,
+    /// 平均スコアの小数点以下の桁数
+    pub(crate) score_decimals: usize,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        Self {
+            group_digits: true,
+            score_decimals: DEFAULT_SCORE_DECIMALS,
+        }
+    }
+}
+
+impl NumberFormat {
+    /// 平均スコアを `score_decimals` の桁数に丸めて3桁区切りで文字列化します。
+    pub(crate) fn average_score(&self, x: f64) -> String {
+        match NonZeroUsize::new(self.score_decimals) {
+            Some(decimals) => self.float(x, decimals),
+            None => self.integer(x.round() as i64),
+        }
+    }
+
+    /// 整数 `x` を3桁区切りで文字列化します（ `group_digits` が無効の場合は区切りません）。
+    pub(crate) fn integer<T: ToFormattedString + ToString>(&self, x: T) -> String {
+        if self.group_digits {
+            x.to_formatted_string(&Locale::en)
+        } else {
+            x.to_string()
+        }
+    }
+
+    /// 浮動小数点数 `x` を、整数部を3桁区切りしつつ小数点以下を `decimals` 桁に丸めて文字列化します。
+    /// 負の0 (`-0.0`) を含む負数でも符号を正しく付加し、大きな整数部も `i64` の範囲で処理します。
+    pub(crate) fn float(&self, x: f64, decimals: NonZeroUsize) -> String {
+        // 桁数（>= 1）
+        let decimals = decimals.get();
 
-    // 小数点で分割（decimals >= 1 なので必ず小数点は存在する）
-    let (int_part, frac_part) = abs_str.split_once('.').unwrap();
+        // 符号を保持
+        let is_negative = x.is_sign_negative();
 
-    // 整数部を i64 にパースしてカンマ区切り
-    // （非常に大きい場合は BigInt などを検討）
-    let int_formatted = int_part
-        .parse::<i64>()
-        .unwrap()
-        .to_formatted_string(&Locale::en);
+        // 絶対値を指定桁数で文字列化
+        // ここで decimals は必ず 1 以上
+        let abs_str = format!("{:.*}", decimals, x.abs());
 
-    // 整数部と小数部を再連結
-    let result = format!("{int_formatted}.{frac_part}");
+        // 小数点で分割（decimals >= 1 なので必ず小数点は存在する）
+        let (int_part, frac_part) = abs_str.split_once('.').unwrap();
+
+        // 整数部を i64 にパースしてカンマ区切り
+        // （非常に大きい場合は BigInt などを検討）
+        let int_formatted = self.integer(int_part.parse::<i64>().unwrap());
+
+        // 整数部と小数部を再連結
+        let result = format!("{int_formatted}.{frac_part}");
+
+        // 負数なら符号を付けて返す
+        if is_negative {
+            format!("-{result}")
+        } else {
+            result
+        }
+    }
+
+    /// スコア `x` を3桁区切りで文字列化します。
+    /// 整数値の場合は小数点以下を省略し、そうでない場合は末尾の0を除いて最大6桁まで表示します。
+    pub(crate) fn score(&self, x: f64) -> String {
+        let abs_str = format!("{:.*}", MAX_SCORE_DECIMALS, x.abs());
+        let decimals = abs_str
+            .trim_end_matches('0')
+            .split_once('.')
+            .unwrap()
+            .1
+            .len();
+
+        match NonZeroUsize::new(decimals) {
+            Some(decimals) => self.float(x, decimals),
+            None => self.integer(x.round() as i64),
+        }
+    }
+}
 
-    // 負数なら符号を付けて返す
-    if is_negative {
-        format!("-{result}")
+/// スコアをJSONの数値としてシリアライズします。
+/// 整数値の場合は従来のフォーマットとの互換性を保つため整数として出力します。
+pub(crate) fn serialize_score<S: Serializer>(
+    score: &f64,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    if score.fract() == 0.0 && (0.0..u64::MAX as f64).contains(score) {
+        serializer.serialize_u64(*score as u64)
     } else {
-        result
+        serializer.serialize_f64(*score)
+    }
+}
+
+/// プラットフォームの指定がない場合に用いるキー
+const DEFAULT_PLATFORM_KEY: &str = "default";
+
+/// 実行するプログラムの指定。文字列か、OSごとのプログラムのテーブル
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ProgramSpec {
+    Single(String),
+    PerPlatform(HashMap<String, String>),
+}
+
+/// 実行するプログラムをデシリアライズします。
+/// `{ windows = "a.exe", default = "./a.out" }` のようにOSごとに指定された場合は、
+/// 実行中のOS（ `std::env::consts::OS` ）に対応するものを、なければ `default` のものを返します。
+pub(crate) fn deserialize_program<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<String, D::Error> {
+    match ProgramSpec::deserialize(deserializer)? {
+        ProgramSpec::Single(program) => Ok(program),
+        ProgramSpec::PerPlatform(programs) => resolve_program(&programs, std::env::consts::OS)
+            .ok_or_else(|| {
+                serde::de::Error::custom(format!(
+                    "program for `{}` is not specified. Add `{}` or `{DEFAULT_PLATFORM_KEY}` to the table.",
+                    std::env::consts::OS,
+                    std::env::consts::OS
+                ))
+            }),
+    }
+}
+
+fn resolve_program(programs: &HashMap<String, String>, os: &str) -> Option<String> {
+    programs
+        .get(os)
+        .or_else(|| programs.get(DEFAULT_PLATFORM_KEY))
+        .cloned()
+}
+
+/// コマンドを表示用に文字列化します。空白を含む引数や空の引数は引用符で囲みます。
+pub(crate) fn format_command<S: AsRef<str>>(program: &str, args: &[S]) -> String {
+    let mut command = program.to_string();
+
+    for arg in args {
+        let arg = arg.as_ref();
+        command.push(' ');
+
+        if arg.is_empty() || arg.contains(char::is_whitespace) || arg.contains('"') {
+            command.push_str(&format!("{arg:?}"));
+        } else {
+            command.push_str(arg);
+        }
+    }
+
+    command
+}
+
+/// カレントディレクトリを一時的に変更する。破棄時に元のディレクトリに戻す
+pub(crate) struct CurrentDirGuard {
+    original: PathBuf,
+}
+
+impl CurrentDirGuard {
+    pub(crate) fn new(dir: &Path) -> std::io::Result<Self> {
+        let original = std::env::current_dir()?;
+        std::env::set_current_dir(dir)?;
+        Ok(Self { original })
+    }
+}
+
+impl Drop for CurrentDirGuard {
+    fn drop(&mut self) {
+        if let Err(e) = std::env::set_current_dir(&self.original) {
+            eprintln!(
+                "Failed to restore the current directory {}: {e}",
+                self.original.display()
+            );
+        }
     }
 }
 
@@ -40,30 +194,83 @@ pub(crate) fn format_float_with_commas(x: f64, decimals: NonZeroUsize) -> String
 mod test {
     use super::*;
 
+    #[test]
+    fn test_format_integer() {
+        let ungrouped = NumberFormat {
+            group_digits: false,
+            ..Default::default()
+        };
+        assert_eq!(NumberFormat::default().integer(1234567), "1,234,567");
+        assert_eq!(ungrouped.integer(-1234567_i64), "-1234567");
+        assert_eq!(NumberFormat::default().integer(123_u128), "123");
+    }
+
+    #[test]
+    fn test_format_average_score() {
+        let format = |score_decimals| NumberFormat {
+            score_decimals,
+            ..Default::default()
+        };
+        assert_eq!(format(2).average_score(1234.5678), "1,234.57");
+        assert_eq!(format(5).average_score(0.0123456), "0.01235");
+        assert_eq!(format(0).average_score(1234.5678), "1,235");
+    }
+
+    #[test]
+    fn test_resolve_program() {
+        let programs = HashMap::from([
+            ("windows".to_string(), "a.exe".to_string()),
+            ("default".to_string(), "./a.out".to_string()),
+        ]);
+        assert_eq!(
+            resolve_program(&programs, "windows").as_deref(),
+            Some("a.exe")
+        );
+        assert_eq!(
+            resolve_program(&programs, "linux").as_deref(),
+            Some("./a.out")
+        );
+
+        let programs = HashMap::from([("windows".to_string(), "a.exe".to_string())]);
+        assert_eq!(resolve_program(&programs, "macos"), None);
+    }
+
+    #[test]
+    fn test_format_command() {
+        assert_eq!(format_command::<&str>("./a.out", &[]), "./a.out");
+        assert_eq!(
+            format_command("sh", &["-c", "echo 1", ""]),
+            r#"sh -c "echo 1" """#
+        );
+    }
+
     #[test]
     fn test_format_float_with_commas_basic() {
         let decimals1 = NonZeroUsize::new(1).unwrap();
         let decimals3 = NonZeroUsize::new(3).unwrap();
 
         // 正の数, 小数点以下1桁
-        assert_eq!(format_float_with_commas(12345.6789, decimals1), "12,345.7");
+        assert_eq!(
+            NumberFormat::default().float(12345.6789, decimals1),
+            "12,345.7"
+        );
         // 負の数, 小数点以下1桁
-        assert_eq!(format_float_with_commas(-0.1, decimals1), "-0.1");
+        assert_eq!(NumberFormat::default().float(-0.1, decimals1), "-0.1");
 
         // 正の数, 小数点以下3桁 (繰り上がりが発生)
         // 12,345.6789 → 12,345.679
         assert_eq!(
-            format_float_with_commas(12345.6789, decimals3),
+            NumberFormat::default().float(12345.6789, decimals3),
             "12,345.679"
         );
 
         // 負の数, 小数点以下3桁, 非常に小さい値
         // -0.0004 → -0.000 (丸め)
-        assert_eq!(format_float_with_commas(-0.0004, decimals3), "-0.000");
+        assert_eq!(NumberFormat::default().float(-0.0004, decimals3), "-0.000");
 
         // 負の0 (is_sign_negative が true となる -0.0)
         // 小数点以下3桁 → -0.000
-        assert_eq!(format_float_with_commas(-0.0, decimals3), "-0.000");
+        assert_eq!(NumberFormat::default().float(-0.0, decimals3), "-0.000");
     }
 
     #[test]
@@ -72,14 +279,31 @@ mod test {
 
         // 非常に大きい数で繰り上がりあり (999,999,999.9999 → 1,000,000,000.000)
         assert_eq!(
-            format_float_with_commas(999999999.9999, decimals3),
+            NumberFormat::default().float(999999999.9999, decimals3),
             "1,000,000,000.000"
         );
 
         // 10桁以上の数
         assert_eq!(
-            format_float_with_commas(1234567890123.001, decimals3),
+            NumberFormat::default().float(1234567890123.001, decimals3),
             "1,234,567,890,123.001"
         );
     }
+
+    #[test]
+    fn test_format_score_with_commas() {
+        assert_eq!(NumberFormat::default().score(0.0), "0");
+        assert_eq!(NumberFormat::default().score(1234567.0), "1,234,567");
+        assert_eq!(NumberFormat::default().score(12345.678), "12,345.678");
+        assert_eq!(NumberFormat::default().score(0.5), "0.5");
+    }
+
+    #[test]
+    fn test_serialize_score() {
+        #[derive(serde::Serialize)]
+        struct Wrapper(#[serde(serialize_with = "serialize_score")] f64);
+
+        assert_eq!(serde_json::to_string(&Wrapper(1234.0)).unwrap(), "1234");
+        assert_eq!(serde_json::to_string(&Wrapper(12.5)).unwrap(), "12.5");
+    }
 }